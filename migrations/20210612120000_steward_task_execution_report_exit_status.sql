@@ -0,0 +1,4 @@
+ALTER TABLE steward_task_execution_report
+    ADD COLUMN IF NOT EXISTS exit_code integer,
+    ADD COLUMN IF NOT EXISTS signal integer,
+    ADD COLUMN IF NOT EXISTS container_status bigint;
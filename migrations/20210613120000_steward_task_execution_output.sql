@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS steward_task_execution_output (
+    report_id uuid NOT NULL REFERENCES steward_task_execution_report (id) ON DELETE CASCADE,
+    sequence bigint NOT NULL,
+    stream varchar(10) NOT NULL,
+    timestamp timestamp NOT NULL,
+    line text NOT NULL,
+    PRIMARY KEY (report_id, sequence)
+);
+
+-- Move the old joined outputs into the new table, line by line
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_name = 'steward_task_execution_report' AND column_name = 'output'
+    ) THEN
+        INSERT INTO steward_task_execution_output ( report_id, sequence, stream, timestamp, line )
+            SELECT report.id, lines.sequence - 1, 'stdout', report.created_at, lines.line
+            FROM steward_task_execution_report report,
+                regexp_split_to_table(report.output, E'\n') WITH ORDINALITY AS lines(line, sequence);
+        ALTER TABLE steward_task_execution_report DROP COLUMN output;
+    END IF;
+END $$;
@@ -0,0 +1,2 @@
+ALTER TABLE steward_tasks
+    ADD COLUMN IF NOT EXISTS timeout bigint;
@@ -0,0 +1,6 @@
+ALTER TABLE steward_tasks
+    ADD COLUMN IF NOT EXISTS retry_policy varchar;
+
+ALTER TABLE steward_task_execution_report
+    ADD COLUMN IF NOT EXISTS attempt integer DEFAULT 1 NOT NULL,
+    ADD COLUMN IF NOT EXISTS original_id uuid;
@@ -0,0 +1,2 @@
+ALTER TABLE steward_tasks
+    ADD COLUMN IF NOT EXISTS overlap_policy varchar(10) DEFAULT 'Allow' NOT NULL;
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS steward_task_executions (
+    id uuid NOT NULL,
+    task_id uuid NOT NULL REFERENCES steward_tasks (id) ON DELETE CASCADE,
+    state varchar(10) NOT NULL,
+    attempt int DEFAULT 1 NOT NULL,
+    queued_at timestamp NOT NULL,
+    starting_at timestamp,
+    running_at timestamp,
+    finished_at timestamp,
+    updated_at timestamp NOT NULL,
+    PRIMARY KEY (id)
+);
+CREATE INDEX IF NOT EXISTS steward_task_executions_state ON steward_task_executions (state);
@@ -0,0 +1,3 @@
+ALTER TABLE steward_task_executions
+    ADD COLUMN IF NOT EXISTS pid int,
+    ADD COLUMN IF NOT EXISTS container_id varchar(64);
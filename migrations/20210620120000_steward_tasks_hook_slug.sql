@@ -0,0 +1,5 @@
+ALTER TABLE steward_tasks
+    ADD COLUMN IF NOT EXISTS hook_slug text,
+    ADD COLUMN IF NOT EXISTS hook_method varchar(10) DEFAULT 'POST' NOT NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS steward_tasks_hook_slug ON steward_tasks (hook_slug);
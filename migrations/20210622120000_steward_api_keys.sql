@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS steward_api_keys (
+    id uuid NOT NULL,
+    name text NOT NULL,
+    key_hash varchar(64) NOT NULL UNIQUE,
+    key_prefix varchar(16) NOT NULL,
+    scopes text[] NOT NULL,
+    created_at timestamp NOT NULL,
+    last_used_at timestamp,
+    revoked_at timestamp,
+    PRIMARY KEY (id)
+);
@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS steward_users (
+    id uuid NOT NULL,
+    username varchar(64) NOT NULL UNIQUE,
+    password_hash text NOT NULL,
+    role varchar(10) NOT NULL,
+    created_at timestamp NOT NULL,
+    updated_at timestamp NOT NULL,
+    PRIMARY KEY (id)
+);
+
+CREATE TABLE IF NOT EXISTS steward_sessions (
+    id uuid NOT NULL,
+    user_id uuid NOT NULL REFERENCES steward_users (id) ON DELETE CASCADE,
+    token_hash varchar(64) NOT NULL UNIQUE,
+    csrf_token varchar(64) NOT NULL,
+    created_at timestamp NOT NULL,
+    expires_at timestamp NOT NULL,
+    PRIMARY KEY (id)
+);
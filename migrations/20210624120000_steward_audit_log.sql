@@ -0,0 +1,31 @@
+CREATE TABLE IF NOT EXISTS steward_audit_log (
+    id uuid NOT NULL,
+    created_at timestamp NOT NULL,
+    actor_type varchar(10) NOT NULL,
+    actor_id uuid,
+    actor_name text NOT NULL,
+    source_ip varchar(45),
+    request text NOT NULL,
+    action varchar(20) NOT NULL,
+    target_type varchar(10) NOT NULL,
+    target_id uuid,
+    execution_id uuid,
+    summary text NOT NULL,
+    diff text,
+    PRIMARY KEY (id)
+);
+
+CREATE INDEX IF NOT EXISTS steward_audit_log_created_at ON steward_audit_log (created_at DESC);
+CREATE INDEX IF NOT EXISTS steward_audit_log_target_id ON steward_audit_log (target_id);
+
+-- Entries can't be changed or removed once they're written
+CREATE OR REPLACE FUNCTION steward_audit_log_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'steward_audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS steward_audit_log_append_only ON steward_audit_log;
+CREATE TRIGGER steward_audit_log_append_only
+    BEFORE UPDATE OR DELETE OR TRUNCATE ON steward_audit_log
+    FOR EACH STATEMENT EXECUTE PROCEDURE steward_audit_log_append_only();
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS steward_secrets (
+    id uuid NOT NULL,
+    name varchar(64) NOT NULL UNIQUE,
+    -- AES-256-GCM with the master key, the name is bound to the ciphertext
+    nonce bytea NOT NULL,
+    ciphertext bytea NOT NULL,
+    created_at timestamp NOT NULL,
+    updated_at timestamp NOT NULL,
+    PRIMARY KEY (id)
+);
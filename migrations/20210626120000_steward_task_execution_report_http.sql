@@ -0,0 +1,3 @@
+ALTER TABLE steward_task_execution_report
+    ADD COLUMN IF NOT EXISTS http_status integer,
+    ADD COLUMN IF NOT EXISTS http_headers text;
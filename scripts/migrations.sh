@@ -1,3 +1,18 @@
 psql $STEWARDX_DATABASE_URL -f ../migrations/20200723212810_steward_tasks.sql
 psql $STEWARDX_DATABASE_URL -f ../migrations/20200723212830_steward_task_errors.sql
-psql $STEWARDX_DATABASE_URL -f ../migrations/20200723212850_steward_task_execution_report.sql
\ No newline at end of file
+psql $STEWARDX_DATABASE_URL -f ../migrations/20200723212850_steward_task_execution_report.sql
+psql $STEWARDX_DATABASE_URL -f ../migrations/20210612120000_steward_task_execution_report_exit_status.sql
+psql $STEWARDX_DATABASE_URL -f ../migrations/20210613120000_steward_task_execution_output.sql
+psql $STEWARDX_DATABASE_URL -f ../migrations/20210614120000_steward_tasks_timeout.sql
+psql $STEWARDX_DATABASE_URL -f ../migrations/20210615120000_steward_retries.sql
+psql $STEWARDX_DATABASE_URL -f ../migrations/20210616120000_steward_tasks_overlap_policy.sql
+psql $STEWARDX_DATABASE_URL -f ../migrations/20210617120000_steward_task_executions.sql
+psql $STEWARDX_DATABASE_URL -f ../migrations/20210618120000_steward_task_executions_process.sql
+psql $STEWARDX_DATABASE_URL -f ../migrations/20210619120000_steward_tasks_hook_secret.sql
+psql $STEWARDX_DATABASE_URL -f ../migrations/20210620120000_steward_tasks_hook_slug.sql
+psql $STEWARDX_DATABASE_URL -f ../migrations/20210621120000_steward_tasks_hook_filters.sql
+psql $STEWARDX_DATABASE_URL -f ../migrations/20210622120000_steward_api_keys.sql
+psql $STEWARDX_DATABASE_URL -f ../migrations/20210623120000_steward_users.sql
+psql $STEWARDX_DATABASE_URL -f ../migrations/20210624120000_steward_audit_log.sql
+psql $STEWARDX_DATABASE_URL -f ../migrations/20210625120000_steward_secrets.sql
+psql $STEWARDX_DATABASE_URL -f ../migrations/20210626120000_steward_task_execution_report_http.sql
@@ -5,7 +5,59 @@ pub struct Config {
     pub index_file_path: String,
     pub logs_folder_path: String,
     pub panel_feature: bool,
-    pub server_crud_feature: bool
+    pub server_crud_feature: bool,
+    // In seconds, applied to the tasks that don't have their own timeout
+    #[serde(default)]
+    pub default_timeout: Option<u64>,
+    // In seconds, how long running tasks are waited for before they're aborted on shutdown
+    #[serde(default = "Config::default_shutdown_grace_period")]
+    pub shutdown_grace_period: u64,
+    // In bytes, bigger hook payloads are rejected
+    #[serde(default = "Config::default_hook_payload_limit")]
+    pub hook_payload_limit: usize,
+    // Content types hook payloads can have, any is allowed when it's empty
+    #[serde(default = "Config::default_hook_content_types")]
+    pub hook_content_types: Vec<String>,
+    // Headers of the hook request that are passed to the task
+    #[serde(default = "Config::default_hook_headers")]
+    pub hook_headers: Vec<String>,
+    // In seconds, how long a login to the panel lasts
+    #[serde(default = "Config::default_session_lifetime")]
+    pub session_lifetime: u64,
+    // Server speaks HTTPS when it's set
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    // File with the master key of the secrets, STEWARDX_SECRETS_KEY is used instead when it's set
+    #[serde(default)]
+    pub secrets_key_path: Option<String>,
+    // Regexes masked in the output of the tasks along with the secrets, only the "value" group if a pattern has it
+    #[serde(default = "Config::default_redact_patterns")]
+    pub redact_patterns: Vec<String>,
+    // Hosts that callback urls can point to, any host with a public address when it's empty
+    #[serde(default)]
+    pub callback_hosts: Vec<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TlsConfig {
+    // PEM files, relative paths are relative to the config directory
+    pub cert_path: String,
+    pub key_path: String,
+    // Client certificates are verified against the CAs in this file when it's set
+    #[serde(default)]
+    pub client_ca_path: Option<String>,
+    // Otherwise clients without a certificate are let in too, like the browsers of the panel
+    #[serde(default)]
+    pub client_cert_required: bool,
+    // In seconds, how often the files are checked for changes
+    #[serde(default = "TlsConfig::default_reload_interval")]
+    pub reload_interval: u64,
+}
+
+impl TlsConfig {
+    fn default_reload_interval() -> u64 {
+        30
+    }
 }
 
 impl Default for Config {
@@ -21,9 +73,58 @@ impl Config {
             index_file_path,
             logs_folder_path,
             panel_feature,
-            server_crud_feature
+            server_crud_feature,
+            default_timeout: None,
+            shutdown_grace_period: Self::default_shutdown_grace_period(),
+            hook_payload_limit: Self::default_hook_payload_limit(),
+            hook_content_types: Self::default_hook_content_types(),
+            hook_headers: Self::default_hook_headers(),
+            session_lifetime: Self::default_session_lifetime(),
+            tls: None,
+            secrets_key_path: None,
+            redact_patterns: Self::default_redact_patterns(),
+            callback_hosts: Vec::default(),
         }
     }
+    fn default_shutdown_grace_period() -> u64 {
+        30
+    }
+    fn default_hook_payload_limit() -> usize {
+        1024 * 1024
+    }
+    fn default_hook_content_types() -> Vec<String> {
+        vec!["application/json", "application/x-www-form-urlencoded", "text/plain"]
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+    fn default_hook_headers() -> Vec<String> {
+        vec![
+            "Content-Type",
+            "User-Agent",
+            "X-Request-Id",
+            "X-GitHub-Event",
+            "X-GitHub-Delivery",
+            "X-Gitlab-Event",
+            "X-Gitea-Event",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect()
+    }
+    fn default_session_lifetime() -> u64 {
+        12 * 60 * 60
+    }
+    fn default_redact_patterns() -> Vec<String> {
+        vec![
+            r"(?i)\bbearer\s+(?P<value>[a-z0-9._~+/-]+=*)",
+            // API keys of StewardX
+            r"\bsx_[0-9a-f]{64}\b",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect()
+    }
     fn create_config_directories() -> PathBuf {
         let path = Self::get_default_config_dir();
         match std::fs::create_dir_all(&path) {
@@ -76,7 +177,7 @@ impl Config {
                 dir_path
             }
         };
-        return match std::fs::read_to_string(&config_path) {
+        let config = match std::fs::read_to_string(&config_path) {
             Ok(o) => serde_json::from_str::<Self>(&o).expect("Malformed config file!"),
             Err(_e) => {
                 tracing::info!("Creating config file with defaults.");
@@ -86,6 +187,19 @@ impl Config {
                 c
             }
         };
+        if let Err(e) = config.check_build() {
+            panic!("{}", e);
+        }
+        return config;
+    }
+    // Settings this build can't honour are refused before anything is started
+    fn check_build(&self) -> Result<(), String> {
+        if self.tls.is_some() && cfg!(not(feature = "tls")) {
+            return Err(String::from(
+                "TLS is configured, but StewardX is built without the tls feature. Remove \"tls\" from the config or build it with the tls feature.",
+            ));
+        }
+        return Ok(());
     }
     pub fn get_index_file_path(&self) -> PathBuf {
         let index_path_string = &self.index_file_path;
@@ -107,6 +221,14 @@ impl Config {
         let index_path = config_dir;
         return index_path;
     }
+    pub fn resolve_path(path: &str) -> PathBuf {
+        if path.starts_with("/") {
+            return PathBuf::from_str(path).unwrap();
+        }
+        let mut config_dir = Self::get_config_dir();
+        config_dir.extend(path.split("/"));
+        return config_dir;
+    }
     pub fn get_features<'a>(&'a self) -> HashMap<&'a str, bool> {
         let mut features = HashMap::new();
         features.insert("panel", self.panel_feature);
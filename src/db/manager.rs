@@ -1,12 +1,15 @@
-use std::vec;
+use std::{collections::HashMap, sync::Arc, vec};
 
 use chrono::{NaiveDateTime, Utc};
-use sqlx::{pool::PoolConnection, Pool, Postgres};
-use tokio::sync::mpsc::Receiver;
+use sqlx::{pool::PoolConnection, Acquire, Pool, Postgres};
+use tokio::sync::{mpsc::Receiver, RwLock};
 use tracing::{info, instrument};
 use uuid::Uuid;
 
-use crate::models::{ExecutionReport, TaskError, TaskModel};
+use crate::models::{
+    ApiKeyModel, AuditModel, ExecutionModel, ExecutionProcess, ExecutionReport, ExecutionState, ExitStatus, OutputLine, OutputStream,
+    SecretModel, SessionModel, TaskError, TaskModel, UserModel,
+};
 use crate::recv_dropped;
 
 use super::DBMessage;
@@ -22,6 +25,25 @@ macro_rules! now {
     };
 }
 
+macro_rules! report_from_row {
+    ($row: expr, $output: expr) => {
+        ExecutionReport::new_raw(
+            $row.id,
+            $row.task_id,
+            $row.created_at,
+            ExitStatus::new_raw(
+                $row.successful,
+                $row.exit_code,
+                $row.signal,
+                $row.container_status,
+            )
+            .with_http($row.http_status, $row.http_headers.as_deref().and_then(|h| serde_json::from_str(h).ok())),
+            $output,
+        )
+        .with_attempt($row.attempt, $row.original_id)
+    };
+}
+
 type Connection = PoolConnection<Postgres>;
 
 impl DBManager {
@@ -39,12 +61,13 @@ impl DBManager {
     pub async fn create_task(
         conn: &mut Connection,
         task: TaskModel,
-    ) -> Result<TaskModel, sqlx::Error> {
+    ) -> Result<TaskModel, anyhow::Error> {
+        Self::check_hook_slug(conn, &task).await?;
         let row = sqlx::query_as!(TaskModel, r#"
             INSERT INTO steward_tasks
-                ( id, task_name, created_at, updated_at, task_type, last_execution, next_execution, serde_string, frequency, exec_count )
+                ( id, task_name, created_at, updated_at, task_type, last_execution, next_execution, serde_string, frequency, exec_count, timeout, retry_policy, overlap_policy, hook_secret, hook_slug, hook_method, hook_filters )
                 VALUES
-                ( $1, $2, $3, $4, $5, $6, $7, $8, $9, $10 )
+                ( $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17 )
                 RETURNING *
             "#,
             task.id,
@@ -56,10 +79,17 @@ impl DBManager {
             task.next_execution,
             task.serde_string,
             task.frequency,
-            task.exec_count
+            task.exec_count,
+            task.timeout,
+            task.retry_policy,
+            task.overlap_policy,
+            task.hook_secret,
+            task.hook_slug,
+            task.hook_method,
+            task.hook_filters
             )
-            .fetch_one(conn).await;
-        return row;
+            .fetch_one(conn).await?;
+        return Ok(row);
     }
     #[instrument(
         name = "Fetching a task from database.",
@@ -171,19 +201,52 @@ impl DBManager {
     pub async fn update_task(
         conn: &mut Connection,
         task: TaskModel,
-    ) -> Result<TaskModel, sqlx::Error> {
+    ) -> Result<TaskModel, anyhow::Error> {
+        Self::check_hook_slug(conn, &task).await?;
         let row = sqlx::query_as!(TaskModel,
-            "UPDATE steward_tasks SET updated_at = $2, serde_string = $3, frequency = $4, last_execution = $5, next_execution = $6, exec_count = $7 WHERE id = $1 RETURNING *",
+            "UPDATE steward_tasks SET updated_at = $2, serde_string = $3, frequency = $4, last_execution = $5, next_execution = $6, exec_count = $7, timeout = $8, retry_policy = $9, overlap_policy = $10, hook_secret = $11, hook_slug = $12, hook_method = $13, hook_filters = $14 WHERE id = $1 RETURNING *",
             task.id,
             now!(),
             task.serde_string,
             task.frequency,
             task.last_execution,
             task.next_execution,
-            task.exec_count
+            task.exec_count,
+            task.timeout,
+            task.retry_policy,
+            task.overlap_policy,
+            task.hook_secret,
+            task.hook_slug,
+            task.hook_method,
+            task.hook_filters
             )
-            .fetch_one(conn).await;
-        row
+            .fetch_one(conn).await?;
+        Ok(row)
+    }
+    // Hook slugs are unique, the index guards it too but this gives a readable error
+    async fn check_hook_slug(conn: &mut Connection, task: &TaskModel) -> Result<(), anyhow::Error> {
+        let slug = match &task.hook_slug {
+            Some(s) => s,
+            None => return Ok(()),
+        };
+        let taken = sqlx::query!(
+            "SELECT id FROM steward_tasks WHERE hook_slug = $1 AND id <> $2",
+            slug,
+            task.id
+        )
+        .fetch_optional(conn)
+        .await?;
+        if let Some(other) = taken {
+            return Err(anyhow::anyhow!("Hook slug '{}' is already used by task {}", slug, other.id));
+        }
+        return Ok(());
+    }
+    #[instrument(name = "Fetching a task by its hook slug from database.", skip(conn))]
+    pub async fn get_task_by_slug(conn: &mut Connection, slug: String) -> Result<TaskModel, sqlx::Error> {
+        let task = sqlx::query_as!(TaskModel, "SELECT * FROM steward_tasks WHERE hook_slug = $1", slug)
+            .fetch_one(conn)
+            .await;
+        return task;
     }
     #[instrument(
         name = "Deleting task.",
@@ -214,32 +277,84 @@ impl DBManager {
         conn: &mut Connection,
         report: ExecutionReport,
     ) -> Result<ExecutionReport, sqlx::Error> {
-        let output = report.output_as_string();
+        let mut tx = conn.begin().await?;
         let row = sqlx::query!(
             r#"
             INSERT INTO steward_task_execution_report
-                ( id, task_id, created_at, successful, output )
+                ( id, task_id, created_at, successful, exit_code, signal, container_status, http_status, http_headers, attempt, original_id )
                 VALUES
-                ( $1, $2, $3, $4, $5 )
+                ( $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11 )
                 RETURNING *
             "#,
             report.id,
             report.task_id,
             report.created_at,
             report.successful,
-            output
+            report.exit_code,
+            report.signal,
+            report.container_status,
+            report.http_status,
+            report.http_headers.as_ref().map(|h| serde_json::to_string(h).unwrap_or_default()),
+            report.attempt,
+            report.original_id
         )
-        .fetch_one(conn)
+        .fetch_one(&mut tx)
+        .await?;
+        let mut sequences = vec![];
+        let mut streams = vec![];
+        let mut timestamps = vec![];
+        let mut lines = vec![];
+        for line in &report.output {
+            sequences.push(line.sequence);
+            streams.push(line.stream.as_str().to_string());
+            timestamps.push(line.timestamp);
+            lines.push(line.line.clone());
+        }
+        sqlx::query!(
+            r#"
+            INSERT INTO steward_task_execution_output
+                ( report_id, sequence, stream, timestamp, line )
+                SELECT $1, * FROM UNNEST($2::bigint[], $3::varchar[], $4::timestamp[], $5::text[])
+            "#,
+            report.id,
+            &sequences,
+            &streams,
+            &timestamps,
+            &lines
+        )
+        .execute(&mut tx)
         .await?;
-        let result = ExecutionReport::new_string_output(
-            row.id,
-            row.task_id,
-            row.created_at,
-            row.successful,
-            row.output,
-        );
+        tx.commit().await?;
+        let result = report_from_row!(row, report.output);
         Ok(result)
     }
+    #[instrument(name = "Get output lines of execution reports.", skip(conn, report_ids))]
+    pub async fn get_output_lines(
+        conn: &mut Connection,
+        report_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<OutputLine>>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT * FROM steward_task_execution_output
+            WHERE report_id = ANY($1)
+            ORDER BY report_id, sequence
+            "#,
+            report_ids
+        )
+        .fetch_all(conn)
+        .await?;
+        let mut results: HashMap<Uuid, Vec<OutputLine>> = HashMap::new();
+        for row in rows {
+            let line = OutputLine::new_raw(
+                row.sequence,
+                OutputStream::from_str(&row.stream),
+                row.timestamp,
+                row.line,
+            );
+            results.entry(row.report_id).or_default().push(line);
+        }
+        Ok(results)
+    }
     #[instrument(name = "Get execution reports.", skip(conn))]
     pub async fn get_execution_reports(
         conn: &mut Connection,
@@ -255,17 +370,14 @@ impl DBManager {
             "#,
             offset
         )
-        .fetch_all(conn)
+        .fetch_all(&mut *conn)
         .await?;
+        let ids: Vec<Uuid> = rows.iter().map(|r| r.id).collect();
+        let mut output = Self::get_output_lines(conn, &ids).await?;
         let mut results = vec![];
         for row in rows {
-            let result = ExecutionReport::new_string_output(
-                row.id,
-                row.task_id,
-                row.created_at,
-                row.successful,
-                row.output,
-            );
+            let lines = output.remove(&row.id).unwrap_or_default();
+            let result = report_from_row!(row, lines);
             results.push(result);
         }
         Ok(results)
@@ -282,15 +394,10 @@ impl DBManager {
             "#,
             id
         )
-        .fetch_one(conn)
+        .fetch_one(&mut *conn)
         .await?;
-        let result = ExecutionReport::new_string_output(
-            row.id,
-            row.task_id,
-            row.created_at,
-            row.successful,
-            row.output,
-        );
+        let lines = Self::get_output_lines(conn, &[id]).await?.remove(&id);
+        let result = report_from_row!(row, lines.unwrap_or_default());
         Ok(result)
     }
     #[instrument(name = "Get execution reports for task.", skip(conn))]
@@ -311,17 +418,14 @@ impl DBManager {
             task_id,
             offset
         )
-        .fetch_all(conn)
+        .fetch_all(&mut *conn)
         .await?;
+        let ids: Vec<Uuid> = rows.iter().map(|r| r.id).collect();
+        let mut output = Self::get_output_lines(conn, &ids).await?;
         let mut results = vec![];
         for row in rows {
-            let result = ExecutionReport::new_string_output(
-                row.id,
-                row.task_id,
-                row.created_at,
-                row.successful,
-                row.output,
-            );
+            let lines = output.remove(&row.id).unwrap_or_default();
+            let result = report_from_row!(row, lines);
             results.push(result);
         }
         Ok(results)
@@ -342,13 +446,8 @@ impl DBManager {
         .await?;
         let mut results = vec![];
         for row in rows {
-            let result = ExecutionReport::new_string_output(
-                row.id,
-                row.task_id,
-                row.created_at,
-                row.successful,
-                row.output,
-            );
+            // Output lines are already gone with the cascade
+            let result = report_from_row!(row, vec![]);
             results.push(result);
         }
         return Ok(results);
@@ -369,6 +468,467 @@ impl DBManager {
         .await?;
         Ok(rows)
     }
+    #[instrument(
+        name = "Creating execution.",
+        skip(conn, execution),
+        fields(
+            execution_id = %execution.id,
+            task_id = %execution.task_id
+        )
+    )]
+    pub async fn create_execution(
+        conn: &mut Connection,
+        execution: ExecutionModel,
+    ) -> Result<ExecutionModel, sqlx::Error> {
+        let row = sqlx::query_as!(
+            ExecutionModel,
+            r#"
+            INSERT INTO steward_task_executions
+                ( id, task_id, state, attempt, queued_at, starting_at, running_at, finished_at, updated_at, pid, container_id )
+                VALUES
+                ( $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11 )
+                RETURNING *
+            "#,
+            execution.id,
+            execution.task_id,
+            execution.state,
+            execution.attempt,
+            execution.queued_at,
+            execution.starting_at,
+            execution.running_at,
+            execution.finished_at,
+            execution.updated_at,
+            execution.pid,
+            execution.container_id
+        )
+        .fetch_one(conn)
+        .await;
+        return row;
+    }
+    #[instrument(name = "Updating execution state.", skip(conn), fields(execution_id = %id))]
+    pub async fn update_execution_state(
+        conn: &mut Connection,
+        id: Uuid,
+        state: ExecutionState,
+    ) -> Result<ExecutionModel, sqlx::Error> {
+        // Every transition leaves its timestamp behind
+        let row = sqlx::query_as!(
+            ExecutionModel,
+            r#"
+            UPDATE steward_task_executions SET
+                state = $2::varchar,
+                updated_at = $3,
+                starting_at = CASE WHEN $2::varchar = 'Starting' THEN $3 ELSE starting_at END,
+                running_at = CASE WHEN $2::varchar = 'Running' THEN $3 ELSE running_at END,
+                finished_at = CASE WHEN $4 THEN $3 ELSE finished_at END
+            WHERE id = $1
+            RETURNING *
+            "#,
+            id,
+            state.to_string(),
+            now!(),
+            state.is_finished()
+        )
+        .fetch_one(conn)
+        .await;
+        return row;
+    }
+    #[instrument(name = "Updating execution process.", skip(conn), fields(execution_id = %id))]
+    pub async fn update_execution_process(
+        conn: &mut Connection,
+        id: Uuid,
+        process: ExecutionProcess,
+    ) -> Result<ExecutionModel, sqlx::Error> {
+        let (pid, container_id) = match process {
+            ExecutionProcess::Pid(pid) => (Some(pid as i32), None),
+            ExecutionProcess::Container(id) => (None, Some(id)),
+        };
+        let row = sqlx::query_as!(
+            ExecutionModel,
+            r#"
+            UPDATE steward_task_executions SET pid = $2, container_id = $3, updated_at = $4
+            WHERE id = $1
+            RETURNING *
+            "#,
+            id,
+            pid,
+            container_id,
+            now!()
+        )
+        .fetch_one(conn)
+        .await;
+        return row;
+    }
+    #[instrument(name = "Get unfinished executions.", skip(conn))]
+    pub async fn get_unfinished_executions(
+        conn: &mut Connection,
+    ) -> Result<Vec<ExecutionModel>, sqlx::Error> {
+        let rows = sqlx::query_as!(
+            ExecutionModel,
+            r#"
+            SELECT * FROM steward_task_executions
+            WHERE state IN ('Queued', 'Starting', 'Running')
+            ORDER BY queued_at
+            "#
+        )
+        .fetch_all(conn)
+        .await;
+        return rows;
+    }
+    #[instrument(name = "Get execution.", skip(conn))]
+    pub async fn get_execution(conn: &mut Connection, id: Uuid) -> Result<ExecutionModel, sqlx::Error> {
+        let row = sqlx::query_as!(
+            ExecutionModel,
+            "SELECT * FROM steward_task_executions WHERE id = $1",
+            id
+        )
+        .fetch_one(conn)
+        .await;
+        return row;
+    }
+    #[instrument(name = "Get executions.", skip(conn))]
+    pub async fn get_executions(
+        conn: &mut Connection,
+        offset: Option<i64>,
+        state: Option<ExecutionState>,
+        task_id: Option<Uuid>,
+    ) -> Result<Vec<ExecutionModel>, sqlx::Error> {
+        let offset = offset.unwrap_or(0);
+        let state = state.map(|s| s.to_string());
+        let rows = sqlx::query_as!(
+            ExecutionModel,
+            r#"
+            SELECT * FROM steward_task_executions
+            WHERE ($1::varchar IS NULL OR state = $1)
+                AND ($2::uuid IS NULL OR task_id = $2)
+            ORDER BY queued_at DESC
+            LIMIT 100
+            OFFSET $3
+            "#,
+            state,
+            task_id,
+            offset
+        )
+        .fetch_all(conn)
+        .await;
+        return rows;
+    }
+    #[instrument(name = "Get active tasks.", skip(conn))]
+    pub async fn get_active_tasks(conn: &mut Connection) -> Result<Vec<TaskModel>, sqlx::Error> {
+        let rows = sqlx::query_as!(
+            TaskModel,
+            r#"
+            SELECT * FROM steward_tasks WHERE id IN (
+                SELECT task_id FROM steward_task_executions
+                WHERE state IN ('Queued', 'Starting', 'Running')
+            )
+            ORDER BY created_at DESC
+            "#
+        )
+        .fetch_all(conn)
+        .await;
+        return rows;
+    }
+    #[instrument(name = "Create API key.", skip(conn, key), fields(id = %key.id))]
+    pub async fn create_api_key(conn: &mut Connection, key: ApiKeyModel) -> Result<ApiKeyModel, sqlx::Error> {
+        let row = sqlx::query_as!(
+            ApiKeyModel,
+            r#"
+            INSERT INTO steward_api_keys
+                ( id, name, key_hash, key_prefix, scopes, created_at )
+                VALUES
+                ( $1, $2, $3, $4, $5, $6 )
+                RETURNING *
+            "#,
+            key.id,
+            key.name,
+            key.key_hash,
+            key.key_prefix,
+            &key.scopes,
+            key.created_at
+        )
+        .fetch_one(conn)
+        .await;
+        return row;
+    }
+    #[instrument(name = "Get API key.", skip(conn, key_hash))]
+    pub async fn get_api_key(conn: &mut Connection, key_hash: String) -> Result<ApiKeyModel, sqlx::Error> {
+        let row = sqlx::query_as!(
+            ApiKeyModel,
+            "UPDATE steward_api_keys SET last_used_at = $2 WHERE key_hash = $1 AND revoked_at IS NULL RETURNING *",
+            key_hash,
+            now!()
+        )
+        .fetch_one(conn)
+        .await;
+        return row;
+    }
+    #[instrument(name = "Get API keys.", skip(conn))]
+    pub async fn get_api_keys(conn: &mut Connection) -> Result<Vec<ApiKeyModel>, sqlx::Error> {
+        let rows = sqlx::query_as!(ApiKeyModel, "SELECT * FROM steward_api_keys ORDER BY created_at DESC")
+            .fetch_all(conn)
+            .await;
+        return rows;
+    }
+    #[instrument(name = "Revoke API key.", skip(conn))]
+    pub async fn revoke_api_key(conn: &mut Connection, id: Uuid) -> Result<ApiKeyModel, sqlx::Error> {
+        let row = sqlx::query_as!(
+            ApiKeyModel,
+            "UPDATE steward_api_keys SET revoked_at = $2 WHERE id = $1 AND revoked_at IS NULL RETURNING *",
+            id,
+            now!()
+        )
+        .fetch_one(conn)
+        .await;
+        return row;
+    }
+    #[instrument(name = "Create user.", skip(conn, user), fields(id = %user.id))]
+    pub async fn create_user(conn: &mut Connection, user: UserModel) -> Result<UserModel, anyhow::Error> {
+        let existing = sqlx::query!("SELECT id FROM steward_users WHERE username = $1", user.username)
+            .fetch_optional(&mut *conn)
+            .await?;
+        if existing.is_some() {
+            return Err(anyhow::anyhow!("Username {} is already taken", user.username));
+        }
+        let row = sqlx::query_as!(
+            UserModel,
+            r#"
+            INSERT INTO steward_users
+                ( id, username, password_hash, role, created_at, updated_at )
+                VALUES
+                ( $1, $2, $3, $4, $5, $6 )
+                RETURNING *
+            "#,
+            user.id,
+            user.username,
+            user.password_hash,
+            user.role,
+            user.created_at,
+            user.updated_at
+        )
+        .fetch_one(conn)
+        .await?;
+        return Ok(row);
+    }
+    #[instrument(name = "Get user.", skip(conn))]
+    pub async fn get_user(conn: &mut Connection, id: Uuid) -> Result<UserModel, sqlx::Error> {
+        let row = sqlx::query_as!(UserModel, "SELECT * FROM steward_users WHERE id = $1", id)
+            .fetch_one(conn)
+            .await;
+        return row;
+    }
+    #[instrument(name = "Get user by username.", skip(conn))]
+    pub async fn get_user_by_username(conn: &mut Connection, username: String) -> Result<UserModel, sqlx::Error> {
+        let row = sqlx::query_as!(UserModel, "SELECT * FROM steward_users WHERE username = $1", username)
+            .fetch_one(conn)
+            .await;
+        return row;
+    }
+    #[instrument(name = "Get users.", skip(conn))]
+    pub async fn get_users(conn: &mut Connection) -> Result<Vec<UserModel>, sqlx::Error> {
+        let rows = sqlx::query_as!(UserModel, "SELECT * FROM steward_users ORDER BY username")
+            .fetch_all(conn)
+            .await;
+        return rows;
+    }
+    #[instrument(name = "Update user.", skip(conn, user), fields(id = %user.id))]
+    pub async fn update_user(conn: &mut Connection, user: UserModel) -> Result<UserModel, sqlx::Error> {
+        let row = sqlx::query_as!(
+            UserModel,
+            "UPDATE steward_users SET password_hash = $2, role = $3, updated_at = $4 WHERE id = $1 RETURNING *",
+            user.id,
+            user.password_hash,
+            user.role,
+            now!()
+        )
+        .fetch_one(conn)
+        .await;
+        return row;
+    }
+    #[instrument(name = "Delete user.", skip(conn))]
+    pub async fn delete_user(conn: &mut Connection, id: Uuid) -> Result<UserModel, sqlx::Error> {
+        let row = sqlx::query_as!(UserModel, "DELETE FROM steward_users WHERE id = $1 RETURNING *", id)
+            .fetch_one(conn)
+            .await;
+        return row;
+    }
+    #[instrument(name = "Create session.", skip(conn, session), fields(id = %session.id))]
+    pub async fn create_session(conn: &mut Connection, session: SessionModel) -> Result<SessionModel, sqlx::Error> {
+        // Nobody logs out of every session, expired ones are swept here
+        sqlx::query!("DELETE FROM steward_sessions WHERE expires_at <= $1", now!())
+            .execute(&mut *conn)
+            .await?;
+        let row = sqlx::query_as!(
+            SessionModel,
+            r#"
+            INSERT INTO steward_sessions
+                ( id, user_id, token_hash, csrf_token, created_at, expires_at )
+                VALUES
+                ( $1, $2, $3, $4, $5, $6 )
+                RETURNING *
+            "#,
+            session.id,
+            session.user_id,
+            session.token_hash,
+            session.csrf_token,
+            session.created_at,
+            session.expires_at
+        )
+        .fetch_one(conn)
+        .await;
+        return row;
+    }
+    #[instrument(name = "Get session.", skip(conn, token_hash))]
+    pub async fn get_session(conn: &mut Connection, token_hash: String) -> Result<SessionModel, sqlx::Error> {
+        let row = sqlx::query_as!(
+            SessionModel,
+            "SELECT * FROM steward_sessions WHERE token_hash = $1 AND expires_at > $2",
+            token_hash,
+            now!()
+        )
+        .fetch_one(conn)
+        .await;
+        return row;
+    }
+    #[instrument(name = "Delete session.", skip(conn))]
+    pub async fn delete_session(conn: &mut Connection, id: Uuid) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!("DELETE FROM steward_sessions WHERE id = $1", id)
+            .execute(conn)
+            .await?;
+        return Ok(result.rows_affected());
+    }
+    #[instrument(name = "Delete sessions for user.", skip(conn))]
+    pub async fn delete_sessions_for_user(conn: &mut Connection, user_id: Uuid) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!("DELETE FROM steward_sessions WHERE user_id = $1", user_id)
+            .execute(conn)
+            .await?;
+        return Ok(result.rows_affected());
+    }
+    #[instrument(name = "Create audit entry.", skip(conn, entry), fields(action = %entry.action))]
+    pub async fn create_audit_entry(conn: &mut Connection, entry: AuditModel) -> Result<AuditModel, sqlx::Error> {
+        let row = sqlx::query_as!(
+            AuditModel,
+            r#"
+            INSERT INTO steward_audit_log
+                ( id, created_at, actor_type, actor_id, actor_name, source_ip, request, action, target_type, target_id, execution_id, summary, diff )
+                VALUES
+                ( $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13 )
+                RETURNING *
+            "#,
+            entry.id,
+            entry.created_at,
+            entry.actor_type,
+            entry.actor_id,
+            entry.actor_name,
+            entry.source_ip,
+            entry.request,
+            entry.action,
+            entry.target_type,
+            entry.target_id,
+            entry.execution_id,
+            entry.summary,
+            entry.diff
+        )
+        .fetch_one(conn)
+        .await;
+        return row;
+    }
+    #[instrument(name = "Get audit entries.", skip(conn))]
+    pub async fn get_audit_entries(
+        conn: &mut Connection,
+        offset: Option<i64>,
+        target_id: Option<Uuid>,
+        action: Option<String>,
+        actor_type: Option<String>,
+    ) -> Result<Vec<AuditModel>, sqlx::Error> {
+        let offset = offset.unwrap_or(0);
+        let rows = sqlx::query_as!(
+            AuditModel,
+            r#"
+            SELECT * FROM steward_audit_log
+            WHERE ($1::uuid IS NULL OR target_id = $1 OR execution_id = $1)
+                AND ($2::varchar IS NULL OR action = $2)
+                AND ($3::varchar IS NULL OR actor_type = $3)
+            ORDER BY created_at DESC
+            LIMIT 100
+            OFFSET $4
+            "#,
+            target_id,
+            action,
+            actor_type,
+            offset
+        )
+        .fetch_all(conn)
+        .await;
+        return rows;
+    }
+    #[instrument(name = "Create secret.", skip(conn, secret), fields(name = %secret.name))]
+    pub async fn create_secret(conn: &mut Connection, secret: SecretModel) -> Result<SecretModel, anyhow::Error> {
+        let existing = sqlx::query!("SELECT id FROM steward_secrets WHERE name = $1", secret.name)
+            .fetch_optional(&mut *conn)
+            .await?;
+        if existing.is_some() {
+            return Err(anyhow::anyhow!("Secret {} already exists", secret.name));
+        }
+        let row = sqlx::query_as!(
+            SecretModel,
+            r#"
+            INSERT INTO steward_secrets
+                ( id, name, nonce, ciphertext, created_at, updated_at )
+                VALUES
+                ( $1, $2, $3, $4, $5, $6 )
+                RETURNING *
+            "#,
+            secret.id,
+            secret.name,
+            secret.nonce,
+            secret.ciphertext,
+            secret.created_at,
+            secret.updated_at
+        )
+        .fetch_one(conn)
+        .await?;
+        return Ok(row);
+    }
+    #[instrument(name = "Get secret.", skip(conn))]
+    pub async fn get_secret(conn: &mut Connection, name: String) -> Result<SecretModel, sqlx::Error> {
+        let row = sqlx::query_as!(SecretModel, "SELECT * FROM steward_secrets WHERE name = $1", name)
+            .fetch_one(conn)
+            .await;
+        return row;
+    }
+    #[instrument(name = "Get secrets.", skip(conn))]
+    pub async fn get_secrets(conn: &mut Connection, names: Option<Vec<String>>) -> Result<Vec<SecretModel>, sqlx::Error> {
+        let rows = sqlx::query_as!(
+            SecretModel,
+            "SELECT * FROM steward_secrets WHERE ($1::text[] IS NULL OR name = ANY($1)) ORDER BY name",
+            names.as_deref()
+        )
+        .fetch_all(conn)
+        .await;
+        return rows;
+    }
+    #[instrument(name = "Update secret.", skip(conn, secret), fields(name = %secret.name))]
+    pub async fn update_secret(conn: &mut Connection, secret: SecretModel) -> Result<SecretModel, sqlx::Error> {
+        let row = sqlx::query_as!(
+            SecretModel,
+            "UPDATE steward_secrets SET nonce = $2, ciphertext = $3, updated_at = $4 WHERE id = $1 RETURNING *",
+            secret.id,
+            secret.nonce,
+            secret.ciphertext,
+            secret.updated_at
+        )
+        .fetch_one(conn)
+        .await;
+        return row;
+    }
+    #[instrument(name = "Delete secret.", skip(conn))]
+    pub async fn delete_secret(conn: &mut Connection, name: String) -> Result<SecretModel, sqlx::Error> {
+        let row = sqlx::query_as!(SecretModel, "DELETE FROM steward_secrets WHERE name = $1 RETURNING *", name)
+            .fetch_one(conn)
+            .await;
+        return row;
+    }
 }
 
 macro_rules! sqlx_to_anyhow {
@@ -383,16 +943,29 @@ macro_rules! sqlx_to_anyhow {
 impl DBManager {
     pub async fn listen(&mut self) {
         info!("DBManager started listening for messages.");
+        // Messages are handled concurrently, each one holds a read lock until it's done
+        let in_flight = Arc::new(RwLock::new(()));
         while let Some(message) = self.rx.recv().await {
             info!("Got a {} message", message.get_type());
+            if let DBMessage::Flush { resp } = message {
+                let _ = in_flight.write().await;
+                recv_dropped!(resp.send(()), "Flush");
+                continue;
+            }
+            let guard = in_flight.clone().read_owned().await;
             let mut connection = self.pool.acquire().await.unwrap();
             let pool = self.pool.clone();
             tokio::spawn(async move {
+                let _guard = guard;
                 match message {
                     DBMessage::GetTask { id, resp } => {
                         let task = sqlx_to_anyhow!(Self::get_task(&mut connection, id).await);
                         recv_dropped!(resp.send(task), "GetTask");
                     }
+                    DBMessage::GetTaskBySlug { slug, resp } => {
+                        let task = sqlx_to_anyhow!(Self::get_task_by_slug(&mut connection, slug).await);
+                        recv_dropped!(resp.send(task), "GetTaskBySlug");
+                    }
                     DBMessage::GetTasks { offset, resp } => {
                         let tasks = sqlx_to_anyhow!(Self::get_tasks(&mut connection, offset).await);
                         recv_dropped!(resp.send(tasks), "GetTasks");
@@ -480,6 +1053,137 @@ impl DBManager {
                         );
                         recv_dropped!(resp.send(report), "GetExecutionReport");
                     }
+                    DBMessage::CreateExecution { execution, resp } => {
+                        let execution = sqlx_to_anyhow!(
+                            Self::create_execution(&mut connection, execution).await
+                        );
+                        recv_dropped!(resp.send(execution), "CreateExecution");
+                    }
+                    DBMessage::UpdateExecutionState { id, state, resp } => {
+                        let execution = sqlx_to_anyhow!(
+                            Self::update_execution_state(&mut connection, id, state).await
+                        );
+                        recv_dropped!(resp.send(execution), "UpdateExecutionState");
+                    }
+                    DBMessage::UpdateExecutionProcess { id, process, resp } => {
+                        let execution = sqlx_to_anyhow!(
+                            Self::update_execution_process(&mut connection, id, process).await
+                        );
+                        recv_dropped!(resp.send(execution), "UpdateExecutionProcess");
+                    }
+                    DBMessage::GetUnfinishedExecutions { resp } => {
+                        let executions =
+                            sqlx_to_anyhow!(Self::get_unfinished_executions(&mut connection).await);
+                        recv_dropped!(resp.send(executions), "GetUnfinishedExecutions");
+                    }
+                    DBMessage::GetExecution { id, resp } => {
+                        let execution =
+                            sqlx_to_anyhow!(Self::get_execution(&mut connection, id).await);
+                        recv_dropped!(resp.send(execution), "GetExecution");
+                    }
+                    // Handled before spawning
+                    DBMessage::Flush { .. } => {}
+                    DBMessage::GetExecutions {
+                        offset,
+                        state,
+                        task_id,
+                        resp,
+                    } => {
+                        let executions = sqlx_to_anyhow!(
+                            Self::get_executions(&mut connection, offset, state, task_id).await
+                        );
+                        recv_dropped!(resp.send(executions), "GetExecutions");
+                    }
+                    DBMessage::GetActiveTasks { resp } => {
+                        let tasks = sqlx_to_anyhow!(Self::get_active_tasks(&mut connection).await);
+                        recv_dropped!(resp.send(tasks), "GetActiveTasks");
+                    }
+                    DBMessage::CreateApiKey { key, resp } => {
+                        let key = sqlx_to_anyhow!(Self::create_api_key(&mut connection, key).await);
+                        recv_dropped!(resp.send(key), "CreateApiKey");
+                    }
+                    DBMessage::GetApiKey { key_hash, resp } => {
+                        let key = sqlx_to_anyhow!(Self::get_api_key(&mut connection, key_hash).await);
+                        recv_dropped!(resp.send(key), "GetApiKey");
+                    }
+                    DBMessage::GetApiKeys { resp } => {
+                        let keys = sqlx_to_anyhow!(Self::get_api_keys(&mut connection).await);
+                        recv_dropped!(resp.send(keys), "GetApiKeys");
+                    }
+                    DBMessage::RevokeApiKey { id, resp } => {
+                        let key = sqlx_to_anyhow!(Self::revoke_api_key(&mut connection, id).await);
+                        recv_dropped!(resp.send(key), "RevokeApiKey");
+                    }
+                    DBMessage::CreateUser { user, resp } => {
+                        let user = Self::create_user(&mut connection, user).await;
+                        recv_dropped!(resp.send(user), "CreateUser");
+                    }
+                    DBMessage::GetUser { id, resp } => {
+                        let user = sqlx_to_anyhow!(Self::get_user(&mut connection, id).await);
+                        recv_dropped!(resp.send(user), "GetUser");
+                    }
+                    DBMessage::GetUserByUsername { username, resp } => {
+                        let user = sqlx_to_anyhow!(Self::get_user_by_username(&mut connection, username).await);
+                        recv_dropped!(resp.send(user), "GetUserByUsername");
+                    }
+                    DBMessage::GetUsers { resp } => {
+                        let users = sqlx_to_anyhow!(Self::get_users(&mut connection).await);
+                        recv_dropped!(resp.send(users), "GetUsers");
+                    }
+                    DBMessage::UpdateUser { user, resp } => {
+                        let user = sqlx_to_anyhow!(Self::update_user(&mut connection, user).await);
+                        recv_dropped!(resp.send(user), "UpdateUser");
+                    }
+                    DBMessage::DeleteUser { id, resp } => {
+                        let user = sqlx_to_anyhow!(Self::delete_user(&mut connection, id).await);
+                        recv_dropped!(resp.send(user), "DeleteUser");
+                    }
+                    DBMessage::CreateSession { session, resp } => {
+                        let session = sqlx_to_anyhow!(Self::create_session(&mut connection, session).await);
+                        recv_dropped!(resp.send(session), "CreateSession");
+                    }
+                    DBMessage::GetSession { token_hash, resp } => {
+                        let session = sqlx_to_anyhow!(Self::get_session(&mut connection, token_hash).await);
+                        recv_dropped!(resp.send(session), "GetSession");
+                    }
+                    DBMessage::DeleteSession { id, resp } => {
+                        let deleted = sqlx_to_anyhow!(Self::delete_session(&mut connection, id).await);
+                        recv_dropped!(resp.send(deleted), "DeleteSession");
+                    }
+                    DBMessage::DeleteSessionsForUser { user_id, resp } => {
+                        let deleted = sqlx_to_anyhow!(Self::delete_sessions_for_user(&mut connection, user_id).await);
+                        recv_dropped!(resp.send(deleted), "DeleteSessionsForUser");
+                    }
+                    DBMessage::CreateAuditEntry { entry, resp } => {
+                        let entry = sqlx_to_anyhow!(Self::create_audit_entry(&mut connection, entry).await);
+                        recv_dropped!(resp.send(entry), "CreateAuditEntry");
+                    }
+                    DBMessage::GetAuditEntries { offset, target_id, action, actor_type, resp } => {
+                        let entries = sqlx_to_anyhow!(
+                            Self::get_audit_entries(&mut connection, offset, target_id, action, actor_type).await
+                        );
+                        recv_dropped!(resp.send(entries), "GetAuditEntries");
+                    }
+                    DBMessage::CreateSecret { secret, resp } => {
+                        let secret = Self::create_secret(&mut connection, secret).await;
+                        recv_dropped!(resp.send(secret), "CreateSecret");
+                    }
+                    DBMessage::GetSecret { name, resp } => {
+                        let secret = sqlx_to_anyhow!(Self::get_secret(&mut connection, name).await);
+                        recv_dropped!(resp.send(secret), "GetSecret");
+                    }
+                    DBMessage::GetSecrets { names, resp } => {
+                        let secrets = sqlx_to_anyhow!(Self::get_secrets(&mut connection, names).await);
+                        recv_dropped!(resp.send(secrets), "GetSecrets");
+                    }
+                    DBMessage::UpdateSecret { secret, resp } => {
+                        let secret = sqlx_to_anyhow!(Self::update_secret(&mut connection, secret).await);
+                        recv_dropped!(resp.send(secret), "UpdateSecret");
+                    }
+                    DBMessage::DeleteSecret { name, resp } => {
+                        let secret = sqlx_to_anyhow!(Self::delete_secret(&mut connection, name).await);
+                        recv_dropped!(resp.send(secret), "DeleteSecret");
+                    }
                 };
             });
         }
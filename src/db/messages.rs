@@ -2,7 +2,10 @@ use chrono::NaiveDateTime;
 use uuid::Uuid;
 
 use crate::{
-    models::{ExecutionReport, TaskError, TaskModel},
+    models::{
+        ApiKeyModel, AuditModel, ExecutionModel, ExecutionProcess, ExecutionReport, ExecutionState, SecretModel, SessionModel,
+        TaskError, TaskModel, UserModel,
+    },
     types::OneShotMessageResponse,
 };
 // TODO: Remove allow when we need unconstructed variants
@@ -13,6 +16,10 @@ pub enum DBMessage {
         id: Uuid,
         resp: DBMessageResponse<TaskModel>,
     },
+    GetTaskBySlug {
+        slug: String,
+        resp: DBMessageResponse<TaskModel>,
+    },
     GetTasks {
         offset: Option<i64>,
         resp: DBMessageResponse<Vec<TaskModel>>,
@@ -71,6 +78,133 @@ pub enum DBMessage {
         report_id: Uuid,
         resp: DBMessageResponse<ExecutionReport>,
     },
+    CreateExecution {
+        execution: ExecutionModel,
+        resp: DBMessageResponse<ExecutionModel>,
+    },
+    UpdateExecutionState {
+        id: Uuid,
+        state: ExecutionState,
+        resp: DBMessageResponse<ExecutionModel>,
+    },
+    UpdateExecutionProcess {
+        id: Uuid,
+        process: ExecutionProcess,
+        resp: DBMessageResponse<ExecutionModel>,
+    },
+    // Executions that were left queued, starting or running
+    GetUnfinishedExecutions {
+        resp: DBMessageResponse<Vec<ExecutionModel>>,
+    },
+    GetExecution {
+        id: Uuid,
+        resp: DBMessageResponse<ExecutionModel>,
+    },
+    // Responds once every message sent before it is handled
+    Flush {
+        resp: OneShotMessageResponse<()>,
+    },
+    GetExecutions {
+        offset: Option<i64>,
+        state: Option<ExecutionState>,
+        task_id: Option<Uuid>,
+        resp: DBMessageResponse<Vec<ExecutionModel>>,
+    },
+    // Tasks that have a queued, starting or running execution
+    GetActiveTasks {
+        resp: DBMessageResponse<Vec<TaskModel>>,
+    },
+    CreateApiKey {
+        key: ApiKeyModel,
+        resp: DBMessageResponse<ApiKeyModel>,
+    },
+    // Only keys that aren't revoked, marks the key as used
+    GetApiKey {
+        key_hash: String,
+        resp: DBMessageResponse<ApiKeyModel>,
+    },
+    GetApiKeys {
+        resp: DBMessageResponse<Vec<ApiKeyModel>>,
+    },
+    RevokeApiKey {
+        id: Uuid,
+        resp: DBMessageResponse<ApiKeyModel>,
+    },
+    CreateUser {
+        user: UserModel,
+        resp: DBMessageResponse<UserModel>,
+    },
+    GetUser {
+        id: Uuid,
+        resp: DBMessageResponse<UserModel>,
+    },
+    GetUserByUsername {
+        username: String,
+        resp: DBMessageResponse<UserModel>,
+    },
+    GetUsers {
+        resp: DBMessageResponse<Vec<UserModel>>,
+    },
+    // Only the role and the password can change
+    UpdateUser {
+        user: UserModel,
+        resp: DBMessageResponse<UserModel>,
+    },
+    // Sessions of the user go with it
+    DeleteUser {
+        id: Uuid,
+        resp: DBMessageResponse<UserModel>,
+    },
+    CreateSession {
+        session: SessionModel,
+        resp: DBMessageResponse<SessionModel>,
+    },
+    // Only sessions that haven't expired
+    GetSession {
+        token_hash: String,
+        resp: DBMessageResponse<SessionModel>,
+    },
+    DeleteSession {
+        id: Uuid,
+        resp: DBMessageResponse<u64>,
+    },
+    DeleteSessionsForUser {
+        user_id: Uuid,
+        resp: DBMessageResponse<u64>,
+    },
+    CreateAuditEntry {
+        entry: AuditModel,
+        resp: DBMessageResponse<AuditModel>,
+    },
+    GetAuditEntries {
+        offset: Option<i64>,
+        target_id: Option<Uuid>,
+        action: Option<String>,
+        actor_type: Option<String>,
+        resp: DBMessageResponse<Vec<AuditModel>>,
+    },
+    CreateSecret {
+        secret: SecretModel,
+        resp: DBMessageResponse<SecretModel>,
+    },
+    GetSecret {
+        name: String,
+        resp: DBMessageResponse<SecretModel>,
+    },
+    // Every secret when names is None
+    GetSecrets {
+        names: Option<Vec<String>>,
+        resp: DBMessageResponse<Vec<SecretModel>>,
+    },
+    // Only the value can change
+    UpdateSecret {
+        secret: SecretModel,
+        resp: DBMessageResponse<SecretModel>,
+    },
+    DeleteSecret {
+        name: String,
+        resp: DBMessageResponse<SecretModel>,
+    },
 }
 
 impl DBMessage {
@@ -79,6 +213,9 @@ impl DBMessage {
             DBMessage::GetTask { .. } => {
                 return "GetTask";
             }
+            DBMessage::GetTaskBySlug { .. } => {
+                return "GetTaskBySlug";
+            }
             DBMessage::GetTasks { .. } => "GetTasks",
             DBMessage::CreateTask { .. } => {
                 return "CreateTask";
@@ -97,6 +234,35 @@ impl DBMessage {
             DBMessage::DeleteErrorsForTask { .. } => "DeleteErrorsForTask",
             DBMessage::GetExecutionReports { .. } => "GetExecutionReports",
             DBMessage::GetExecutionReport { .. } => "GetExecutionReport",
+            DBMessage::CreateExecution { .. } => "CreateExecution",
+            DBMessage::UpdateExecutionState { .. } => "UpdateExecutionState",
+            DBMessage::UpdateExecutionProcess { .. } => "UpdateExecutionProcess",
+            DBMessage::GetUnfinishedExecutions { .. } => "GetUnfinishedExecutions",
+            DBMessage::GetExecution { .. } => "GetExecution",
+            DBMessage::Flush { .. } => "Flush",
+            DBMessage::GetExecutions { .. } => "GetExecutions",
+            DBMessage::GetActiveTasks { .. } => "GetActiveTasks",
+            DBMessage::CreateApiKey { .. } => "CreateApiKey",
+            DBMessage::GetApiKey { .. } => "GetApiKey",
+            DBMessage::GetApiKeys { .. } => "GetApiKeys",
+            DBMessage::RevokeApiKey { .. } => "RevokeApiKey",
+            DBMessage::CreateUser { .. } => "CreateUser",
+            DBMessage::GetUser { .. } => "GetUser",
+            DBMessage::GetUserByUsername { .. } => "GetUserByUsername",
+            DBMessage::GetUsers { .. } => "GetUsers",
+            DBMessage::UpdateUser { .. } => "UpdateUser",
+            DBMessage::DeleteUser { .. } => "DeleteUser",
+            DBMessage::CreateSession { .. } => "CreateSession",
+            DBMessage::GetSession { .. } => "GetSession",
+            DBMessage::DeleteSession { .. } => "DeleteSession",
+            DBMessage::DeleteSessionsForUser { .. } => "DeleteSessionsForUser",
+            DBMessage::CreateAuditEntry { .. } => "CreateAuditEntry",
+            DBMessage::GetAuditEntries { .. } => "GetAuditEntries",
+            DBMessage::CreateSecret { .. } => "CreateSecret",
+            DBMessage::GetSecret { .. } => "GetSecret",
+            DBMessage::GetSecrets { .. } => "GetSecrets",
+            DBMessage::UpdateSecret { .. } => "UpdateSecret",
+            DBMessage::DeleteSecret { .. } => "DeleteSecret",
         }
     }
 }
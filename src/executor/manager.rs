@@ -1,11 +1,11 @@
-use tokio::task::JoinHandle;
+use tokio::{sync::oneshot, task::JoinHandle};
 use tracing::{info, instrument};
 use uuid::Uuid;
 
 use crate::{
-    models::TaskError,
-    traits::BoxedStream,
-    types::{BoxedTask, OneShotMessageResponse},
+    models::{AbortReason, ExitStatus, TaskError},
+    tasks::OverlapPolicy,
+    types::{BoxedTask, OneShotMessageResponse, TaskExecution},
 };
 
 use super::ExecutorMessage;
@@ -14,22 +14,143 @@ use super::ExecutorMessage;
 pub struct TaskHandle {
     inner_handle: JoinHandle<()>,
     id: Uuid,
+    execution_id: Uuid,
     // task_resp: OneShotMessageResponse<Result<BoxedStream, TaskError>>,
-    abort_tx: OneShotMessageResponse<bool>,
+    abort_tx: OneShotMessageResponse<AbortReason>,
+    timeout_handle: Option<JoinHandle<()>>,
+}
+
+impl TaskHandle {
+    fn release(self) -> OneShotMessageResponse<AbortReason> {
+        if let Some(timeout_handle) = self.timeout_handle {
+            timeout_handle.abort();
+        }
+        self.abort_tx
+    }
+}
+
+// A run that waits for the current one to finish, see OverlapPolicy::Queue
+pub struct QueuedExecution {
+    task: BoxedTask,
+    execution_id: Uuid,
+    timeout: Option<u64>,
+    starting: OneShotMessageResponse<()>,
+    resp: OneShotMessageResponse<Result<TaskExecution, TaskError>>,
 }
 
 pub struct Executor {
     pub task_handles: Vec<TaskHandle>,
+    pub queue: Vec<QueuedExecution>,
+    // In seconds
+    pub default_timeout: Option<u64>,
 }
 
 impl Executor {
     #[instrument(skip(task), fields(task = %task.get_id()))]
-    async fn execute(task: &mut BoxedTask) -> Result<BoxedStream, TaskError> {
+    async fn execute(task: &mut BoxedTask) -> Result<TaskExecution, TaskError> {
         info!("Executing task");
         let handle = task.exec().await;
         info!("Task execution finished.");
         return handle;
     }
+    // Execution that never runs, it's reported with the given reason
+    pub fn never_started(reason: AbortReason) -> TaskExecution {
+        let (completion_tx, completion_rx) = oneshot::channel();
+        completion_tx
+            .send(ExitStatus::unknown().abort(reason))
+            .unwrap_or_default();
+        TaskExecution::new(Box::new(tokio_stream::empty()), completion_rx)
+    }
+    fn start(
+        &mut self,
+        mut task: BoxedTask,
+        execution_id: Uuid,
+        timeout: Option<u64>,
+        starting: OneShotMessageResponse<()>,
+        resp: OneShotMessageResponse<Result<TaskExecution, TaskError>>,
+        inner_tx: tokio::sync::mpsc::Sender<ExecutorMessage>,
+    ) {
+        let id = task.get_id();
+        let (abort_tx, mut abort_rx) = oneshot::channel::<AbortReason>();
+        info!("Executing task: {}, execution: {}", id, execution_id);
+        starting.send(()).unwrap_or_default();
+        let handle = tokio::spawn(async move {
+            let execution = match Self::execute(&mut task).await {
+                Ok(execution) => execution,
+                Err(e) => {
+                    // Closes the handle before anyone hears about it, see ExecutionFinished
+                    drop(abort_rx);
+                    resp.send(Err(e)).unwrap_or_default();
+                    return;
+                }
+            };
+            // We hand out our own completion, so we can tell if the task is cut short
+            let mut task_completion = execution.completion;
+            let (completion_tx, completion_rx) = oneshot::channel();
+            let execution = TaskExecution {
+                output: execution.output,
+                completion: completion_rx,
+                process: execution.process,
+            };
+            resp.send(Ok(execution)).unwrap_or_default();
+            let status = tokio::select! {
+                status = &mut task_completion => status.unwrap_or_else(|_| ExitStatus::unknown()),
+                Ok(reason) = &mut abort_rx => {
+                    info!("Aborting execution {} of task {}", execution_id, id);
+                    task.abort().await;
+                    let status = task_completion.await.unwrap_or_else(|_| ExitStatus::unknown());
+                    status.abort(reason)
+                }
+            };
+            drop(abort_rx);
+            completion_tx.send(status).unwrap_or_default();
+            info!("Aborting timespan finished.");
+        });
+        // Timeouts are enforced through the same path as the manual aborts
+        let timeout_handle = timeout.or(self.default_timeout).map(|secs| {
+            tokio::spawn(async move {
+                tokio::time::sleep(tokio::time::Duration::from_secs(secs)).await;
+                info!("Execution {} of task {} timed out after {} seconds", execution_id, id, secs);
+                let (resp, _) = oneshot::channel();
+                let reason = AbortReason::Timeout(secs);
+                inner_tx
+                    .send(ExecutorMessage::Abort { id: execution_id, reason, resp })
+                    .await
+                    .unwrap_or_default();
+            })
+        });
+        // println!("handle-end {}", task.get_id());
+        self.task_handles.push(TaskHandle {
+            inner_handle: handle,
+            id,
+            execution_id,
+            abort_tx,
+            timeout_handle,
+        });
+        // Reactor panics because of dropped response.
+        // To truly abort the task we need to store its resp too
+        // handle.abort();
+    }
+    fn is_running(&self, task_id: Uuid) -> bool {
+        self.task_handles.iter().any(|t| t.id == task_id)
+    }
+    // Starts the queued run of the task once nothing else of it is running
+    fn start_queued(&mut self, task_id: Uuid, inner_tx: tokio::sync::mpsc::Sender<ExecutorMessage>) {
+        if self.is_running(task_id) {
+            return;
+        }
+        if let Some(index) = self.queue.iter().position(|q| q.task.get_id() == task_id) {
+            let queued = self.queue.remove(index);
+            self.start(
+                queued.task,
+                queued.execution_id,
+                queued.timeout,
+                queued.starting,
+                queued.resp,
+                inner_tx,
+            );
+        }
+    }
     pub async fn listen(
         &mut self,
         mut rx: tokio::sync::mpsc::Receiver<ExecutorMessage>,
@@ -40,79 +161,109 @@ impl Executor {
             info!("Executor got message: {}", message.get_type());
             let inner_tx = tx.clone();
             match message {
-                ExecutorMessage::Execute { mut task, resp } => {
+                ExecutorMessage::Execute { task, execution_id, timeout, overlap, starting, resp } => {
                     let id = task.get_id();
-                    let (abort_tx, abort_rx) = tokio::sync::oneshot::channel::<bool>();
-                    info!("Executing task: {}", id);
-                    let handle = tokio::spawn(async move {
-                        let result = Self::execute(&mut task).await;
-                        resp.send(result).unwrap_or_default();
-                        // task.abort().await;
-                        // We can listen for the abort oneshot
-
-                        if let Ok(_) = abort_rx.await {
-                            info!("Aborting task {}", id);
-                            task.abort().await;
-                            // if let Ok(_) = inner_tx.send(ExecutorMessage::Abort { id }).await {}
+                    if self.is_running(id) {
+                        match overlap {
+                            OverlapPolicy::Allow => {}
+                            OverlapPolicy::Skip => {
+                                info!("Task {} is still running, skipping", id);
+                                resp.send(Ok(Self::never_started(AbortReason::Skipped))).unwrap_or_default();
+                                continue;
+                            }
+                            OverlapPolicy::Queue => {
+                                if self.queue.iter().any(|q| q.task.get_id() == id) {
+                                    info!("Task {} already has a queued run, skipping", id);
+                                    resp.send(Ok(Self::never_started(AbortReason::Skipped))).unwrap_or_default();
+                                } else {
+                                    info!("Task {} is still running, queueing", id);
+                                    self.queue.push(QueuedExecution {
+                                        task,
+                                        execution_id,
+                                        timeout,
+                                        starting,
+                                        resp,
+                                    });
+                                }
+                                continue;
+                            }
+                            OverlapPolicy::Replace => {
+                                info!("Task {} is still running, replacing", id);
+                                let (abort_resp, _) = oneshot::channel();
+                                self.abort_task(id, AbortReason::Replaced, abort_resp).await;
+                            }
                         }
-                        info!("Aborting timespan finished.");
-                    });
-                    // println!("handle-end {}", task.get_id());
-                    self.task_handles.push(TaskHandle {
-                        inner_handle: handle,
-                        id,
-                        abort_tx,
-                    });
-                    // Reactor panics because of dropped response.
-                    // To truly abort the task we need to store its resp too
-                    // handle.abort();
+                    }
+                    self.start(task, execution_id, timeout, starting, resp, inner_tx);
                 }
-                ExecutorMessage::ExecutionFinished { id } => {
-                    info!("Execution of task: {} is finished", id);
-                    if let Some(index) = get_handle_index(&mut self.task_handles, id) {
-                        let _val = self.task_handles.remove(index);
+                ExecutorMessage::ExecutionFinished { task_id, execution_id } => {
+                    info!("Execution {} of task: {} is finished", execution_id, task_id);
+                    // Aborted runs are already released
+                    let index = self
+                        .task_handles
+                        .iter()
+                        .position(|t| t.execution_id == execution_id);
+                    if let Some(index) = index {
+                        let _val = self.task_handles.remove(index).release();
                     }
+                    self.start_queued(task_id, inner_tx);
                 }
-                ExecutorMessage::Abort { id, resp } => {
-                    if self.abort_task(id, resp).await {
-                        inner_tx
-                            .send(ExecutorMessage::ExecutionFinished { id })
-                            .await
-                            .unwrap_or_default();
+                ExecutorMessage::Abort { id, reason, resp } => {
+                    if let Some(task_id) = self.abort_task(id, reason, resp).await {
+                        self.start_queued(task_id, inner_tx);
                     }
                 }
-                ExecutorMessage::GetActiveTaskIDs { resp } => {
-                    resp.send(
-                        self.task_handles
-                            .iter()
-                            .map(|t| t.id)
-                            .collect::<Vec<Uuid>>(),
-                    )
-                    .unwrap_or_default();
+                ExecutorMessage::GetExecutions { resp } => {
+                    let running = self.task_handles.iter().map(|t| t.execution_id);
+                    let queued = self.queue.iter().map(|q| q.execution_id);
+                    resp.send(running.chain(queued).collect()).unwrap_or_default();
+                }
+                ExecutorMessage::AbortAll { reason, resp } => {
+                    // Queued runs go first, otherwise they'd start when the others are aborted
+                    let mut aborted = 0;
+                    for queued in self.queue.drain(..) {
+                        queued.resp.send(Ok(Self::never_started(reason))).unwrap_or_default();
+                        aborted += 1;
+                    }
+                    for handle in self.task_handles.iter_mut().filter(|t| !t.abort_tx.is_closed()) {
+                        info!("Aborting execution {} of task {}", handle.execution_id, handle.id);
+                        if let Some(timeout_handle) = handle.timeout_handle.take() {
+                            timeout_handle.abort();
+                        }
+                        // Handle stays until the run reports back, so it can be waited for
+                        let (closed_tx, _) = oneshot::channel();
+                        std::mem::replace(&mut handle.abort_tx, closed_tx).send(reason).unwrap_or_default();
+                        aborted += 1;
+                    }
+                    resp.send(aborted).unwrap_or_default();
                 }
             }
         }
     }
+    // Returns the id of the aborted task, if there was anything to abort
     pub async fn abort_task(
         &mut self,
-        task_id: Uuid,
+        id: Uuid,
+        reason: AbortReason,
         resp: tokio::sync::oneshot::Sender<bool>,
-    ) -> bool {
-        if let Some(index) = get_handle_index(&mut self.task_handles, task_id) {
-            info!("Found the task to abort: {}", task_id);
-            let val = self.task_handles.remove(index);
-            val.abort_tx.send(true).unwrap_or_default();
+    ) -> Option<Uuid> {
+        let mut task_id = None;
+        while let Some(index) = get_handle_index(&mut self.task_handles, id) {
+            let handle = self.task_handles.remove(index);
+            info!("Found the execution to abort: {}", handle.execution_id);
+            task_id = Some(handle.id);
+            let abort_tx = handle.release();
+            abort_tx.send(reason).unwrap_or_default();
             // val.inner_handle.abort();
-            resp.send(true).unwrap_or_default();
-            return true;
         }
-        resp.send(false).unwrap_or_default();
-        return false;
+        resp.send(task_id.is_some()).unwrap_or_default();
+        return task_id;
     }
 }
 
-fn get_handle_index(task_handles: &mut Vec<TaskHandle>, task_id: Uuid) -> Option<usize> {
-    let predicate = |t: &TaskHandle| t.id == task_id;
+// Finds the handle of a run that's still going on, by its execution id or its task id
+fn get_handle_index(task_handles: &mut Vec<TaskHandle>, id: Uuid) -> Option<usize> {
+    let predicate = |t: &TaskHandle| (t.execution_id == id || t.id == id) && !t.abort_tx.is_closed();
     let mut i: usize = 0;
     while i != task_handles.len() {
         if predicate(&mut task_handles[i]) {
@@ -135,6 +286,8 @@ mod tests {
     fn create_executor() -> Executor {
         let executor = Executor {
             task_handles: Vec::default(),
+            queue: Vec::default(),
+            default_timeout: None,
         };
         return executor;
     }
@@ -173,6 +326,10 @@ mod tests {
         match tx
             .send(ExecutorMessage::Execute {
                 task,
+                execution_id: Uuid::new_v4(),
+                timeout: None,
+                overlap: OverlapPolicy::Allow,
+                starting: oneshot::channel().0,
                 resp: exec_tx,
             })
             .await
@@ -181,7 +338,7 @@ mod tests {
             Err(_) => panic!("Should never happen"),
         };
         let (abort_tx, abort_rx) = oneshot::channel();
-        match tx.send(ExecutorMessage::Abort { id, resp: abort_tx }).await {
+        match tx.send(ExecutorMessage::Abort { id, reason: AbortReason::Manual, resp: abort_tx }).await {
             Ok(_) => {}
             Err(_) => panic!("Check abort task, probably receiver is dropped?"),
         };
@@ -196,8 +353,11 @@ mod tests {
             }
         };
         let mut res = exec_rx.await.unwrap().unwrap();
-        let none = res.next().await;
-        assert_eq!(none, None);
+        // Only the line about how it was stopped
+        let stopped = res.output.next().await.unwrap();
+        assert_eq!(stopped.stream, crate::models::OutputStream::System);
+        let none = res.output.next().await;
+        assert_eq!(none.is_none(), true);
         tokio::time::sleep(tokio::time::Duration::from_millis(110)).await;
     }
 
@@ -205,6 +365,7 @@ mod tests {
     async fn task_handles() {
         let task = create_boxed_long_task().await;
         let id = task.get_id();
+        let execution_id = Uuid::new_v4();
         let handle = tokio::spawn(async move {
             tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         });
@@ -212,12 +373,17 @@ mod tests {
         let handle = TaskHandle {
             inner_handle: handle,
             id,
+            execution_id,
             abort_tx: tx,
+            timeout_handle: None,
         };
         let mut task_handles = vec![handle];
         let index = get_handle_index(&mut task_handles, id);
         assert_eq!(index.is_some(), true);
         assert_eq!(index.unwrap(), 0);
+        let index = get_handle_index(&mut task_handles, execution_id);
+        assert_eq!(index, Some(0));
+        assert_eq!(get_handle_index(&mut task_handles, Uuid::new_v4()).is_none(), true);
     }
 
     #[tokio::test]
@@ -233,6 +399,10 @@ mod tests {
         match tx
             .send(ExecutorMessage::Execute {
                 task,
+                execution_id: Uuid::new_v4(),
+                timeout: None,
+                overlap: OverlapPolicy::Allow,
+                starting: oneshot::channel().0,
                 resp: exec_tx,
             })
             .await
@@ -241,8 +411,156 @@ mod tests {
             Err(_) => panic!("Should never happen"),
         };
         let mut result = exec_rx.await.unwrap().unwrap();
-        let output = result.next().await;
-        assert_eq!(output.unwrap(), String::from("Hey hey hey"));
+        let output = result.output.next().await;
+        assert_eq!(output.unwrap().line, String::from("Hey hey hey"));
         cleanup().await;
     }
+
+    #[tokio::test]
+    async fn timeout_aborts_task() {
+        let (tx, rx) = mpsc::channel(32);
+        let mut executor = create_executor();
+        let inner_tx = tx.clone();
+        let _handle = tokio::spawn(async move {
+            executor.listen(rx, inner_tx).await;
+        });
+        let task = Box::new(CmdTask::new(Uuid::new_v4(), Box::new("sleep 5".into())));
+        let (exec_tx, exec_rx) = oneshot::channel();
+        match tx
+            .send(ExecutorMessage::Execute {
+                task,
+                execution_id: Uuid::new_v4(),
+                timeout: Some(1),
+                overlap: OverlapPolicy::Allow,
+                starting: oneshot::channel().0,
+                resp: exec_tx,
+            })
+            .await
+        {
+            Ok(_) => {}
+            Err(_) => panic!("Should never happen"),
+        };
+        let execution = exec_rx.await.unwrap().unwrap();
+        let status = execution.completion.await.unwrap();
+        assert_eq!(status.successful, false);
+        assert_eq!(status.aborted, Some(AbortReason::Timeout(1)));
+    }
+
+    #[tokio::test]
+    async fn skips_overlapping_runs() {
+        let (tx, rx) = mpsc::channel(32);
+        let mut executor = create_executor();
+        let inner_tx = tx.clone();
+        let _handle = tokio::spawn(async move {
+            executor.listen(rx, inner_tx).await;
+        });
+        let id = Uuid::new_v4();
+        let mut receivers = vec![];
+        for _ in 0..2 {
+            let task = Box::new(CmdTask::new(id, Box::new("sleep 5".into())));
+            let (exec_tx, exec_rx) = oneshot::channel();
+            tx.send(ExecutorMessage::Execute {
+                task,
+                execution_id: Uuid::new_v4(),
+                timeout: None,
+                overlap: OverlapPolicy::Skip,
+                starting: oneshot::channel().0,
+                resp: exec_tx,
+            })
+            .await
+            .unwrap_or_default();
+            receivers.push(exec_rx);
+        }
+        let skipped = receivers.pop().unwrap().await.unwrap().unwrap();
+        let status = skipped.completion.await.unwrap();
+        assert_eq!(status.aborted, Some(AbortReason::Skipped));
+        let (abort_tx, abort_rx) = oneshot::channel();
+        tx.send(ExecutorMessage::Abort { id, reason: AbortReason::Manual, resp: abort_tx })
+            .await
+            .unwrap_or_default();
+        assert_eq!(abort_rx.await.unwrap(), true);
+    }
+
+    #[tokio::test]
+    async fn aborts_single_execution() {
+        let (tx, rx) = mpsc::channel(32);
+        let mut executor = create_executor();
+        let inner_tx = tx.clone();
+        let _handle = tokio::spawn(async move {
+            executor.listen(rx, inner_tx).await;
+        });
+        let id = Uuid::new_v4();
+        let mut executions = vec![];
+        for _ in 0..2 {
+            let task = Box::new(CmdTask::new(id, Box::new("sleep 5".into())));
+            let execution_id = Uuid::new_v4();
+            let (exec_tx, exec_rx) = oneshot::channel();
+            tx.send(ExecutorMessage::Execute {
+                task,
+                execution_id,
+                timeout: None,
+                overlap: OverlapPolicy::Allow,
+                starting: oneshot::channel().0,
+                resp: exec_tx,
+            })
+            .await
+            .unwrap_or_default();
+            executions.push((execution_id, exec_rx.await.unwrap().unwrap()));
+        }
+        let (second_id, second) = executions.pop().unwrap();
+        let (first_id, first) = executions.pop().unwrap();
+        let (abort_tx, abort_rx) = oneshot::channel();
+        tx.send(ExecutorMessage::Abort { id: first_id, reason: AbortReason::Manual, resp: abort_tx })
+            .await
+            .unwrap_or_default();
+        assert_eq!(abort_rx.await.unwrap(), true);
+        assert_eq!(first.completion.await.unwrap().aborted, Some(AbortReason::Manual));
+        let mut second_completion = second.completion;
+        assert_eq!(second_completion.try_recv().is_err(), true);
+        let (abort_tx, abort_rx) = oneshot::channel();
+        tx.send(ExecutorMessage::Abort { id: second_id, reason: AbortReason::Manual, resp: abort_tx })
+            .await
+            .unwrap_or_default();
+        assert_eq!(abort_rx.await.unwrap(), true);
+        assert_eq!(second_completion.await.unwrap().aborted, Some(AbortReason::Manual));
+    }
+    #[tokio::test]
+    async fn aborts_everything_on_shutdown() {
+        let (tx, rx) = mpsc::channel(32);
+        let mut executor = create_executor();
+        let inner_tx = tx.clone();
+        let _handle = tokio::spawn(async move {
+            executor.listen(rx, inner_tx).await;
+        });
+        let id = Uuid::new_v4();
+        let mut receivers = vec![];
+        for _ in 0..2 {
+            let task = Box::new(CmdTask::new(id, Box::new("sleep 5".into())));
+            let (exec_tx, exec_rx) = oneshot::channel();
+            tx.send(ExecutorMessage::Execute {
+                task,
+                execution_id: Uuid::new_v4(),
+                timeout: None,
+                overlap: OverlapPolicy::Queue,
+                starting: oneshot::channel().0,
+                resp: exec_tx,
+            })
+            .await
+            .unwrap_or_default();
+            receivers.push(exec_rx);
+        }
+        let (abort_tx, abort_rx) = oneshot::channel();
+        tx.send(ExecutorMessage::AbortAll { reason: AbortReason::Shutdown, resp: abort_tx })
+            .await
+            .unwrap_or_default();
+        assert_eq!(abort_rx.await.unwrap(), 2);
+        for exec_rx in receivers {
+            let status = exec_rx.await.unwrap().unwrap().completion.await.unwrap();
+            assert_eq!(status.aborted, Some(AbortReason::Shutdown));
+        }
+        // Aborted run is still listed until it reports back
+        let (get_tx, get_rx) = oneshot::channel();
+        tx.send(ExecutorMessage::GetExecutions { resp: get_tx }).await.unwrap_or_default();
+        assert_eq!(get_rx.await.unwrap().len(), 1);
+    }
 }
@@ -1,27 +1,42 @@
 use uuid::Uuid;
 
 use crate::{
-    models::TaskError,
-    traits::BoxedStream,
-    types::{BoxedTask, OneShotMessageResponse},
+    models::{AbortReason, TaskError},
+    tasks::OverlapPolicy,
+    types::{BoxedTask, OneShotMessageResponse, TaskExecution},
 };
 
 pub enum ExecutorMessage {
     Execute {
         task: BoxedTask,
-        resp: OneShotMessageResponse<Result<BoxedStream, TaskError>>,
+        execution_id: Uuid,
+        // In seconds, overrides the default timeout of the Executor
+        timeout: Option<u64>,
+        overlap: OverlapPolicy,
+        // Fired when the run leaves the queue, dropped if it never does
+        starting: OneShotMessageResponse<()>,
+        resp: OneShotMessageResponse<Result<TaskExecution, TaskError>>,
     },
     ExecutionFinished {
-        id: Uuid,
+        task_id: Uuid,
+        execution_id: Uuid,
         // resp: OneShotMessageResponse<bool>
     },
     Abort {
+        // Either an execution id or a task id, the latter aborts every run of the task
         id: Uuid,
+        reason: AbortReason,
         resp: OneShotMessageResponse<bool>,
     },
-    GetActiveTaskIDs {
+    // Execution ids of the runs that are going on or waiting in the queue
+    GetExecutions {
         resp: OneShotMessageResponse<Vec<Uuid>>,
     },
+    // Aborts every run and empties the queue, responds with the number of runs cut short
+    AbortAll {
+        reason: AbortReason,
+        resp: OneShotMessageResponse<usize>,
+    },
 }
 
 impl ExecutorMessage {
@@ -30,7 +45,8 @@ impl ExecutorMessage {
             ExecutorMessage::Execute { .. } => "Execute",
             ExecutorMessage::ExecutionFinished { .. } => "ExecutionFinished",
             ExecutorMessage::Abort { .. } => "Abort",
-            ExecutorMessage::GetActiveTaskIDs { .. } => "GetActiveTaskIDs",
+            ExecutorMessage::GetExecutions { .. } => "GetExecutions",
+            ExecutorMessage::AbortAll { .. } => "AbortAll",
         }
     }
 }
@@ -2,6 +2,7 @@
 use executor::Executor;
 use models::{
     OutputModel,
+    SecretsKey,
     // TaskModel
 };
 #[cfg(feature = "docker")]
@@ -16,6 +17,7 @@ use tasks::{
     // CmdTask,
     // DockerImageType,
     // DockerTask,
+    Redactor,
     TaskWatcher
 };
 use tracing::subscriber::set_global_default;
@@ -65,6 +67,19 @@ async fn main() {
             panic!("Database connection failed. Check if your connection URL is correct and your DB is reachable.")
         }
     };
+    let secrets_key_path = CONFIG.secrets_key_path.as_ref().map(|p| config::Config::resolve_path(p));
+    let secrets_key = match SecretsKey::load(secrets_key_path.as_deref()) {
+        Ok(Some(key)) => Some(Arc::new(key)),
+        Ok(None) => {
+            tracing::info!("No secrets key is configured, tasks can't use secrets.");
+            None
+        }
+        Err(e) => panic!("{}", e.to_string()),
+    };
+    let redact_patterns = match Redactor::compile_patterns(&CONFIG.redact_patterns) {
+        Ok(p) => Arc::new(p),
+        Err(e) => panic!("{}", e.to_string()),
+    };
     let (db_tx, db_rx) = tokio::sync::mpsc::channel(32);
     let (ex_tx, ex_rx) = tokio::sync::mpsc::channel(32);
     let (tw_tx, tw_rx) = tokio::sync::mpsc::channel(32);
@@ -86,6 +101,8 @@ async fn main() {
     tokio::spawn(async {
         let mut executor = Executor {
             task_handles: Vec::default(),
+            queue: Vec::default(),
+            default_timeout: CONFIG.default_timeout,
         };
         executor.listen(ex_rx, inner_ex_tx).await;
     });
@@ -109,6 +126,21 @@ async fn main() {
         server.listen(host, port).await;
     });
 
+    let (tx, rx) = tokio::sync::mpsc::channel(128);
+    // Both the signals and the stop command of the socket end up here
+    let (shutdown_tx, mut shutdown_rx) = tokio::sync::mpsc::channel::<()>(1);
+    let reactor_tx = tx.clone();
+    tokio::spawn(async move {
+        if shutdown_rx.recv().await.is_some() {
+            // Any other request fails from now on
+            drop(shutdown_rx);
+            println!("Shutting down...");
+            Reactor::shutdown(&reactor_tx, CONFIG.shutdown_grace_period).await;
+            println!("Goodbye!");
+            socket::unix_utils::exit();
+        }
+    });
+    let signal_shutdown_tx = shutdown_tx.clone();
     tokio::spawn(async move {
         if cfg!(unix) {
             use tokio::signal::unix::*;
@@ -117,23 +149,27 @@ async fn main() {
             let mut quit = signal(SignalKind::quit()).unwrap();
             let mut term = signal(SignalKind::terminate()).unwrap();
 
-            tokio::select! {
-                v = hup.recv() => v.unwrap(),
-                v = int.recv() => v.unwrap(),
-                v = quit.recv() => v.unwrap(),
-                v = term.recv() => v.unwrap(),
+            loop {
+                tokio::select! {
+                    v = hup.recv() => v.unwrap(),
+                    v = int.recv() => v.unwrap(),
+                    v = quit.recv() => v.unwrap(),
+                    v = term.recv() => v.unwrap(),
+                }
+                // Asking twice means it shouldn't wait for the tasks
+                if signal_shutdown_tx.try_send(()).is_err() {
+                    println!("Goodbye!");
+                    socket::unix_utils::exit();
+                }
             }
-
-            println!("Goodbye!");
-            socket::unix_utils::exit();
         }
     });
+    let socket_reactor_tx = tx.clone();
     tokio::spawn(async move {
-        socket::SocketManager::listen().await;
+        socket::SocketManager::listen(shutdown_tx, socket_reactor_tx).await;
     });
     let _ = tokio::spawn(async {
         let server_receiver = Arc::new(tokio::sync::Mutex::new(sv_rx));
-        let (tx, rx) = tokio::sync::mpsc::channel(128);
         let mut reactor = Reactor {
             db_sender: db_tx,
             executor_sender: ex_tx,
@@ -141,6 +177,8 @@ async fn main() {
             output_emitter: o_tx,
             server_receiver,
             inner_sender: tx,
+            secrets_key,
+            redact_patterns,
         };
         reactor.listen(rx).await;
     })
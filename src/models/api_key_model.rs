@@ -0,0 +1,139 @@
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::now;
+
+// What an API key is allowed to do
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    TasksRead,
+    TasksWrite,
+    Execute,
+    Abort,
+    ReportsRead,
+    AuditRead,
+    SecretsRead,
+    SecretsWrite,
+}
+
+impl ToString for Scope {
+    fn to_string(&self) -> String {
+        match self {
+            Scope::TasksRead => String::from("tasks:read"),
+            Scope::TasksWrite => String::from("tasks:write"),
+            Scope::Execute => String::from("execute"),
+            Scope::Abort => String::from("abort"),
+            Scope::ReportsRead => String::from("reports:read"),
+            Scope::AuditRead => String::from("audit:read"),
+            Scope::SecretsRead => String::from("secrets:read"),
+            Scope::SecretsWrite => String::from("secrets:write"),
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tasks:read" => Ok(Scope::TasksRead),
+            "tasks:write" => Ok(Scope::TasksWrite),
+            "execute" => Ok(Scope::Execute),
+            "abort" => Ok(Scope::Abort),
+            "reports:read" => Ok(Scope::ReportsRead),
+            "audit:read" => Ok(Scope::AuditRead),
+            "secrets:read" => Ok(Scope::SecretsRead),
+            "secrets:write" => Ok(Scope::SecretsWrite),
+            _ => Err(anyhow::anyhow!(
+                "Unknown scope {}, possible values: tasks:read, tasks:write, execute, abort, reports:read, audit:read, secrets:read, secrets:write",
+                s
+            )),
+        }
+    }
+}
+
+impl Scope {
+    // Comma separated, like "tasks:read,execute"
+    pub fn parse_list(scopes: &str) -> Result<Vec<Scope>, anyhow::Error> {
+        let mut parsed = Vec::new();
+        for scope in scopes.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            let scope = Scope::from_str(scope)?;
+            if !parsed.contains(&scope) {
+                parsed.push(scope);
+            }
+        }
+        if parsed.is_empty() {
+            return Err(anyhow::anyhow!("An API key needs at least one scope"));
+        }
+        return Ok(parsed);
+    }
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, Serialize)]
+pub struct ApiKeyModel {
+    pub id: Uuid,
+    pub name: String,
+    // Hex SHA-256 of the key, the key itself is only shown once when it's created
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    // Beginning of the key, so people can tell their keys apart
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+impl ApiKeyModel {
+    // Returns the model to store and the key to hand out
+    pub fn generate(name: String, scopes: &[Scope]) -> (Self, String) {
+        let key = format!(
+            "sx_{}{}",
+            Uuid::new_v4().to_simple().to_string(),
+            Uuid::new_v4().to_simple().to_string()
+        );
+        let model = Self {
+            id: Uuid::new_v4(),
+            name,
+            key_hash: Self::hash(&key),
+            key_prefix: key.chars().take(11).collect(),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            created_at: now!(),
+            last_used_at: None,
+            revoked_at: None,
+        };
+        return (model, key);
+    }
+    // Keys are random enough that a plain hash is enough, no need for a slow one
+    pub fn hash(key: &str) -> String {
+        hex::encode(Sha256::digest(key.as_bytes()))
+    }
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        let scope = scope.to_string();
+        self.scopes.iter().any(|s| s == &scope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_keys() {
+        let scopes = Scope::parse_list("tasks:read, execute,tasks:read").unwrap();
+        assert_eq!(scopes, vec![Scope::TasksRead, Scope::Execute]);
+        assert!(Scope::parse_list("tasks:delete").is_err());
+        assert!(Scope::parse_list("").is_err());
+
+        let (model, key) = ApiKeyModel::generate(String::from("ci"), &scopes);
+        assert_eq!(model.key_hash, ApiKeyModel::hash(&key));
+        assert_ne!(model.key_hash, ApiKeyModel::hash(&key[1..]));
+        assert!(key.starts_with(&model.key_prefix));
+        assert!(model.has_scope(Scope::Execute));
+        assert!(!model.has_scope(Scope::Abort));
+    }
+}
@@ -0,0 +1,140 @@
+use chrono::NaiveDateTime;
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use super::TaskModel;
+use crate::now;
+
+// Who is behind an action
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ActorKind {
+    ApiKey,
+    User,
+    // Unix socket, only the user running StewardX can get in
+    Socket,
+    // Hook url without a key, vouched for by the task's secret
+    Hook,
+}
+
+impl ToString for ActorKind {
+    fn to_string(&self) -> String {
+        match self {
+            ActorKind::ApiKey => String::from("api_key"),
+            ActorKind::User => String::from("user"),
+            ActorKind::Socket => String::from("socket"),
+            ActorKind::Hook => String::from("hook"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Actor {
+    pub kind: ActorKind,
+    // Id of the key or the user
+    pub id: Option<Uuid>,
+    pub name: String,
+    pub source_ip: Option<String>,
+    // Like "POST /tasks/#id#"
+    pub request: String,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, Serialize)]
+pub struct AuditModel {
+    pub id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub actor_type: String,
+    pub actor_id: Option<Uuid>,
+    pub actor_name: String,
+    pub source_ip: Option<String>,
+    pub request: String,
+    pub action: String,
+    // task, api_key or user
+    pub target_type: String,
+    pub target_id: Option<Uuid>,
+    pub execution_id: Option<Uuid>,
+    pub summary: String,
+    // JSON object of the fields that changed, with their values before and after
+    #[serde(serialize_with = "serialize_diff")]
+    pub diff: Option<String>,
+}
+
+fn serialize_diff<S: Serializer>(diff: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    let diff = diff.as_ref().and_then(|d| serde_json::from_str::<Value>(d).ok());
+    diff.serialize(serializer)
+}
+
+impl AuditModel {
+    pub fn new(actor: &Actor, action: &str, target_type: &str, target_id: Option<Uuid>, summary: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            created_at: now!(),
+            actor_type: actor.kind.to_string(),
+            actor_id: actor.id,
+            actor_name: actor.name.clone(),
+            source_ip: actor.source_ip.clone(),
+            request: actor.request.clone(),
+            action: action.to_string(),
+            target_type: target_type.to_string(),
+            target_id,
+            execution_id: None,
+            summary,
+            diff: None,
+        }
+    }
+    pub fn with_execution(mut self, execution_id: Option<Uuid>) -> Self {
+        self.execution_id = execution_id;
+        self
+    }
+    pub fn with_diff(mut self, before: Option<&TaskModel>, after: Option<&TaskModel>) -> Self {
+        self.diff = Self::diff(before, after);
+        self
+    }
+    // None when nothing changed
+    pub fn diff(before: Option<&TaskModel>, after: Option<&TaskModel>) -> Option<String> {
+        let to_map = |task: Option<&TaskModel>| match task.map(serde_json::to_value) {
+            Some(Ok(Value::Object(map))) => map,
+            _ => Map::new(),
+        };
+        let before = to_map(before);
+        let after = to_map(after);
+        let mut diff = Map::new();
+        for key in before.keys().chain(after.keys()) {
+            // Changes with every update, the entry has its own time
+            if key == "updated_at" || diff.contains_key(key) {
+                continue;
+            }
+            let old = before.get(key).cloned().unwrap_or(Value::Null);
+            let new = after.get(key).cloned().unwrap_or(Value::Null);
+            if old != new {
+                diff.insert(key.clone(), serde_json::json!({ "before": old, "after": new }));
+            }
+        }
+        if diff.is_empty() {
+            return None;
+        }
+        return Some(Value::Object(diff).to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_tasks() {
+        let before = TaskModel::new(None, String::from("backup"), String::from("CmdTask"), String::from("{}"), String::from("Hook"));
+        let mut after = before.clone();
+        after.task_name = String::from("nightly backup");
+        after.hook_secret = Some(String::from("hunter2"));
+        after.updated_at = now!();
+
+        let diff: Value = serde_json::from_str(&AuditModel::diff(Some(&before), Some(&after)).unwrap()).unwrap();
+        assert_eq!(diff, serde_json::json!({ "task_name": { "before": "backup", "after": "nightly backup" } }));
+        assert_eq!(AuditModel::diff(Some(&before), Some(&before)), None);
+
+        let created: Value = serde_json::from_str(&AuditModel::diff(None, Some(&after)).unwrap()).unwrap();
+        assert_eq!(created["task_name"]["before"], Value::Null);
+        assert_eq!(created["id"]["after"], serde_json::json!(after.id));
+    }
+}
@@ -0,0 +1,81 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use super::{HookPayload, TaskModel};
+
+// What started the run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trigger {
+    Cron,
+    Hook,
+    Manual,
+    Retry,
+}
+
+impl ToString for Trigger {
+    fn to_string(&self) -> String {
+        match self {
+            Trigger::Cron => String::from("cron"),
+            Trigger::Hook => String::from("hook"),
+            Trigger::Manual => String::from("manual"),
+            Trigger::Retry => String::from("retry"),
+        }
+    }
+}
+
+// Handed to the task before it's executed, so it knows why and when it runs
+#[derive(Debug, Clone)]
+pub struct ExecutionContext {
+    pub task_id: Uuid,
+    pub task_name: String,
+    pub execution_id: Uuid,
+    // When the run was supposed to start, the time it was requested if it's not scheduled
+    pub scheduled_at: NaiveDateTime,
+    pub trigger: Trigger,
+    pub attempt: u32,
+    pub payload: Option<Arc<HookPayload>>,
+    // Values of the secrets the task references, by name
+    pub secrets: Arc<HashMap<String, String>>,
+}
+
+impl ExecutionContext {
+    pub fn new(task: &TaskModel, scheduled_at: NaiveDateTime, trigger: Trigger, attempt: u32) -> Self {
+        Self {
+            task_id: task.id,
+            task_name: task.task_name.clone(),
+            execution_id: Uuid::new_v4(),
+            scheduled_at,
+            trigger,
+            attempt,
+            payload: None,
+            secrets: Arc::default(),
+        }
+    }
+    pub fn with_payload(mut self, payload: Option<Arc<HookPayload>>) -> Self {
+        self.payload = payload;
+        self
+    }
+    pub fn with_secrets(mut self, secrets: HashMap<String, String>) -> Self {
+        self.secrets = Arc::new(secrets);
+        self
+    }
+    pub fn to_env(&self) -> Vec<(String, String)> {
+        let mut env = vec![
+            (String::from("STEWARDX_TASK_ID"), self.task_id.to_string()),
+            (String::from("STEWARDX_TASK_NAME"), self.task_name.clone()),
+            (String::from("STEWARDX_EXECUTION_ID"), self.execution_id.to_string()),
+            (
+                String::from("STEWARDX_SCHEDULED_AT"),
+                self.scheduled_at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            ),
+            (String::from("STEWARDX_TRIGGER"), self.trigger.to_string()),
+            (String::from("STEWARDX_ATTEMPT"), self.attempt.to_string()),
+        ];
+        if let Some(payload) = &self.payload {
+            env.extend(payload.to_env());
+        }
+        return env;
+    }
+}
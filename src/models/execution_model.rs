@@ -0,0 +1,128 @@
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::now;
+
+use super::{AbortReason, ExecutionReport};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ExecutionState {
+    // Requested, waiting for the Executor
+    Queued,
+    // Executor picked it up, the task is being prepared (pulling images etc.)
+    Starting,
+    Running,
+    Succeeded,
+    Failed,
+    Aborted,
+    TimedOut,
+    Skipped,
+}
+
+impl ExecutionState {
+    pub fn is_finished(&self) -> bool {
+        !matches!(
+            self,
+            ExecutionState::Queued | ExecutionState::Starting | ExecutionState::Running
+        )
+    }
+    pub fn from_report(report: &ExecutionReport) -> Self {
+        if report.successful {
+            return ExecutionState::Succeeded;
+        }
+        match report.aborted {
+            Some(AbortReason::Manual) | Some(AbortReason::Replaced) | Some(AbortReason::Shutdown) => {
+                ExecutionState::Aborted
+            }
+            Some(AbortReason::Timeout(_)) => ExecutionState::TimedOut,
+            Some(AbortReason::Skipped) => ExecutionState::Skipped,
+            None => ExecutionState::Failed,
+        }
+    }
+}
+
+impl ToString for ExecutionState {
+    fn to_string(&self) -> String {
+        match &self {
+            ExecutionState::Queued => return String::from("Queued"),
+            ExecutionState::Starting => return String::from("Starting"),
+            ExecutionState::Running => return String::from("Running"),
+            ExecutionState::Succeeded => return String::from("Succeeded"),
+            ExecutionState::Failed => return String::from("Failed"),
+            ExecutionState::Aborted => return String::from("Aborted"),
+            ExecutionState::TimedOut => return String::from("TimedOut"),
+            ExecutionState::Skipped => return String::from("Skipped"),
+        }
+    }
+}
+
+impl FromStr for ExecutionState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Queued" => Ok(ExecutionState::Queued),
+            "Starting" => Ok(ExecutionState::Starting),
+            "Running" => Ok(ExecutionState::Running),
+            "Succeeded" => Ok(ExecutionState::Succeeded),
+            "Failed" => Ok(ExecutionState::Failed),
+            "Aborted" => Ok(ExecutionState::Aborted),
+            "TimedOut" => Ok(ExecutionState::TimedOut),
+            "Skipped" => Ok(ExecutionState::Skipped),
+            _ => Err(anyhow::anyhow!(
+                "Unknown execution state {}, possible values: Queued, Starting, Running, Succeeded, Failed, Aborted, TimedOut, Skipped",
+                s
+            )),
+        }
+    }
+}
+
+// Where a run lives outside of StewardX
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionProcess {
+    Pid(u32),
+    Container(String),
+}
+
+// A single run of a task, the report of the run shares its id
+#[derive(sqlx::FromRow, Debug, Clone, Serialize)]
+pub struct ExecutionModel {
+    pub id: Uuid,
+    pub task_id: Uuid,
+    pub state: String,
+    pub attempt: i32,
+    pub queued_at: NaiveDateTime,
+    pub starting_at: Option<NaiveDateTime>,
+    pub running_at: Option<NaiveDateTime>,
+    pub finished_at: Option<NaiveDateTime>,
+    pub updated_at: NaiveDateTime,
+    pub pid: Option<i32>,
+    pub container_id: Option<String>,
+}
+
+impl ExecutionModel {
+    pub fn new(id: Uuid, task_id: Uuid, attempt: i32) -> Self {
+        Self {
+            id,
+            task_id,
+            state: ExecutionState::Queued.to_string(),
+            attempt,
+            queued_at: now!(),
+            starting_at: None,
+            running_at: None,
+            finished_at: None,
+            updated_at: now!(),
+            pid: None,
+            container_id: None,
+        }
+    }
+    pub fn get_process(&self) -> Option<ExecutionProcess> {
+        if let Some(container_id) = &self.container_id {
+            return Some(ExecutionProcess::Container(container_id.clone()));
+        }
+        self.pid.map(|pid| ExecutionProcess::Pid(pid as u32))
+    }
+}
@@ -1,54 +1,63 @@
+use std::collections::BTreeMap;
+
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::now;
 
-#[derive(sqlx::FromRow, Debug, Serialize, Deserialize)]
+use super::{AbortReason, ExitStatus, OutputLine};
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ExecutionReport {
     pub id: Uuid,
     pub task_id: Uuid,
     pub created_at: NaiveDateTime,
     pub successful: bool,
-    pub output: Vec<String>,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub container_status: Option<i64>,
+    pub http_status: Option<i32>,
+    pub http_headers: Option<BTreeMap<String, String>>,
+    pub output: Vec<OutputLine>,
+    // Starts from 1, retries of a run point to the report of its first attempt
+    pub attempt: i32,
+    pub original_id: Option<Uuid>,
+    #[serde(skip)]
+    pub aborted: Option<AbortReason>,
 }
 
 impl ExecutionReport {
-    pub fn new(task_id: Uuid, successful: bool, output: Vec<String>) -> Self {
-        Self {
-            id: Uuid::new_v4(),
-            task_id,
-            created_at: now!(),
-            successful,
-            output,
-        }
+    // Reports share the id of the execution they belong to
+    pub fn new(id: Uuid, task_id: Uuid, status: ExitStatus, output: Vec<OutputLine>) -> Self {
+        Self::new_raw(id, task_id, now!(), status, output)
     }
     pub fn new_raw(
         id: Uuid,
         task_id: Uuid,
         created_at: NaiveDateTime,
-        successful: bool,
-        output: Vec<String>,
+        status: ExitStatus,
+        output: Vec<OutputLine>,
     ) -> Self {
         Self {
             id,
             task_id,
             created_at,
-            successful,
+            successful: status.successful,
+            exit_code: status.exit_code,
+            signal: status.signal,
+            container_status: status.container_status,
+            http_status: status.http_status,
+            http_headers: status.http_headers,
             output,
+            attempt: 1,
+            original_id: None,
+            aborted: status.aborted,
         }
     }
-    pub fn output_as_string(&self) -> String {
-        self.output.join("\n").to_string()
-    }
-    pub fn new_string_output(
-        id: Uuid,
-        task_id: Uuid,
-        created_at: NaiveDateTime,
-        successful: bool,
-        output: String,
-    ) -> Self {
-        let output = output.split("\n").map(|l| l.to_string()).collect();
-        Self::new_raw(id, task_id, created_at, successful, output)
+    pub fn with_attempt(mut self, attempt: i32, original_id: Option<Uuid>) -> Self {
+        self.attempt = attempt;
+        self.original_id = original_id;
+        self
     }
 }
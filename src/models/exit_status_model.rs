@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+// Why an execution was cut short, or never started, by StewardX
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AbortReason {
+    Manual,
+    // Seconds the task was allowed to run
+    Timeout(u64),
+    // A newer run took its place
+    Replaced,
+    // Previous run was still going on
+    Skipped,
+    // StewardX stopped before the run could finish
+    Shutdown,
+}
+
+impl AbortReason {
+    pub fn describe(&self) -> String {
+        match self {
+            AbortReason::Manual => String::from("Execution was aborted, it was cut short."),
+            AbortReason::Timeout(secs) => format!("Execution timed out after {} seconds, it was cut short.", secs),
+            AbortReason::Replaced => String::from("Execution was replaced by a newer run, it was cut short."),
+            AbortReason::Skipped => String::from("Execution was skipped, previous run of the task was still in progress."),
+            AbortReason::Shutdown => String::from("StewardX was shutting down, execution was cut short."),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExitStatus {
+    pub successful: bool,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub container_status: Option<i64>,
+    // Set by the HttpTask, repeated headers are joined with commas
+    #[serde(default)]
+    pub http_status: Option<i32>,
+    #[serde(default)]
+    pub http_headers: Option<BTreeMap<String, String>>,
+    #[serde(skip)]
+    pub aborted: Option<AbortReason>,
+}
+
+impl ExitStatus {
+    pub fn new_raw(
+        successful: bool,
+        exit_code: Option<i32>,
+        signal: Option<i32>,
+        container_status: Option<i64>,
+    ) -> Self {
+        Self {
+            successful,
+            exit_code,
+            signal,
+            container_status,
+            http_status: None,
+            http_headers: None,
+            aborted: None,
+        }
+    }
+    pub fn new(successful: bool) -> Self {
+        Self::new_raw(successful, None, None, None)
+    }
+    // Used when the task couldn't tell us how it ended, it's never considered successful
+    pub fn unknown() -> Self {
+        Self::new(false)
+    }
+    pub fn from_process(status: std::process::ExitStatus) -> Self {
+        #[cfg(unix)]
+        let signal = {
+            use std::os::unix::process::ExitStatusExt;
+            status.signal()
+        };
+        #[cfg(not(unix))]
+        let signal = None;
+        Self::new_raw(status.success(), status.code(), signal, None)
+    }
+    pub fn with_http(mut self, status: Option<i32>, headers: Option<BTreeMap<String, String>>) -> Self {
+        self.http_status = status;
+        self.http_headers = headers;
+        self
+    }
+    pub fn abort(mut self, reason: AbortReason) -> Self {
+        self.successful = false;
+        self.aborted = Some(reason);
+        self
+    }
+    pub fn from_container(status_code: u64) -> Self {
+        Self::new_raw(status_code == 0, None, None, Some(status_code as i64))
+    }
+}
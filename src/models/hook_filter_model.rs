@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// One condition on a hook request, on either a header or a value in the JSON body.
+// Without equals or one_of, the value only has to be there
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookFilterRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    // JSON pointer to a value in the body, like "/ref"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equals: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub one_of: Option<Vec<String>>,
+}
+
+// A hook request is only executed when it matches every rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HookFilter {
+    pub rules: Vec<HookFilterRule>,
+}
+
+impl HookFilter {
+    pub fn from_value(value: Value) -> Result<Self, anyhow::Error> {
+        let filter = match serde_json::from_value::<Self>(value) {
+            Ok(f) => f,
+            Err(e) => return Err(anyhow::anyhow!("Malformed hook filters: {}", e)),
+        };
+        for rule in &filter.rules {
+            match (&rule.header, &rule.pointer) {
+                (Some(_), None) => {}
+                (None, Some(pointer)) if pointer.is_empty() || pointer.starts_with('/') => {}
+                (None, Some(pointer)) => {
+                    return Err(anyhow::anyhow!("Hook filter pointer '{}' has to start with '/'", pointer));
+                }
+                _ => return Err(anyhow::anyhow!("Every hook filter needs either a header or a pointer")),
+            }
+            if rule.equals.is_some() && rule.one_of.is_some() {
+                return Err(anyhow::anyhow!("A hook filter can't have both equals and one_of"));
+            }
+        }
+        return Ok(filter);
+    }
+    // Validates the filters coming from the API and turns them into what we store
+    pub fn serialize_value(value: Option<Value>) -> Result<Option<String>, anyhow::Error> {
+        match value {
+            None | Some(Value::Null) => Ok(None),
+            Some(value) => {
+                let filter = Self::from_value(value)?;
+                if filter.rules.is_empty() {
+                    return Ok(None);
+                }
+                Ok(Some(serde_json::to_string(&filter)?))
+            }
+        }
+    }
+    // Returns why the request doesn't match
+    pub fn check(&self, headers: &hyper::HeaderMap, body: &[u8]) -> Result<(), String> {
+        // Only parsed when a rule needs it
+        let mut json: Option<Option<Value>> = None;
+        for rule in &self.rules {
+            let (name, value) = match (&rule.header, &rule.pointer) {
+                (Some(header), _) => {
+                    let value = headers
+                        .get(header.as_str())
+                        .map(|v| String::from_utf8_lossy(v.as_bytes()).to_string());
+                    (format!("header {}", header), value)
+                }
+                (None, Some(pointer)) => {
+                    let body = json.get_or_insert_with(|| serde_json::from_slice(body).ok());
+                    let value = body.as_ref().and_then(|b| b.pointer(pointer)).map(|v| match v {
+                        Value::String(s) => s.clone(),
+                        v => v.to_string(),
+                    });
+                    (format!("body {}", pointer), value)
+                }
+                (None, None) => continue,
+            };
+            let value = match value {
+                Some(v) => v,
+                None => return Err(format!("{} is missing", name)),
+            };
+            if let Some(expected) = &rule.equals {
+                if &value != expected {
+                    return Err(format!("{} is '{}', expected '{}'", name, value, expected));
+                }
+            }
+            if let Some(expected) = &rule.one_of {
+                if !expected.contains(&value) {
+                    return Err(format!("{} is '{}', expected one of {}", name, value, expected.join(", ")));
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_headers_and_body() {
+        let filter = HookFilter::from_value(serde_json::json!([
+            { "header": "X-GitHub-Event", "equals": "push" },
+            { "pointer": "/ref", "one_of": ["refs/heads/main", "refs/heads/release"] },
+            { "pointer": "/forced", "equals": "false" }
+        ]))
+        .unwrap();
+        let mut headers = hyper::HeaderMap::new();
+        headers.insert("X-GitHub-Event", "push".parse().unwrap());
+        let body = br#"{"ref":"refs/heads/main","forced":false}"#;
+        assert_eq!(filter.check(&headers, body), Ok(()));
+        assert_eq!(
+            filter.check(&headers, br#"{"ref":"refs/heads/dev","forced":false}"#),
+            Err(String::from("body /ref is 'refs/heads/dev', expected one of refs/heads/main, refs/heads/release"))
+        );
+        assert_eq!(filter.check(&headers, b"not json"), Err(String::from("body /ref is missing")));
+        headers.insert("X-GitHub-Event", "issue_comment".parse().unwrap());
+        assert_eq!(
+            filter.check(&headers, body),
+            Err(String::from("header X-GitHub-Event is 'issue_comment', expected 'push'"))
+        );
+        assert!(HookFilter::from_value(serde_json::json!([{ "pointer": "ref" }])).is_err());
+        assert!(HookFilter::from_value(serde_json::json!([{ "equals": "push" }])).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+// Request that triggered the run through the hook url
+#[derive(Debug, Clone, Default)]
+pub struct HookPayload {
+    pub body: Vec<u8>,
+    pub content_type: Option<String>,
+    // Only the headers that are allowed in config, with lowercase names
+    pub headers: HashMap<String, String>,
+    pub query: HashMap<String, String>,
+}
+
+impl HookPayload {
+    // Bigger bodies are only on stdin or in the mounted file
+    const MAX_ENV_BODY: usize = 32 * 1024;
+
+    pub fn to_env(&self) -> Vec<(String, String)> {
+        let mut env = vec![(String::from("STEWARDX_HOOK_PAYLOAD_SIZE"), self.body.len().to_string())];
+        if let Some(content_type) = &self.content_type {
+            env.push((String::from("STEWARDX_HOOK_CONTENT_TYPE"), content_type.clone()));
+        }
+        if self.body.len() <= Self::MAX_ENV_BODY {
+            if let Ok(body) = std::str::from_utf8(&self.body) {
+                env.push((String::from("STEWARDX_HOOK_PAYLOAD"), body.to_string()));
+            }
+        }
+        for (name, value) in &self.headers {
+            env.push((format!("STEWARDX_HOOK_HEADER_{}", Self::env_name(name)), value.clone()));
+        }
+        for (name, value) in &self.query {
+            env.push((format!("STEWARDX_HOOK_QUERY_{}", Self::env_name(name)), value.clone()));
+        }
+        // A process can't be given a variable with a NUL byte in it
+        env.retain(|(_, value)| !value.contains('\0'));
+        return env;
+    }
+    fn env_name(name: &str) -> String {
+        name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_env() {
+        let mut payload = HookPayload {
+            body: br#"{"ref":"main"}"#.to_vec(),
+            content_type: Some(String::from("application/json")),
+            ..HookPayload::default()
+        };
+        payload.headers.insert(String::from("x-github-event"), String::from("push"));
+        payload.query.insert(String::from("branch-name"), String::from("main\0"));
+        let env: HashMap<String, String> = payload.to_env().into_iter().collect();
+        assert_eq!(env["STEWARDX_HOOK_PAYLOAD"], r#"{"ref":"main"}"#);
+        assert_eq!(env["STEWARDX_HOOK_HEADER_X_GITHUB_EVENT"], "push");
+        assert_eq!(env.contains_key("STEWARDX_HOOK_QUERY_BRANCH_NAME"), false);
+    }
+}
@@ -1,8 +1,32 @@
+mod api_key_model;
+mod audit_model;
+mod execution_context_model;
+mod execution_model;
 mod execution_report_model;
+mod exit_status_model;
+mod hook_filter_model;
+mod hook_payload_model;
+mod output_line_model;
 mod output_model;
+mod retry_policy_model;
+mod secret_model;
+mod session_model;
 mod task_error_model;
 mod task_model;
+mod user_model;
+pub use api_key_model::{ApiKeyModel, Scope};
+pub use audit_model::{Actor, ActorKind, AuditModel};
+pub use execution_context_model::{ExecutionContext, Trigger};
+pub use execution_model::{ExecutionModel, ExecutionProcess, ExecutionState};
 pub use execution_report_model::ExecutionReport;
+pub use exit_status_model::{AbortReason, ExitStatus};
+pub use hook_filter_model::HookFilter;
+pub use hook_payload_model::HookPayload;
+pub use output_line_model::{OutputLine, OutputStream};
 pub use output_model::OutputModel;
+pub use retry_policy_model::{FailureKind, RetryPolicy, RetryState};
+pub use secret_model::{SecretModel, SecretsKey};
+pub use session_model::SessionModel;
 pub use task_error_model::TaskError;
 pub use task_model::TaskModel;
+pub use user_model::{Role, UserModel};
@@ -0,0 +1,59 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::now;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+    // Lines written by StewardX itself, like errors of the runtime
+    System,
+}
+
+impl OutputStream {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputStream::Stdout => "stdout",
+            OutputStream::Stderr => "stderr",
+            OutputStream::System => "system",
+        }
+    }
+    pub fn from_str(stream: &str) -> Self {
+        match stream {
+            "stdout" => OutputStream::Stdout,
+            "stderr" => OutputStream::Stderr,
+            _ => OutputStream::System,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputLine {
+    // Order of the line in its execution, set by the TaskWatcher
+    pub sequence: i64,
+    pub stream: OutputStream,
+    pub timestamp: NaiveDateTime,
+    pub line: String,
+}
+
+impl OutputLine {
+    pub fn new_raw(sequence: i64, stream: OutputStream, timestamp: NaiveDateTime, line: String) -> Self {
+        Self {
+            sequence,
+            stream,
+            timestamp,
+            line,
+        }
+    }
+    pub fn new(stream: OutputStream, line: String) -> Self {
+        Self::new_raw(0, stream, now!(), line)
+    }
+    pub fn stdout(line: String) -> Self {
+        Self::new(OutputStream::Stdout, line)
+    }
+    pub fn system(line: String) -> Self {
+        Self::new(OutputStream::System, line)
+    }
+}
@@ -1,21 +1,16 @@
-use chrono::NaiveDateTime;
 use uuid::Uuid;
 
-use crate::now;
+use super::OutputLine;
 
 #[derive(Debug, Clone)]
 pub struct OutputModel {
     task_id: Uuid,
-    timestamp: NaiveDateTime,
-    output: String,
+    execution_id: Uuid,
+    line: OutputLine,
 }
 
 impl OutputModel {
-    pub fn new(task_id: Uuid, output: String) -> Self {
-        Self {
-            task_id,
-            timestamp: now!(),
-            output,
-        }
+    pub fn new(task_id: Uuid, execution_id: Uuid, line: OutputLine) -> Self {
+        Self { task_id, execution_id, line }
     }
 }
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::HookPayload;
+
+// Delays are in seconds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Backoff {
+    Fixed { delay: u64 },
+    Exponential { initial_delay: u64, max_delay: u64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FailureKind {
+    // Task ran but exited unsuccessfully
+    ExitCode,
+    Timeout,
+    // Task couldn't even be started
+    SpawnError,
+}
+
+fn default_retry_on() -> Vec<FailureKind> {
+    vec![FailureKind::ExitCode, FailureKind::Timeout, FailureKind::SpawnError]
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    // Including the first attempt
+    pub max_attempts: u32,
+    pub backoff: Backoff,
+    #[serde(default = "default_retry_on")]
+    pub retry_on: Vec<FailureKind>,
+}
+
+impl RetryPolicy {
+    pub fn from_value(value: serde_json::Value) -> Result<Self, anyhow::Error> {
+        let policy = match serde_json::from_value::<Self>(value) {
+            Ok(p) => p,
+            Err(e) => return Err(anyhow::anyhow!("Malformed retry policy: {}", e)),
+        };
+        if policy.max_attempts == 0 {
+            return Err(anyhow::anyhow!("Retry policy's max_attempts must be at least 1"));
+        }
+        if let Backoff::Exponential { initial_delay, max_delay } = policy.backoff {
+            if initial_delay > max_delay {
+                return Err(anyhow::anyhow!("Retry policy's initial_delay can't be greater than max_delay"));
+            }
+        }
+        return Ok(policy);
+    }
+    // Validates the policy coming from the API and turns it into what we store
+    pub fn serialize_value(value: Option<serde_json::Value>) -> Result<Option<String>, anyhow::Error> {
+        match value {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(value) => {
+                let policy = Self::from_value(value)?;
+                Ok(Some(serde_json::to_string(&policy)?))
+            }
+        }
+    }
+    // Attempts start from 1
+    pub fn should_retry(&self, attempt: u32, failure: FailureKind) -> bool {
+        attempt < self.max_attempts && self.retry_on.contains(&failure)
+    }
+    // Delay before the attempt after the given one
+    pub fn get_delay(&self, attempt: u32) -> u64 {
+        match self.backoff {
+            Backoff::Fixed { delay } => delay,
+            Backoff::Exponential { initial_delay, max_delay } => {
+                let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+                std::cmp::min(initial_delay.saturating_mul(factor), max_delay)
+            }
+        }
+    }
+}
+
+// Travels with an execution, so the Reactor knows which attempt it is
+#[derive(Debug, Clone)]
+pub struct RetryState {
+    pub policy: Option<RetryPolicy>,
+    pub attempt: u32,
+    // Report id of the first attempt
+    pub original_id: Option<Uuid>,
+    // Retries of a hook run get the same payload
+    pub payload: Option<Arc<HookPayload>>,
+}
+
+impl RetryState {
+    pub fn first(policy: Option<RetryPolicy>) -> Self {
+        Self {
+            policy,
+            attempt: 1,
+            original_id: None,
+            payload: None,
+        }
+    }
+    // Returns the state of the next attempt and the delay before it, if the failure is worth a retry
+    pub fn next(&self, report_id: Uuid, failure: FailureKind) -> Option<(Self, u64)> {
+        let policy = self.policy.as_ref()?;
+        if !policy.should_retry(self.attempt, failure) {
+            return None;
+        }
+        let delay = policy.get_delay(self.attempt);
+        let next = Self {
+            policy: self.policy.clone(),
+            attempt: self.attempt + 1,
+            original_id: self.original_id.or(Some(report_id)),
+            payload: self.payload.clone(),
+        };
+        return Some((next, delay));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exponential_backoff_is_capped() {
+        let policy = RetryPolicy::from_value(serde_json::json!({
+            "max_attempts": 5,
+            "backoff": { "type": "Exponential", "initial_delay": 5, "max_delay": 30 }
+        }))
+        .unwrap();
+        let delays: Vec<u64> = (1..5).map(|a| policy.get_delay(a)).collect();
+        assert_eq!(delays, vec![5, 10, 20, 30]);
+        assert_eq!(policy.should_retry(4, FailureKind::Timeout), true);
+        assert_eq!(policy.should_retry(5, FailureKind::Timeout), false);
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use aes_gcm::{
+    aead::{Aead, NewAead, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use chrono::NaiveDateTime;
+use rand_core::{OsRng, RngCore};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::now;
+
+// 256 bit key for AES-GCM, secrets can't be stored or used without it
+pub struct SecretsKey(Aes256Gcm);
+
+impl std::fmt::Debug for SecretsKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretsKey(..)")
+    }
+}
+
+impl SecretsKey {
+    const ENV: &'static str = "STEWARDX_SECRETS_KEY";
+
+    // 64 hex characters, like the output of "openssl rand -hex 32"
+    pub fn from_hex(hex_key: &str) -> Result<Self, anyhow::Error> {
+        let bytes = match hex::decode(hex_key.trim()) {
+            Ok(b) if b.len() == 32 => b,
+            _ => return Err(anyhow::anyhow!("Secrets key should be 64 hex characters (32 bytes)")),
+        };
+        return Ok(Self(Aes256Gcm::new(Key::from_slice(&bytes))));
+    }
+    // The environment variable comes first, then the file in the config
+    pub fn load(key_path: Option<&std::path::Path>) -> Result<Option<Self>, anyhow::Error> {
+        if let Ok(hex_key) = std::env::var(Self::ENV) {
+            return Self::from_hex(&hex_key).map(Some);
+        }
+        let key_path = match key_path {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+        let hex_key = match std::fs::read_to_string(key_path) {
+            Ok(k) => k,
+            Err(e) => return Err(anyhow::anyhow!("Couldn't read the secrets key {}, {}", key_path.display(), e)),
+        };
+        return Self::from_hex(&hex_key).map(Some);
+    }
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, Serialize)]
+pub struct SecretModel {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub nonce: Vec<u8>,
+    // Value is never sent back once it's written
+    #[serde(skip_serializing)]
+    pub ciphertext: Vec<u8>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl SecretModel {
+    const MAX_NAME_LENGTH: usize = 64;
+    const REFERENCE_START: &'static str = "{{secret:";
+    const REFERENCE_END: &'static str = "}}";
+
+    pub fn new(name: String, value: &str, key: &SecretsKey) -> Result<Self, anyhow::Error> {
+        let name = Self::parse_name(name)?;
+        let now = now!();
+        let mut secret = Self {
+            id: Uuid::new_v4(),
+            name,
+            nonce: Vec::default(),
+            ciphertext: Vec::default(),
+            created_at: now,
+            updated_at: now,
+        };
+        secret.set_value(value, key)?;
+        return Ok(secret);
+    }
+    pub fn parse_name(name: String) -> Result<String, anyhow::Error> {
+        let name = name.trim().to_string();
+        if name.is_empty() || name.len() > Self::MAX_NAME_LENGTH {
+            return Err(anyhow::anyhow!(
+                "Secret name should be between 1 and {} characters long",
+                Self::MAX_NAME_LENGTH
+            ));
+        }
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.') {
+            return Err(anyhow::anyhow!("Secret name can only have letters, digits and the characters _ - ."));
+        }
+        return Ok(name);
+    }
+    // Every write gets a fresh nonce
+    pub fn set_value(&mut self, value: &str, key: &SecretsKey) -> Result<(), anyhow::Error> {
+        if value.is_empty() {
+            return Err(anyhow::anyhow!("Value of the secret is empty"));
+        }
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+        let payload = Payload { msg: value.as_bytes(), aad: self.name.as_bytes() };
+        let ciphertext = match key.0.encrypt(Nonce::from_slice(&nonce), payload) {
+            Ok(c) => c,
+            Err(_) => return Err(anyhow::anyhow!("Couldn't encrypt secret {}", self.name)),
+        };
+        self.nonce = nonce.to_vec();
+        self.ciphertext = ciphertext;
+        self.updated_at = now!();
+        return Ok(());
+    }
+    // Fails with a different key, or if the row was tampered with
+    pub fn decrypt(&self, key: &SecretsKey) -> Result<String, anyhow::Error> {
+        if self.nonce.len() != 12 {
+            return Err(anyhow::anyhow!("Secret {} is corrupted", self.name));
+        }
+        let payload = Payload { msg: self.ciphertext.as_slice(), aad: self.name.as_bytes() };
+        let value = match key.0.decrypt(Nonce::from_slice(&self.nonce), payload) {
+            Ok(v) => v,
+            Err(_) => return Err(anyhow::anyhow!("Couldn't decrypt secret {}, is it the same key?", self.name)),
+        };
+        return String::from_utf8(value).map_err(|_| anyhow::anyhow!("Secret {} isn't valid UTF-8", self.name));
+    }
+    // Names of the secrets referenced like {{secret:NAME}}
+    pub fn references(text: &str) -> Vec<String> {
+        let mut names = vec![];
+        let mut rest = text;
+        while let Some((name, after)) = Self::next_reference(rest) {
+            if let Some(name) = name {
+                if !names.contains(&name.to_string()) {
+                    names.push(name.to_string());
+                }
+            }
+            rest = after;
+        }
+        return names;
+    }
+    // References to secrets that aren't in the map are left as they are
+    pub fn resolve(text: &str, secrets: &HashMap<String, String>) -> String {
+        if secrets.is_empty() {
+            return text.to_string();
+        }
+        let mut resolved = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(Self::REFERENCE_START) {
+            let (name, after) = match Self::next_reference(rest) {
+                Some(r) => r,
+                None => break,
+            };
+            let skipped = rest.len() - after.len();
+            match name.and_then(|name| secrets.get(name)) {
+                Some(value) => {
+                    resolved.push_str(&rest[..start]);
+                    resolved.push_str(value);
+                }
+                None => resolved.push_str(&rest[..skipped]),
+            }
+            rest = after;
+        }
+        resolved.push_str(rest);
+        return resolved;
+    }
+    // Name of the next reference if it's a valid one, and the text after it
+    fn next_reference(text: &str) -> Option<(Option<&str>, &str)> {
+        let start = text.find(Self::REFERENCE_START)?;
+        let after_start = &text[start + Self::REFERENCE_START.len()..];
+        // Anything that isn't a reference is only skipped up to its start, there might be one in it
+        let end = match after_start.find(Self::REFERENCE_END) {
+            Some(e) => e,
+            None => return Some((None, after_start)),
+        };
+        let name = after_start[..end].trim();
+        if Self::parse_name(name.to_string()).is_err() {
+            return Some((None, after_start));
+        }
+        return Some((Some(name), &after_start[end + Self::REFERENCE_END.len()..]));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypts_and_resolves() {
+        let key = SecretsKey::from_hex(&"ab".repeat(32)).unwrap();
+        let secret = SecretModel::new(String::from("DB_PASSWORD"), "hunter2", &key).unwrap();
+        assert_ne!(secret.ciphertext, b"hunter2".to_vec());
+        assert_eq!(secret.decrypt(&key).unwrap(), "hunter2");
+        assert!(secret.decrypt(&SecretsKey::from_hex(&"cd".repeat(32)).unwrap()).is_err());
+        // Ciphertext is bound to the name
+        let mut moved = secret.clone();
+        moved.name = String::from("OTHER");
+        assert!(moved.decrypt(&key).is_err());
+        assert!(SecretsKey::from_hex("abcd").is_err());
+        assert!(SecretModel::parse_name(String::from("no spaces")).is_err());
+
+        let text = "psql -U {{secret:DB_USER}} -W {{ secret:DB_PASSWORD }} {{secret:DB_PASSWORD}} {{secret:MISSING}} {{secret:}} {{secret: {{secret:DB_USER}}";
+        assert_eq!(SecretModel::references(text), vec!["DB_USER", "DB_PASSWORD", "MISSING"]);
+        let mut secrets = HashMap::new();
+        secrets.insert(String::from("DB_USER"), String::from("admin"));
+        secrets.insert(String::from("DB_PASSWORD"), secret.decrypt(&key).unwrap());
+        assert_eq!(
+            SecretModel::resolve(text, &secrets),
+            "psql -U admin -W {{ secret:DB_PASSWORD }} hunter2 {{secret:MISSING}} {{secret:}} {{secret: admin"
+        );
+    }
+}
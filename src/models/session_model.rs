@@ -0,0 +1,53 @@
+use chrono::{Duration, NaiveDateTime};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::now;
+
+// Login of a user, the token lives in the session cookie
+#[derive(sqlx::FromRow, Debug, Clone, Serialize)]
+pub struct SessionModel {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    // Hex SHA-256 of the token, the token itself is only in the cookie
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    // Has to come back in the X-CSRF-Token header of the requests that change something
+    #[serde(skip_serializing)]
+    pub csrf_token: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+impl SessionModel {
+    pub const COOKIE_NAME: &'static str = "stewardx_session";
+
+    // Returns the model to store and the token to put in the cookie
+    pub fn generate(user_id: Uuid, lifetime: u64) -> (Self, String) {
+        let token = Self::random_token();
+        let created_at = now!();
+        let model = Self {
+            id: Uuid::new_v4(),
+            user_id,
+            token_hash: Self::hash(&token),
+            csrf_token: Self::random_token(),
+            created_at,
+            expires_at: created_at + Duration::seconds(lifetime as i64),
+        };
+        return (model, token);
+    }
+    fn random_token() -> String {
+        format!(
+            "{}{}",
+            Uuid::new_v4().to_simple().to_string(),
+            Uuid::new_v4().to_simple().to_string()
+        )
+    }
+    pub fn hash(token: &str) -> String {
+        hex::encode(Sha256::digest(token.as_bytes()))
+    }
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= now!()
+    }
+}
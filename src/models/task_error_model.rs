@@ -2,7 +2,7 @@ use chrono::NaiveDateTime;
 use uuid::Uuid;
 
 use crate::now;
-#[derive(sqlx::FromRow, Debug)]
+#[derive(sqlx::FromRow, Debug, Clone)]
 pub struct TaskError {
     pub id: Uuid,
     pub task_id: Uuid,
@@ -46,11 +46,32 @@ impl TaskError {
             format!("Generic error occurred: {}", error),
         );
     }
-    pub fn invalid_cmd(task_id: Uuid, command: String) -> Self {
+    pub fn invalid_cmd(task_id: Uuid, command: String, reason: &str) -> Self {
         return Self::new(
             task_id,
             "InvalidCmd".to_string(),
-            format!("Invalid command specified. Command: {}", command),
+            format!("Invalid command specified, {}. Command: {}", reason, command),
+        );
+    }
+    pub fn unauthorized_hook(task_id: Uuid, reason: &str) -> Self {
+        return Self::new(
+            task_id,
+            "UnauthorizedHook".to_string(),
+            format!("Hook request was rejected, {}.", reason),
+        );
+    }
+    pub fn spawn_error(task_id: Uuid, error: String) -> Self {
+        return Self::new(
+            task_id,
+            "SpawnError".to_string(),
+            format!("Task couldn't be started: {}", error),
+        );
+    }
+    pub fn timeout(task_id: Uuid, secs: u64) -> Self {
+        return Self::new(
+            task_id,
+            "Timeout".to_string(),
+            format!("Timeout occurred for task, it was aborted after {} seconds.", secs),
         );
     }
     // pub fn docker_image_not_found(task_id: Uuid, image: String) -> Self {
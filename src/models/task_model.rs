@@ -2,7 +2,9 @@ use chrono::NaiveDateTime;
 use serde::Serialize;
 use uuid::Uuid;
 
-use crate::{now, tasks::Frequency};
+use crate::{now, tasks::{Frequency, OverlapPolicy}};
+
+use super::{HookFilter, RetryPolicy};
 #[derive(sqlx::FromRow, Debug, Clone, Serialize)]
 pub struct TaskModel {
     pub id: Uuid,
@@ -15,6 +17,19 @@ pub struct TaskModel {
     pub last_execution: Option<NaiveDateTime>,
     pub next_execution: Option<NaiveDateTime>,
     pub exec_count: i64,
+    // In seconds, when it's empty the default in config is used
+    pub timeout: Option<i64>,
+    // Serialized RetryPolicy
+    pub retry_policy: Option<String>,
+    pub overlap_policy: String,
+    // Hook requests have to be signed with it or carry it as a token, never sent back
+    #[serde(skip_serializing)]
+    pub hook_secret: Option<String>,
+    // Path of the task under /hooks/, like "github/deploy"
+    pub hook_slug: Option<String>,
+    pub hook_method: String,
+    // Serialized HookFilter
+    pub hook_filters: Option<String>,
 }
 
 impl TaskModel {
@@ -58,6 +73,13 @@ impl TaskModel {
             last_execution: None,
             next_execution: None,
             exec_count: 0,
+            timeout: None,
+            retry_policy: None,
+            overlap_policy: OverlapPolicy::default().to_string(),
+            hook_secret: None,
+            hook_slug: None,
+            hook_method: String::from("POST"),
+            hook_filters: None,
         };
         task.next_execution = task.calc_next_execution();
         return task;
@@ -84,10 +106,92 @@ impl TaskModel {
             last_execution: None,
             next_execution: None,
             exec_count: 0,
+            timeout: None,
+            retry_policy: None,
+            overlap_policy: OverlapPolicy::default().to_string(),
+            hook_secret: None,
+            hook_slug: None,
+            hook_method: String::from("POST"),
+            hook_filters: None,
         };
         task.next_execution = task.calc_next_execution();
         return task;
     }
+    pub fn get_timeout(&self) -> Option<u64> {
+        self.timeout.map(|t| t as u64)
+    }
+    pub fn get_retry_policy(&self) -> Option<RetryPolicy> {
+        let policy = self.retry_policy.as_ref()?;
+        match serde_json::from_str::<RetryPolicy>(policy) {
+            Ok(p) => Some(p),
+            Err(e) => {
+                tracing::error!("Retry policy of task {} is malformed: {}", self.id, e);
+                None
+            }
+        }
+    }
+    pub fn get_hook_filter(&self) -> Option<HookFilter> {
+        let filter = self.hook_filters.as_ref()?;
+        match serde_json::from_str::<HookFilter>(filter) {
+            Ok(f) => Some(f),
+            Err(e) => {
+                tracing::error!("Hook filters of task {} are malformed: {}", self.id, e);
+                None
+            }
+        }
+    }
+    pub fn get_overlap_policy(&self) -> OverlapPolicy {
+        <OverlapPolicy as std::str::FromStr>::from_str(&self.overlap_policy).unwrap_or_default()
+    }
+    // Validates the policy coming from the API, Allow is the default
+    pub fn parse_overlap_policy(policy: Option<String>) -> Result<String, anyhow::Error> {
+        match policy {
+            Some(p) => Ok(<OverlapPolicy as std::str::FromStr>::from_str(&p)?.to_string()),
+            None => Ok(OverlapPolicy::default().to_string()),
+        }
+    }
+    pub fn validate_timeout(timeout: Option<i64>) -> Result<(), anyhow::Error> {
+        match timeout {
+            Some(t) if t <= 0 => Err(anyhow::anyhow!("Timeout must be a positive number of seconds")),
+            _ => Ok(()),
+        }
+    }
+    // An empty secret removes it
+    pub fn parse_hook_secret(secret: String) -> Option<String> {
+        match secret.is_empty() {
+            true => None,
+            false => Some(secret),
+        }
+    }
+    // Slugs are lowercase words separated by slashes, an empty one removes it
+    pub fn parse_hook_slug(slug: Option<String>) -> Result<Option<String>, anyhow::Error> {
+        let slug = match slug {
+            Some(s) if !s.trim_matches('/').is_empty() => s.trim_matches('/').to_string(),
+            _ => return Ok(None),
+        };
+        let valid = slug.len() <= 100
+            && slug.split('/').all(|part| {
+                !part.is_empty()
+                    && part.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+            });
+        if !valid {
+            return Err(anyhow::anyhow!(
+                "Hook slug '{}' is invalid, it can only have lowercase letters, digits, '-', '_' and '/'",
+                slug
+            ));
+        }
+        return Ok(Some(slug));
+    }
+    pub fn parse_hook_method(method: Option<String>) -> Result<String, anyhow::Error> {
+        let method = method.unwrap_or_else(|| String::from("POST")).to_uppercase();
+        match method.as_str() {
+            "GET" | "POST" | "PUT" | "PATCH" | "DELETE" => Ok(method),
+            _ => Err(anyhow::anyhow!(
+                "Hook method '{}' is invalid, possible values: GET, POST, PUT, PATCH, DELETE",
+                method
+            )),
+        }
+    }
     pub fn get_serde_from_props(
         id: Uuid,
         task_type: String,
@@ -103,7 +207,26 @@ impl TaskModel {
             "DockerTask" => {
                 return crate::tasks::DockerTask::get_serde_from_props(id, task_props);
             }
+            #[cfg(feature = "http")]
+            "HttpTask" => {
+                return crate::tasks::HttpTask::get_serde_from_props(id, task_props);
+            }
             _ => return Err(anyhow::anyhow!("Unknown task type {}", task_type)),
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hook_slug() {
+        assert_eq!(TaskModel::parse_hook_slug(Some("/github/deploy-prod/".into())).unwrap(), Some("github/deploy-prod".into()));
+        assert_eq!(TaskModel::parse_hook_slug(Some("".into())).unwrap(), None);
+        assert!(TaskModel::parse_hook_slug(Some("github//deploy".into())).is_err());
+        assert!(TaskModel::parse_hook_slug(Some("Deploy?now".into())).is_err());
+        assert_eq!(TaskModel::parse_hook_method(Some("get".into())).unwrap(), "GET");
+        assert!(TaskModel::parse_hook_method(Some("TRACE".into())).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use std::str::FromStr;
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use chrono::NaiveDateTime;
+use rand_core::OsRng;
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::Scope;
+use crate::now;
+
+// What a user of the panel is allowed to do
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Role {
+    Viewer,
+    Operator,
+    Admin,
+}
+
+impl ToString for Role {
+    fn to_string(&self) -> String {
+        match self {
+            Role::Viewer => String::from("viewer"),
+            Role::Operator => String::from("operator"),
+            Role::Admin => String::from("admin"),
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(Role::Viewer),
+            "operator" => Ok(Role::Operator),
+            "admin" => Ok(Role::Admin),
+            _ => Err(anyhow::anyhow!(
+                "Unknown role {}, possible values: viewer, operator, admin",
+                s
+            )),
+        }
+    }
+}
+
+impl Role {
+    // Roles are sets of the API key scopes, so both go through the same checks
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        match self {
+            Role::Viewer => matches!(scope, Scope::TasksRead | Scope::ReportsRead),
+            Role::Operator => !matches!(scope, Scope::TasksWrite | Scope::AuditRead | Scope::SecretsWrite),
+            Role::Admin => true,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, Serialize)]
+pub struct UserModel {
+    pub id: Uuid,
+    pub username: String,
+    // Argon2id PHC string, has its own salt and parameters
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    pub role: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl UserModel {
+    const MIN_PASSWORD_LENGTH: usize = 8;
+    const MAX_USERNAME_LENGTH: usize = 64;
+
+    pub fn new(username: String, password: &str, role: Role) -> Result<Self, anyhow::Error> {
+        let username = Self::parse_username(username)?;
+        let password_hash = Self::hash_password(password)?;
+        let now = now!();
+        return Ok(Self {
+            id: Uuid::new_v4(),
+            username,
+            password_hash,
+            role: role.to_string(),
+            created_at: now,
+            updated_at: now,
+        });
+    }
+    pub fn parse_username(username: String) -> Result<String, anyhow::Error> {
+        let username = username.trim().to_lowercase();
+        if username.is_empty() || username.len() > Self::MAX_USERNAME_LENGTH {
+            return Err(anyhow::anyhow!(
+                "Username should be between 1 and {} characters long",
+                Self::MAX_USERNAME_LENGTH
+            ));
+        }
+        if !username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' || c == '@')
+        {
+            return Err(anyhow::anyhow!(
+                "Username can only have letters, digits and the characters - _ . @"
+            ));
+        }
+        return Ok(username);
+    }
+    // Slow on purpose, call it from a blocking thread
+    pub fn hash_password(password: &str) -> Result<String, anyhow::Error> {
+        if password.chars().count() < Self::MIN_PASSWORD_LENGTH {
+            return Err(anyhow::anyhow!(
+                "Password should be at least {} characters long",
+                Self::MIN_PASSWORD_LENGTH
+            ));
+        }
+        let salt = SaltString::generate(&mut OsRng);
+        return match Argon2::default().hash_password(password.as_bytes(), &salt) {
+            Ok(hash) => Ok(hash.to_string()),
+            Err(e) => Err(anyhow::anyhow!("Couldn't hash the password, {}", e)),
+        };
+    }
+    pub fn verify_password(&self, password: &str) -> bool {
+        let hash = match PasswordHash::new(&self.password_hash) {
+            Ok(h) => h,
+            Err(_) => return false,
+        };
+        Argon2::default().verify_password(password.as_bytes(), &hash).is_ok()
+    }
+    pub fn get_role(&self) -> Role {
+        // Column is only written from a parsed role, viewer is the safe fallback anyway
+        Role::from_str(&self.role).unwrap_or(Role::Viewer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_passwords() {
+        let user = UserModel::new(String::from(" Admin "), "correct horse", Role::Admin).unwrap();
+        assert_eq!(user.username, "admin");
+        assert_ne!(user.password_hash, "correct horse");
+        assert!(user.verify_password("correct horse"));
+        assert!(!user.verify_password("correct horse "));
+        assert!(UserModel::new(String::from("admin"), "short", Role::Admin).is_err());
+        assert!(UserModel::parse_username(String::from("no spaces")).is_err());
+
+        assert!(Role::Viewer.has_scope(Scope::ReportsRead));
+        assert!(!Role::Viewer.has_scope(Scope::Execute));
+        assert!(Role::Operator.has_scope(Scope::Abort));
+        assert!(!Role::Operator.has_scope(Scope::TasksWrite));
+        assert!(!Role::Operator.has_scope(Scope::AuditRead));
+        assert!(!Role::Operator.has_scope(Scope::SecretsWrite));
+        assert!(Role::Admin.has_scope(Scope::TasksWrite));
+    }
+}
@@ -2,9 +2,12 @@ use chrono::NaiveDateTime;
 use uuid::Uuid;
 
 use crate::{
-    models::{ExecutionReport, OutputModel, TaskError, TaskModel},
-    traits::BoxedStream,
-    types::{BoxedTask, OneShotMessageResponse},
+    models::{
+        Actor, ApiKeyModel, AuditModel, ExecutionContext, ExecutionModel, HookPayload, ExecutionReport, ExecutionState,
+        OutputModel, RetryState, Role, Scope, SecretModel, SessionModel, TaskError, TaskModel, UserModel,
+    },
+    tasks::OverlapPolicy,
+    types::{BoxedTask, OneShotMessageResponse, TaskExecution},
 };
 
 pub enum ReactorMessage {
@@ -17,14 +20,38 @@ pub enum ReactorMessage {
     },
     ExecuteTask {
         task: BoxedTask,
+        // Created when the run is requested, the report of the run gets its execution id
+        context: ExecutionContext,
+        // In seconds
+        timeout: Option<u64>,
+        overlap: OverlapPolicy,
+        retry: RetryState,
         // resp: ComposedResponse<BoxedStream>
     },
     CreateExecutionReport {
         report: ExecutionReport,
     },
+    // Stops scheduling, waits for the running executions up to the grace period then aborts the rest
+    Shutdown {
+        // In seconds
+        grace_period: u64,
+        resp: OneShotMessageResponse<()>,
+    },
+    // A run that was going on when StewardX stopped
+    ReconcileExecution {
+        execution: ExecutionModel,
+    },
+    // Follows a run again after a restart, it doesn't get a new execution
+    ReattachExecution {
+        task: BoxedTask,
+        execution: ExecutionModel,
+        timeout: Option<u64>,
+    },
     WatchExecution {
         task_id: Uuid,
-        exec_process: Result<BoxedStream, TaskError>,
+        execution_id: Uuid,
+        exec_process: Result<TaskExecution, TaskError>,
+        retry: RetryState,
         // output_resp: OutputSender,
         // resp: OneShotMessageResponse<ExecutionReport>
     },
@@ -34,7 +61,10 @@ pub enum ReactorMessage {
     },
     ExecutionFinished {
         id: Uuid,
+        execution_id: Uuid,
         should_update: bool,
+        // Next attempt and the seconds to wait before it
+        next_attempt: Option<(RetryState, u64)>,
     },
     CreateError {
         error: TaskError
@@ -47,23 +77,55 @@ pub enum ReactorMessage {
         task_id: Uuid,
         resp: OneShotMessageResponse<anyhow::Result<TaskModel>>,
     },
+    ServerGetTaskBySlug {
+        slug: String,
+        resp: OneShotMessageResponse<anyhow::Result<TaskModel>>,
+    },
     ServerCreateTask {
         task_name: String,
         frequency: String,
         task_type: String,
         task_props: serde_json::Value,
+        timeout: Option<i64>,
+        retry_policy: Option<serde_json::Value>,
+        overlap_policy: Option<String>,
+        hook_secret: Option<String>,
+        hook_slug: Option<String>,
+        hook_method: Option<String>,
+        hook_filters: Option<serde_json::Value>,
+        actor: Actor,
         resp: OneShotMessageResponse<anyhow::Result<TaskModel>>,
     },
     ServerExecuteTask {
         task_id: Uuid,
-        resp: OneShotMessageResponse<bool>,
+        payload: Option<HookPayload>,
+        actor: Actor,
+        // Execution id of the run
+        resp: OneShotMessageResponse<Option<Uuid>>,
     },
     ServerAbortTask {
-        task_id: Uuid,
+        // Either an execution id or a task id
+        id: Uuid,
+        actor: Actor,
         resp: OneShotMessageResponse<bool>,
     },
+    ServerGetExecution {
+        execution_id: Uuid,
+        resp: OneShotMessageResponse<anyhow::Result<ExecutionModel>>,
+    },
+    ServerNotifyWhenFinished {
+        execution_id: Uuid,
+        resp: OneShotMessageResponse<tokio::sync::oneshot::Receiver<()>>,
+    },
+    ServerGetExecutions {
+        offset: Option<i64>,
+        state: Option<ExecutionState>,
+        task_id: Option<Uuid>,
+        resp: ComposedResponse<Vec<ExecutionModel>>,
+    },
     ServerDeleteTask {
         task_id: Uuid,
+        actor: Actor,
         resp: OneShotMessageResponse<anyhow::Result<TaskModel>>,
     },
     ServerGetActiveTasks {
@@ -74,6 +136,14 @@ pub enum ReactorMessage {
         task_name: String,
         frequency: String,
         task_props: serde_json::Value,
+        timeout: Option<i64>,
+        retry_policy: Option<serde_json::Value>,
+        overlap_policy: Option<String>,
+        hook_secret: Option<String>,
+        hook_slug: Option<String>,
+        hook_method: Option<String>,
+        hook_filters: Option<serde_json::Value>,
+        actor: Actor,
         resp: OneShotMessageResponse<anyhow::Result<TaskModel>>,
     },
     ServerGetExecutionReportsForTask {
@@ -92,6 +162,89 @@ pub enum ReactorMessage {
     UpdateTaskExecution {
         task_id: Uuid,
     },
+    // Responds with the stored key and the key itself, which isn't stored anywhere
+    CreateApiKey {
+        name: String,
+        scopes: Vec<Scope>,
+        actor: Actor,
+        resp: ComposedResponse<(ApiKeyModel, String)>,
+    },
+    GetApiKeys {
+        resp: ComposedResponse<Vec<ApiKeyModel>>,
+    },
+    RevokeApiKey {
+        id: Uuid,
+        actor: Actor,
+        resp: ComposedResponse<ApiKeyModel>,
+    },
+    ServerGetApiKey {
+        key_hash: String,
+        resp: ComposedResponse<ApiKeyModel>,
+    },
+    CreateUser {
+        username: String,
+        password: String,
+        role: Role,
+        actor: Actor,
+        resp: ComposedResponse<UserModel>,
+    },
+    GetUsers {
+        resp: ComposedResponse<Vec<UserModel>>,
+    },
+    // Changing the password logs the user out everywhere
+    UpdateUser {
+        id: Uuid,
+        password: Option<String>,
+        role: Option<Role>,
+        actor: Actor,
+        resp: ComposedResponse<UserModel>,
+    },
+    DeleteUser {
+        id: Uuid,
+        actor: Actor,
+        resp: ComposedResponse<UserModel>,
+    },
+    ServerLogin {
+        username: String,
+        password: String,
+        resp: ComposedResponse<(UserModel, SessionModel, String)>,
+    },
+    ServerGetSession {
+        token_hash: String,
+        resp: ComposedResponse<(UserModel, SessionModel)>,
+    },
+    ServerLogout {
+        session_id: Uuid,
+        resp: ComposedResponse<u64>,
+    },
+    ServerGetAuditEntries {
+        offset: Option<i64>,
+        target_id: Option<Uuid>,
+        action: Option<String>,
+        actor_type: Option<String>,
+        resp: ComposedResponse<Vec<AuditModel>>,
+    },
+    // Only the names, values never leave the Reactor
+    GetSecrets {
+        resp: ComposedResponse<Vec<SecretModel>>,
+    },
+    CreateSecret {
+        name: String,
+        value: String,
+        actor: Actor,
+        resp: ComposedResponse<SecretModel>,
+    },
+    UpdateSecret {
+        name: String,
+        value: String,
+        actor: Actor,
+        resp: ComposedResponse<SecretModel>,
+    },
+    DeleteSecret {
+        name: String,
+        actor: Actor,
+        resp: ComposedResponse<SecretModel>,
+    },
 }
 
 // type AnyResult<T> = Result<T, anyhow::Error>;
@@ -104,6 +257,9 @@ impl ReactorMessage {
             ReactorMessage::ExecuteScheduledTasks { .. } => "ExecuteScheduledTasks",
             ReactorMessage::ExecuteTask { .. } => "ExecuteTask",
             ReactorMessage::CreateExecutionReport { .. } => "CreateExecutionReport",
+            ReactorMessage::Shutdown { .. } => "Shutdown",
+            ReactorMessage::ReconcileExecution { .. } => "ReconcileExecution",
+            ReactorMessage::ReattachExecution { .. } => "ReattachExecution",
             ReactorMessage::WatchExecution { .. } => "WatchExecution",
             ReactorMessage::OutputReceived { .. } => "OutputReceived",
             ReactorMessage::ExecutionFinished { .. } => "ExecutionFinished",
@@ -111,10 +267,14 @@ impl ReactorMessage {
             ReactorMessage::ServerCreateTask { .. } => "ServerCreateTask",
             ReactorMessage::ServerExecuteTask { .. } => "ServerExecuteTask",
             ReactorMessage::ServerAbortTask { .. } => "ServerAbortTask",
+            ReactorMessage::ServerGetExecution { .. } => "ServerGetExecution",
+            ReactorMessage::ServerNotifyWhenFinished { .. } => "ServerNotifyWhenFinished",
+            ReactorMessage::ServerGetExecutions { .. } => "ServerGetExecutions",
             ReactorMessage::ServerDeleteTask { .. } => "ServerDeleteTask",
             ReactorMessage::ServerGetActiveTasks { .. } => "ServerGetActiveTasks",
             ReactorMessage::UpdateTaskExecution { .. } => "UpdateTaskExecution",
             ReactorMessage::ServerGetTask { .. } => "ServerGetTask",
+            ReactorMessage::ServerGetTaskBySlug { .. } => "ServerGetTaskBySlug",
             ReactorMessage::ServerUpdateTask { .. } => "ServerUpdateTask",
             ReactorMessage::ServerGetExecutionReportsForTask { .. } => {
                 "ServerGetExecutionReportsForTask"
@@ -122,6 +282,22 @@ impl ReactorMessage {
             ReactorMessage::ServerGetExecutionReports { .. } => "ServerGetExecutionReports",
             ReactorMessage::ServerGetExecutionReport { .. } => "ServerGetExecutionReport",
             ReactorMessage::CreateError { .. } => "CreateError",
+            ReactorMessage::CreateApiKey { .. } => "CreateApiKey",
+            ReactorMessage::GetApiKeys { .. } => "GetApiKeys",
+            ReactorMessage::RevokeApiKey { .. } => "RevokeApiKey",
+            ReactorMessage::ServerGetApiKey { .. } => "ServerGetApiKey",
+            ReactorMessage::CreateUser { .. } => "CreateUser",
+            ReactorMessage::GetUsers { .. } => "GetUsers",
+            ReactorMessage::UpdateUser { .. } => "UpdateUser",
+            ReactorMessage::DeleteUser { .. } => "DeleteUser",
+            ReactorMessage::ServerLogin { .. } => "ServerLogin",
+            ReactorMessage::ServerGetSession { .. } => "ServerGetSession",
+            ReactorMessage::ServerLogout { .. } => "ServerLogout",
+            ReactorMessage::ServerGetAuditEntries { .. } => "ServerGetAuditEntries",
+            ReactorMessage::GetSecrets { .. } => "GetSecrets",
+            ReactorMessage::CreateSecret { .. } => "CreateSecret",
+            ReactorMessage::UpdateSecret { .. } => "UpdateSecret",
+            ReactorMessage::DeleteSecret { .. } => "DeleteSecret",
         };
     }
 }
@@ -1,23 +1,39 @@
 mod messages;
 
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use regex::Regex;
 use tokio::sync::{broadcast, oneshot, Mutex};
 
 use crate::{
     db::DBMessage,
-    executor::ExecutorMessage,
-    models::{ExecutionReport, TaskError, TaskModel},
+    executor::{Executor, ExecutorMessage},
+    models::{
+        AbortReason, ApiKeyModel, AuditModel, ExecutionContext, ExecutionModel, ExecutionProcess, ExecutionReport, ExecutionState, ExitStatus,
+        FailureKind, HookFilter, OutputLine, RetryPolicy, RetryState, SecretModel, SecretsKey, SessionModel, TaskError, TaskModel,
+        Trigger, UserModel,
+    },
     now,
     server::ServerMessage,
-    tasks::TaskWatcherMessage,
+    tasks::{OverlapPolicy, Redactor, TaskWatcherMessage},
     types::{
         DBSender, ExecutorSender, OutputSender, ReactorReceiver, ReactorSender, ServerReceiver,
         TaskWatcherSender,
     },
-    ModelToTask,
+    ModelToTask, CONFIG,
 };
 pub use messages::ReactorMessage;
 
+// Callers waiting for the runs to finish, by execution id
+type ExecutionWaiters = Arc<Mutex<HashMap<uuid::Uuid, Vec<oneshot::Sender<()>>>>>;
+// Loaded with the first run, emptied whenever a secret changes
+type CachedRedactor = Arc<Mutex<Option<Redactor>>>;
+
 use tracing::{error, info};
 
 pub struct Reactor {
@@ -27,11 +43,15 @@ pub struct Reactor {
     pub output_emitter: OutputSender,
     pub server_receiver: Arc<Mutex<ServerReceiver>>,
     pub inner_sender: ReactorSender,
+    // None when it isn't configured, tasks that reference secrets fail then
+    pub secrets_key: Option<Arc<SecretsKey>>,
+    // Masked in the output along with the values of the secrets
+    pub redact_patterns: Arc<Vec<Regex>>,
 }
 
 impl Reactor {
-    pub async fn schedule(sender: ReactorSender) {
-        loop {
+    pub async fn schedule(sender: ReactorSender, shutting_down: Arc<AtomicBool>) {
+        while !shutting_down.load(Ordering::SeqCst) {
             match sender
                 .send(ReactorMessage::ExecuteScheduledTasks { when: now!() })
                 .await {
@@ -44,6 +64,164 @@ impl Reactor {
             tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         }
     }
+    // Asks the Reactor to shut down and waits until it's safe to exit
+    pub async fn shutdown(sender: &ReactorSender, grace_period: u64) {
+        let (tx, rx) = oneshot::channel();
+        if sender.send(ReactorMessage::Shutdown { grace_period, resp: tx }).await.is_err() {
+            error!("Reactor didnt receive the Shutdown message!");
+            return;
+        }
+        rx.await.unwrap_or_default();
+    }
+    // Returns false if there are still executions when the deadline is reached
+    async fn wait_for_executions(executor_sender: &ExecutorSender, deadline: tokio::time::Instant) -> bool {
+        loop {
+            let (tx, rx) = oneshot::channel();
+            if executor_sender.send(ExecutorMessage::GetExecutions { resp: tx }).await.is_err() {
+                error!("Executor didnt receive the GetExecutions message!");
+                return false;
+            }
+            let executions = rx.await.unwrap_or_default();
+            if executions.is_empty() {
+                return true;
+            }
+            if tokio::time::Instant::now() >= deadline {
+                info!("{} executions are still going on", executions.len());
+                return false;
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(250)).await;
+        }
+    }
+    // Awaited, so the report is stored before anyone hears that the run is finished
+    async fn create_execution_report(db_sender: &DBSender, report: ExecutionReport) {
+        info!("Sending CreateExecutionReport message to DBManager: {}", report.task_id);
+        let (tx, rx) = oneshot::channel();
+        if db_sender.send(DBMessage::CreateExecutionReport { resp: tx, report }).await.is_err() {
+            error!("Database didnt receive the CreateExecutionReport message!");
+            return;
+        }
+        if let Ok(Err(e)) = rx.await {
+            error!("Couldn't save the execution report: {}", e);
+        }
+    }
+    // Awaited, so the transitions of a run are stored in order
+    async fn set_execution_state(db_sender: &DBSender, waiters: &ExecutionWaiters, id: uuid::Uuid, state: ExecutionState) {
+        let (tx, rx) = oneshot::channel();
+        let message = DBMessage::UpdateExecutionState { id, state, resp: tx };
+        if db_sender.send(message).await.is_err() {
+            error!("Database didnt receive the UpdateExecutionState message!");
+        } else if let Ok(Err(e)) = rx.await {
+            error!("Couldn't set the state of execution {} to {}: {}", id, state.to_string(), e);
+        }
+        // Even if it couldn't be stored, so the waiters find that out instead of waiting
+        if state.is_finished() {
+            if let Some(senders) = waiters.lock().await.remove(&id) {
+                for sender in senders {
+                    sender.send(()).unwrap_or_default();
+                }
+            }
+        }
+    }
+    async fn set_execution_process(db_sender: &DBSender, id: uuid::Uuid, process: ExecutionProcess) {
+        let (tx, rx) = oneshot::channel();
+        let message = DBMessage::UpdateExecutionProcess { id, process, resp: tx };
+        if db_sender.send(message).await.is_err() {
+            error!("Database didnt receive the UpdateExecutionProcess message!");
+            return;
+        }
+        if let Ok(Err(e)) = rx.await {
+            error!("Couldn't save the process of execution {}: {}", id, e);
+        }
+    }
+    // Runs that were going on when StewardX stopped, must be taken before anything new starts
+    async fn get_unfinished_executions(db_sender: &DBSender) -> Vec<ExecutionModel> {
+        let (tx, rx) = oneshot::channel();
+        if db_sender.send(DBMessage::GetUnfinishedExecutions { resp: tx }).await.is_err() {
+            error!("Database didnt receive the GetUnfinishedExecutions message!");
+            return vec![];
+        }
+        match rx.await {
+            Ok(Ok(executions)) => executions,
+            Ok(Err(e)) => {
+                error!("Couldn't get the unfinished executions: {}", e);
+                vec![]
+            }
+            Err(_) => vec![],
+        }
+    }
+    // Written after the response is sent, a failure is only logged
+    async fn audit(db_sender: &DBSender, entry: AuditModel) {
+        let (tx, rx) = oneshot::channel();
+        if db_sender.send(DBMessage::CreateAuditEntry { entry, resp: tx }).await.is_err() {
+            error!("Database didnt receive the CreateAuditEntry message!");
+            return;
+        }
+        if let Ok(Err(e)) = rx.await {
+            error!("Couldn't write the audit entry, {}", e);
+        }
+    }
+    fn no_secrets_key() -> anyhow::Error {
+        anyhow::anyhow!("Secrets need a key, set STEWARDX_SECRETS_KEY or secrets_key_path in the config")
+    }
+    // Values of the secrets the task references, every one of them has to exist
+    async fn resolve_secrets(
+        db_sender: &DBSender,
+        secrets_key: Option<&SecretsKey>,
+        task: &str,
+    ) -> Result<HashMap<String, String>, anyhow::Error> {
+        let names = SecretModel::references(task);
+        if names.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let secrets_key = match secrets_key {
+            Some(k) => k,
+            None => return Err(anyhow::anyhow!("Task references secrets, but there's no secrets key")),
+        };
+        let (tx, rx) = oneshot::channel();
+        if db_sender.send(DBMessage::GetSecrets { names: Some(names.clone()), resp: tx }).await.is_err() {
+            return Err(anyhow::anyhow!("Database didnt receive the GetSecrets message"));
+        }
+        let secrets = rx.await.unwrap_or_else(|_| Err(anyhow::anyhow!("Database dropped the GetSecrets message")))?;
+        let mut values = HashMap::new();
+        for secret in secrets {
+            values.insert(secret.name.clone(), secret.decrypt(secrets_key)?);
+        }
+        if let Some(missing) = names.iter().find(|name| !values.contains_key(*name)) {
+            return Err(anyhow::anyhow!("Secret {} doesn't exist", missing));
+        }
+        return Ok(values);
+    }
+    // Every secret is masked, not only the ones the task references, a script can find the others too
+    async fn load_redactor(
+        db_sender: &DBSender,
+        secrets_key: Option<&SecretsKey>,
+        cached_redactor: &CachedRedactor,
+        patterns: Arc<Vec<Regex>>,
+    ) -> Result<Redactor, anyhow::Error> {
+        // Held until it's loaded, so runs that start together don't all decrypt the secrets
+        let mut cached = cached_redactor.lock().await;
+        if let Some(redactor) = cached.as_ref() {
+            return Ok(redactor.clone());
+        }
+        let (tx, rx) = oneshot::channel();
+        if db_sender.send(DBMessage::GetSecrets { names: None, resp: tx }).await.is_err() {
+            return Err(anyhow::anyhow!("Database didnt receive the GetSecrets message"));
+        }
+        let secrets = rx.await.unwrap_or_else(|_| Err(anyhow::anyhow!("Database dropped the GetSecrets message")))?;
+        let mut values = vec![];
+        for secret in secrets {
+            match secrets_key {
+                Some(key) => values.push(secret.decrypt(key)?),
+                None => return Err(anyhow::anyhow!("Secret {} can't be decrypted without a secrets key", secret.name)),
+            }
+        }
+        let redactor = Redactor::new(values, patterns);
+        *cached = Some(redactor.clone());
+        return Ok(redactor);
+    }
+    async fn forget_secrets(cached_redactor: &CachedRedactor) {
+        *cached_redactor.lock().await = None;
+    }
     pub async fn listen_for_server(receiver: &mut ServerReceiver, inner_sender: ReactorSender) {
         while let Some(message) = receiver.recv().await {
             info!("Received ServerMessage::{}", message.get_type());
@@ -51,14 +229,23 @@ impl Reactor {
                 ServerMessage::GetTasks { offset, resp } => {
                     ReactorMessage::ServerGetTasks { offset, resp }
                 }
-                ServerMessage::ExecuteTask { task_id, resp } => {
-                    ReactorMessage::ServerExecuteTask { task_id, resp }
+                ServerMessage::ExecuteTask { task_id, payload, actor, resp } => {
+                    ReactorMessage::ServerExecuteTask { task_id, payload, actor, resp }
+                }
+                ServerMessage::AbortTask { id, actor, resp } => {
+                    ReactorMessage::ServerAbortTask { id, actor, resp }
+                }
+                ServerMessage::GetExecution { execution_id, resp } => {
+                    ReactorMessage::ServerGetExecution { execution_id, resp }
                 }
-                ServerMessage::AbortTask { task_id, resp } => {
-                    ReactorMessage::ServerAbortTask { task_id, resp }
+                ServerMessage::NotifyWhenFinished { execution_id, resp } => {
+                    ReactorMessage::ServerNotifyWhenFinished { execution_id, resp }
                 }
-                ServerMessage::DeleteTask { task_id, resp } => {
-                    ReactorMessage::ServerDeleteTask { task_id, resp }
+                ServerMessage::GetExecutions { offset, state, task_id, resp } => {
+                    ReactorMessage::ServerGetExecutions { offset, state, task_id, resp }
+                }
+                ServerMessage::DeleteTask { task_id, actor, resp } => {
+                    ReactorMessage::ServerDeleteTask { task_id, actor, resp }
                 }
 
                 ServerMessage::CreateTask {
@@ -66,12 +253,28 @@ impl Reactor {
                     frequency,
                     task_type,
                     task_props,
+                    timeout,
+                    retry_policy,
+                    overlap_policy,
+                    hook_secret,
+                    hook_slug,
+                    hook_method,
+                    hook_filters,
+                    actor,
                     resp,
                 } => ReactorMessage::ServerCreateTask {
                     task_name,
                     frequency,
                     task_type,
                     task_props,
+                    timeout,
+                    retry_policy,
+                    overlap_policy,
+                    hook_secret,
+                    hook_slug,
+                    hook_method,
+                    hook_filters,
+                    actor,
                     resp,
                 },
                 ServerMessage::GetActiveTasks { resp } => {
@@ -80,17 +283,36 @@ impl Reactor {
                 ServerMessage::GetTask { task_id, resp } => {
                     ReactorMessage::ServerGetTask { task_id, resp }
                 }
+                ServerMessage::GetTaskBySlug { slug, resp } => {
+                    ReactorMessage::ServerGetTaskBySlug { slug, resp }
+                }
                 ServerMessage::UpdateTask {
                     task_id,
                     task_name,
                     frequency,
                     task_props,
+                    timeout,
+                    retry_policy,
+                    overlap_policy,
+                    hook_secret,
+                    hook_slug,
+                    hook_method,
+                    hook_filters,
+                    actor,
                     resp,
                 } => ReactorMessage::ServerUpdateTask {
                     task_id,
                     task_name,
                     frequency,
                     task_props,
+                    timeout,
+                    retry_policy,
+                    overlap_policy,
+                    hook_secret,
+                    hook_slug,
+                    hook_method,
+                    hook_filters,
+                    actor,
                     resp,
                 },
                 ServerMessage::GetExecutionReportsForTask {
@@ -108,15 +330,51 @@ impl Reactor {
                 ServerMessage::GetExecutionReport { report_id, resp } => {
                     ReactorMessage::ServerGetExecutionReport { report_id, resp }
                 }
+                ServerMessage::CreateError { error } => ReactorMessage::CreateError { error },
+                ServerMessage::GetApiKey { key_hash, resp } => {
+                    ReactorMessage::ServerGetApiKey { key_hash, resp }
+                }
+                ServerMessage::Login { username, password, resp } => {
+                    ReactorMessage::ServerLogin { username, password, resp }
+                }
+                ServerMessage::GetSession { token_hash, resp } => {
+                    ReactorMessage::ServerGetSession { token_hash, resp }
+                }
+                ServerMessage::Logout { session_id, resp } => ReactorMessage::ServerLogout { session_id, resp },
+                ServerMessage::GetAuditEntries { offset, target_id, action, actor_type, resp } => {
+                    ReactorMessage::ServerGetAuditEntries { offset, target_id, action, actor_type, resp }
+                }
+                ServerMessage::GetSecrets { resp } => ReactorMessage::GetSecrets { resp },
+                ServerMessage::CreateSecret { name, value, actor, resp } => {
+                    ReactorMessage::CreateSecret { name, value, actor, resp }
+                }
+                ServerMessage::UpdateSecret { name, value, actor, resp } => {
+                    ReactorMessage::UpdateSecret { name, value, actor, resp }
+                }
+                ServerMessage::DeleteSecret { name, actor, resp } => ReactorMessage::DeleteSecret { name, actor, resp },
             };
             inner_sender.send(reactor_message).await.unwrap_or_default();
         }
     }
     pub async fn listen(&mut self, mut receiver: ReactorReceiver) {
+        let unfinished = Self::get_unfinished_executions(&self.db_sender).await;
+        let reconcile_sender = self.inner_sender.clone();
+        tokio::spawn(async move {
+            for execution in unfinished {
+                reconcile_sender
+                    .send(ReactorMessage::ReconcileExecution { execution })
+                    .await
+                    .unwrap_or_default();
+            }
+        });
+        let shutting_down = Arc::new(AtomicBool::new(false));
+        let execution_waiters: ExecutionWaiters = Arc::default();
+        let cached_redactor: CachedRedactor = Arc::default();
         let schedule_sender = self.inner_sender.clone();
         let inner_sender = self.inner_sender.clone();
+        let schedule_stopped = shutting_down.clone();
         tokio::spawn(async move {
-            Self::schedule(schedule_sender).await;
+            Self::schedule(schedule_sender, schedule_stopped).await;
         });
         let server_receiver = self.server_receiver.clone();
         tokio::spawn(async move {
@@ -149,6 +407,11 @@ impl Reactor {
             let task_watcher_sender = self.task_watcher_sender.clone();
             let output_emitter = self.output_emitter.clone();
             let inner_sender = self.inner_sender.clone();
+            let secrets_key = self.secrets_key.clone();
+            let redact_patterns = self.redact_patterns.clone();
+            let shutting_down = shutting_down.clone();
+            let execution_waiters = execution_waiters.clone();
+            let cached_redactor = cached_redactor.clone();
             tokio::spawn(async move {
                 match message {
                     // TODO wrong use refactor later
@@ -179,51 +442,121 @@ impl Reactor {
                             }
                         };
                         // println!("{:?}", task_models);
-                        let mut tasks = task_models.iter().map(|task| {
+                        let tasks = task_models.iter().map(|task| {
                             let boxed_task;
                             ModelToTask!(task => boxed_task);
-                            return boxed_task;
+                            let retry = RetryState::first(task.get_retry_policy());
+                            let scheduled_at = task.next_execution.unwrap_or(when);
+                            let context = ExecutionContext::new(task, scheduled_at, Trigger::Cron, retry.attempt);
+                            return (boxed_task, context, task.get_timeout(), task.get_overlap_policy(), retry, task.calc_next_execution());
                         });
-                        for task in tasks.next() {
+                        for (task, context, timeout, overlap, retry, next_execution) in tasks {
                             if let Some(task) = task {
-                                let _id = task.get_id();
+                                // The schedule moves on even if the run is skipped, it'd be due again on the next tick otherwise
+                                let id = task.get_id();
+                                let (db_tx, db_rx) = oneshot::channel();
+                                didnt_receive!(db_sender
+                                    .send(DBMessage::UpdateNextExecution { id, next_execution, resp: db_tx })
+                                    .await, "Database", "UpdateNextExecution");
+                                if let Ok(Err(e)) = db_rx.await {
+                                    error!("Couldn't update the next execution of task {}: {}", id, e);
+                                }
                                 didnt_receive!(inner_sender
-                                    .send(ReactorMessage::ExecuteTask { task })
+                                    .send(ReactorMessage::ExecuteTask { task, context, timeout, overlap, retry })
                                     .await, "Reactor", "ExecuteTask");
                             }
                         }
                     }
-                    ReactorMessage::ExecuteTask { task } => {
+                    ReactorMessage::ExecuteTask { mut task, mut context, timeout, overlap, retry } => {
                         let id = task.get_id();
-                        info!("Sending Execute message to Executor for task {}", id);
+                        let execution_id = context.execution_id;
+                        // Resolved for every run, so the values are never kept with the task
+                        let secrets = Self::resolve_secrets(&db_sender, secrets_key.as_deref(), &task.to_string()).await;
+                        let secrets_error = match secrets {
+                            Ok(secrets) => {
+                                context = context.with_secrets(secrets);
+                                None
+                            }
+                            Err(e) => Some(TaskError::generic(id, e.to_string())),
+                        };
+                        task.set_context(&context);
+                        let (db_tx, db_rx) = oneshot::channel();
+                        let execution = ExecutionModel::new(execution_id, id, retry.attempt as i32);
+                        didnt_receive!(db_sender
+                            .send(DBMessage::CreateExecution { execution, resp: db_tx })
+                            .await, "Database", "CreateExecution");
+                        if let Ok(Err(e)) = db_rx.await {
+                            error!("Couldn't save execution {}: {}", execution_id, e);
+                        }
+                        if shutting_down.load(Ordering::SeqCst) {
+                            info!("StewardX is shutting down, execution {} won't start", execution_id);
+                            didnt_receive!(inner_sender
+                                .send(ReactorMessage::WatchExecution {
+                                    task_id: id,
+                                    execution_id,
+                                    exec_process: Ok(Executor::never_started(AbortReason::Shutdown)),
+                                    retry,
+                                })
+                                .await, "Reactor", "WatchExecution");
+                            return;
+                        }
+                        // Written by WatchExecution, once it's redacted
+                        if let Some(error) = secrets_error {
+                            didnt_receive!(inner_sender
+                                .send(ReactorMessage::WatchExecution {
+                                    task_id: id,
+                                    execution_id,
+                                    exec_process: Err(error),
+                                    retry,
+                                })
+                                .await, "Reactor", "WatchExecution");
+                            return;
+                        }
+                        info!("Sending Execute message to Executor for task {}, execution {}", id, execution_id);
+                        let (s_tx, s_rx) = oneshot::channel();
                         let (t_tx, t_rx) = oneshot::channel();
-                        let message = ExecutorMessage::Execute { task, resp: t_tx };
+                        let message = ExecutorMessage::Execute {
+                            task,
+                            execution_id,
+                            timeout,
+                            overlap,
+                            starting: s_tx,
+                            resp: t_tx,
+                        };
                         didnt_receive!(executor_sender.send(message).await, "Executor", msg_type);
-                        didnt_receive!(inner_sender
-                            .send(ReactorMessage::UpdateTaskExecution { task_id: id })
-                            .await, "Reactor", msg_type);
+                        // Skipped runs never leave the queue, and don't count as executions of the task
+                        let started = s_rx.await.is_ok();
+                        if started {
+                            Self::set_execution_state(&db_sender, &execution_waiters, execution_id, ExecutionState::Starting).await;
+                            didnt_receive!(inner_sender
+                                .send(ReactorMessage::UpdateTaskExecution { task_id: id })
+                                .await, "Reactor", msg_type);
+                        }
                         let result = match t_rx.await {
                             Ok(r) => {
-                                match r {
-                                    Ok(o) => o,
-                                    Err(e) => {
-                                        error!("{}", e.to_string());
-                                        didnt_receive!(inner_sender.send(ReactorMessage::CreateError {
-                                            error: e,
-                                        }).await, "Reactor", "CreateError");
-                                        return;
+                                // The stream isn't Sync, it can't be borrowed across the awaits below
+                                let process = r.as_ref().map(|execution| execution.process.clone());
+                                // Runs that never started go from the queue to their end state
+                                if let (true, Ok(process)) = (started, process) {
+                                    Self::set_execution_state(&db_sender, &execution_waiters, execution_id, ExecutionState::Running).await;
+                                    if let Some(process) = process {
+                                        Self::set_execution_process(&db_sender, execution_id, process).await;
                                     }
                                 }
+                                // Failed executions are watched too, so they get an unsuccessful report
+                                // and their handle is released from the Executor. The error is written there, redacted.
+                                r
                             }
                             Err(e) => {
                                 // Receiver dropped
                                 error!("{}", e.to_string());
-                                let report = ExecutionReport::new(id, false, Vec::default());
+                                let report = ExecutionReport::new(execution_id, id, ExitStatus::unknown(), Vec::default());
                                 // We wont be creating an error, because in this case, well, I forgot
                                 // But I didn't add a TODO here so it should be the expected behavior?
                                 didnt_receive!(inner_sender
                                     .send(ReactorMessage::CreateExecutionReport { report })
                                     .await, "Reactor", "CreateExecutionReport");
+                                Self::set_execution_state(&db_sender, &execution_waiters, execution_id, ExecutionState::Failed).await;
                                 return;
                             }
                         };
@@ -235,32 +568,182 @@ impl Reactor {
                         didnt_receive!(inner_sender
                             .send(ReactorMessage::WatchExecution {
                                 task_id: id,
-                                exec_process: Ok(result),
+                                execution_id,
+                                exec_process: result,
+                                retry,
+                            })
+                            .await, "Reactor", "WatchExecution");
+                    }
+                    ReactorMessage::ReconcileExecution { execution } => {
+                        info!("Execution {} of task {} was left {}, reconciling", execution.id, execution.task_id, execution.state);
+                        let (db_tx, db_rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::GetTask { id: execution.task_id, resp: db_tx })
+                            .await, "Database", "GetTask");
+                        let task = match db_rx.await {
+                            Ok(Ok(task)) => Some(task),
+                            _ => None,
+                        };
+                        let (status, message) = match execution.get_process() {
+                            #[cfg(feature = "docker")]
+                            Some(ExecutionProcess::Container(container_id)) => {
+                                use crate::{tasks::DockerTask, traits::FromJson};
+                                match DockerTask::orphan_status(&container_id).await {
+                                    Ok(None) => {
+                                        let docker_task = task
+                                            .as_ref()
+                                            .and_then(|t| DockerTask::from_json(t.serde_string.clone()).ok());
+                                        match docker_task {
+                                            Some(mut docker_task) => {
+                                                info!("Container {} is still running, reattaching", container_id);
+                                                docker_task.reattach(container_id);
+                                                // Only the time it had left, the clock kept running while StewardX was down
+                                                let started_at = execution.running_at.unwrap_or(execution.updated_at);
+                                                let elapsed = (now!() - started_at).num_seconds().max(0) as u64;
+                                                let timeout = task
+                                                    .and_then(|t| t.get_timeout())
+                                                    .or(CONFIG.default_timeout)
+                                                    .map(|t| t.saturating_sub(elapsed));
+                                                didnt_receive!(inner_sender
+                                                    .send(ReactorMessage::ReattachExecution {
+                                                        task: Box::new(docker_task),
+                                                        execution,
+                                                        timeout,
+                                                    })
+                                                    .await, "Reactor", "ReattachExecution");
+                                                return;
+                                            }
+                                            None => (
+                                                ExitStatus::unknown(),
+                                                format!("Container {} is still running but its task couldn't be parsed, it's left alone.", container_id),
+                                            ),
+                                        }
+                                    }
+                                    Ok(Some(status)) => (
+                                        status,
+                                        format!("Container {} finished while StewardX was down, its output is lost.", container_id),
+                                    ),
+                                    Err(e) => (
+                                        ExitStatus::unknown(),
+                                        format!("Container {} couldn't be inspected after the restart: {}", container_id, e),
+                                    ),
+                                }
+                            }
+                            #[cfg(feature = "cmd")]
+                            Some(ExecutionProcess::Pid(pid)) => {
+                                let started_before = execution.running_at.unwrap_or(execution.updated_at);
+                                if crate::tasks::CmdTask::kill_orphan(pid, started_before) {
+                                    (
+                                        ExitStatus::new_raw(false, None, Some(libc::SIGKILL), None),
+                                        format!("StewardX was stopped during the run, process {} was killed.", pid),
+                                    )
+                                } else {
+                                    (
+                                        ExitStatus::unknown(),
+                                        format!("StewardX was stopped during the run, process {} had already exited.", pid),
+                                    )
+                                }
+                            }
+                            _ => (
+                                ExitStatus::unknown(),
+                                String::from("StewardX was stopped before the run could finish."),
+                            ),
+                        };
+                        let report = ExecutionReport::new(execution.id, execution.task_id, status, vec![OutputLine::system(message)])
+                            .with_attempt(execution.attempt, None);
+                        let state = ExecutionState::from_report(&report);
+                        Self::create_execution_report(&db_sender, report).await;
+                        Self::set_execution_state(&db_sender, &execution_waiters, execution.id, state).await;
+                    }
+                    ReactorMessage::ReattachExecution { task, execution, timeout } => {
+                        let (t_tx, t_rx) = oneshot::channel();
+                        let message = ExecutorMessage::Execute {
+                            task,
+                            execution_id: execution.id,
+                            timeout,
+                            overlap: OverlapPolicy::Allow,
+                            starting: oneshot::channel().0,
+                            resp: t_tx,
+                        };
+                        didnt_receive!(executor_sender.send(message).await, "Executor", msg_type);
+                        let exec_process = match t_rx.await {
+                            Ok(r) => r,
+                            Err(e) => {
+                                error!("{}", e.to_string());
+                                return;
+                            }
+                        };
+                        // Retries are only for the runs that we started
+                        let mut retry = RetryState::first(None);
+                        retry.attempt = execution.attempt as u32;
+                        didnt_receive!(inner_sender
+                            .send(ReactorMessage::WatchExecution {
+                                task_id: execution.task_id,
+                                execution_id: execution.id,
+                                exec_process,
+                                retry,
                             })
                             .await, "Reactor", "WatchExecution");
                     }
                     ReactorMessage::CreateExecutionReport { report } => {
-                        info!(
-                            "Sending CreateExecutionReport message to DBManager: {}",
-                            report.task_id
-                        );
+                        Self::create_execution_report(&db_sender, report).await;
+                    }
+                    ReactorMessage::Shutdown { grace_period, resp } => {
+                        info!("Shutting down, waiting up to {} seconds for the running executions", grace_period);
+                        shutting_down.store(true, Ordering::SeqCst);
+                        let deadline = tokio::time::Instant::now() + tokio::time::Duration::from_secs(grace_period);
+                        if !Self::wait_for_executions(&executor_sender, deadline).await {
+                            let (tx, rx) = oneshot::channel();
+                            didnt_receive!(executor_sender
+                                .send(ExecutorMessage::AbortAll { reason: AbortReason::Shutdown, resp: tx })
+                                .await, "Executor", "AbortAll");
+                            let aborted = rx.await.unwrap_or_default();
+                            info!("Aborted {} executions, waiting for their reports", aborted);
+                            // Aborted runs only need to report back
+                            let deadline = tokio::time::Instant::now() + tokio::time::Duration::from_secs(10);
+                            if !Self::wait_for_executions(&executor_sender, deadline).await {
+                                error!("Some executions didn't stop in time, they'll be reconciled on the next start");
+                            }
+                        }
                         let (tx, rx) = oneshot::channel();
-                        didnt_receive!(db_sender
-                            .send(DBMessage::CreateExecutionReport { resp: tx, report })
-                            .await, "Database", "CreateExecutionReport");
-                        let _report = rx.await.unwrap();
-                        // resp.send(report);
+                        didnt_receive!(db_sender.send(DBMessage::Flush { resp: tx }).await, "Database", "Flush");
+                        rx.await.unwrap_or_default();
+                        info!("Shutdown is complete");
+                        resp.send(()).unwrap_or_default();
                     }
                     ReactorMessage::WatchExecution {
                         task_id,
+                        execution_id,
                         exec_process,
+                        retry,
                     } => {
+                        // Task couldn't even be started
+                        let spawn_failed = exec_process.is_err();
+                        let redactor = match Self::load_redactor(&db_sender, secrets_key.as_deref(), &cached_redactor, redact_patterns).await {
+                            Ok(r) => r,
+                            Err(e) => {
+                                // Storing it unredacted would leak the secrets
+                                error!("Output of execution {} is withheld, {}", execution_id, e);
+                                Redactor::withholding()
+                            }
+                        };
+                        // Errors of a run can quote its command, secrets and all
+                        if let Err(e) = &exec_process {
+                            let mut error = e.clone();
+                            error.error_message = redactor.redact(&error.error_message);
+                            error!("{}", error.to_string());
+                            didnt_receive!(inner_sender
+                                .send(ReactorMessage::CreateError { error })
+                                .await, "Reactor", "CreateError");
+                        }
                         let (o_tx, mut o_rx) = broadcast::channel(128);
                         let (er_tx, er_rx) = oneshot::channel();
                         didnt_receive!(task_watcher_sender
                             .send(TaskWatcherMessage::WatchExecution {
                                 task_id,
+                                execution_id,
                                 exec_process,
+                                redactor,
                                 output_resp: o_tx,
                                 resp: er_tx,
                             })
@@ -270,16 +753,38 @@ impl Reactor {
                                 .send(ReactorMessage::OutputReceived { model: output })
                                 .await, "Reactor", "OutputReceived");
                         }
+                        let mut next_attempt = None;
+                        let mut state = ExecutionState::Failed;
                         // If output receiver is dropped, it means that execution has finished!
                         if let Ok(report) = er_rx.await {
-                            didnt_receive!(inner_sender
-                                .send(ReactorMessage::CreateExecutionReport { report })
-                                .await, "Reactor", "CreateExecutionReport");
+                            state = ExecutionState::from_report(&report);
+                            if let Some(AbortReason::Timeout(secs)) = report.aborted {
+                                didnt_receive!(inner_sender.send(ReactorMessage::CreateError {
+                                    error: TaskError::timeout(task_id, secs),
+                                }).await, "Reactor", "CreateError");
+                            }
+                            let report = report.with_attempt(retry.attempt as i32, retry.original_id);
+                            let failure = match report.aborted {
+                                _ if spawn_failed => Some(FailureKind::SpawnError),
+                                _ if report.successful => None,
+                                Some(AbortReason::Timeout(_)) => Some(FailureKind::Timeout),
+                                // Aborted on purpose or never ran, retrying would be rude
+                                Some(AbortReason::Manual)
+                                | Some(AbortReason::Replaced)
+                                | Some(AbortReason::Skipped)
+                                | Some(AbortReason::Shutdown) => None,
+                                None => Some(FailureKind::ExitCode),
+                            };
+                            next_attempt = failure.and_then(|f| retry.next(report.id, f));
+                            Self::create_execution_report(&db_sender, report).await;
                         }
+                        Self::set_execution_state(&db_sender, &execution_waiters, execution_id, state).await;
                         didnt_receive!(inner_sender
                             .send(ReactorMessage::ExecutionFinished {
                                 id: task_id,
+                                execution_id,
                                 should_update: false,
+                                next_attempt,
                             })
                             .await, "Reactor", "ExecutionFinished");
                     }
@@ -292,15 +797,49 @@ impl Reactor {
                             }
                         };
                     }
-                    ReactorMessage::ExecutionFinished { id, should_update } => {
-                        info!("{}'s execution has finished", id);
-                        let message = ExecutorMessage::ExecutionFinished { id };
+                    ReactorMessage::ExecutionFinished { id, execution_id, should_update, next_attempt } => {
+                        info!("{}'s execution {} has finished", id, execution_id);
+                        let message = ExecutorMessage::ExecutionFinished { task_id: id, execution_id };
                         didnt_receive!(executor_sender.send(message).await, "Executor", "ExecutionFinished");
                         if should_update {
                             didnt_receive!(inner_sender
                                 .send(ReactorMessage::UpdateTaskExecution { task_id: id })
                                 .await, "Reactor", "UpdateTaskExecution");
                         }
+                        if let Some((retry, delay)) = next_attempt {
+                            info!("Retrying task {} in {} seconds, attempt {}", id, delay, retry.attempt);
+                            tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
+                            if shutting_down.load(Ordering::SeqCst) {
+                                info!("StewardX is shutting down, task {} won't be retried", id);
+                                return;
+                            }
+                            // Task might be updated or deleted in the meantime
+                            let (db_tx, db_rx) = oneshot::channel();
+                            didnt_receive!(db_sender
+                                .send(DBMessage::GetTask { id, resp: db_tx })
+                                .await, "Database", "GetTask");
+                            let task = match db_rx.await {
+                                Ok(Ok(task)) => task,
+                                _ => {
+                                    error!("Task {} couldn't be found, it won't be retried.", id);
+                                    return;
+                                }
+                            };
+                            let boxed_task;
+                            ModelToTask!(task => boxed_task);
+                            if let Some(boxed_task) = boxed_task {
+                                didnt_receive!(inner_sender
+                                    .send(ReactorMessage::ExecuteTask {
+                                        task: boxed_task,
+                                        context: ExecutionContext::new(&task, now!(), Trigger::Retry, retry.attempt)
+                                            .with_payload(retry.payload.clone()),
+                                        timeout: task.get_timeout(),
+                                        overlap: task.get_overlap_policy(),
+                                        retry,
+                                    })
+                                    .await, "Reactor", "ExecuteTask");
+                            }
+                        }
                     }
                     ReactorMessage::ServerGetTasks { offset, resp } => {
                         let (db_tx, db_rx) = tokio::sync::oneshot::channel();
@@ -314,7 +853,7 @@ impl Reactor {
                         let result = db_rx.await.unwrap();
                         server_receiver_dropped!(resp.send(result), "ServerGetTasks");
                     }
-                    ReactorMessage::ServerExecuteTask { task_id, resp } => {
+                    ReactorMessage::ServerExecuteTask { task_id, payload, actor, resp } => {
                         let (db_tx, db_rx) = tokio::sync::oneshot::channel();
                         didnt_receive!(db_sender
                             .send(DBMessage::GetTask {
@@ -327,19 +866,35 @@ impl Reactor {
                             Ok(task) => {
                                 let boxed_task;
                                 ModelToTask!(task => boxed_task);
+                                let timeout = task.get_timeout();
+                                let overlap = task.get_overlap_policy();
+                                let mut retry = RetryState::first(task.get_retry_policy());
+                                retry.payload = payload.map(Arc::new);
+                                // Hook tasks are only ever run through the API
+                                let trigger = match task.frequency.as_str() {
+                                    "Hook" => Trigger::Hook,
+                                    _ => Trigger::Manual,
+                                };
+                                let context = ExecutionContext::new(&task, now!(), trigger, retry.attempt)
+                                    .with_payload(retry.payload.clone());
+                                let summary = format!("Executed task {} ({})", task.task_name, trigger.to_string());
                                 match boxed_task {
                                     Some(task) => {
+                                        let execution_id = context.execution_id;
                                         didnt_receive!(inner_sender
-                                            .send(ReactorMessage::ExecuteTask { task })
+                                            .send(ReactorMessage::ExecuteTask { task, context, timeout, overlap, retry })
                                             .await, "Reactor", "ExecuteTask");
-                                        server_receiver_dropped!(resp.send(true), "ServerExecuteTask");
+                                        server_receiver_dropped!(resp.send(Some(execution_id)), "ServerExecuteTask");
+                                        let entry = AuditModel::new(&actor, "execute", "task", Some(task_id), summary)
+                                            .with_execution(Some(execution_id));
+                                        Self::audit(&db_sender, entry).await;
                                     }
                                     None => {
                                         let task_json = serde_json::to_string(&task).unwrap_or(task.task_type);
                                         didnt_receive!(inner_sender.send(ReactorMessage::CreateError {
                                             error: TaskError::generic(task.id, format!("Task couldn't be parsed to boxed task, {}", task_json))
                                         }).await, "Reactor", "CreateError");
-                                        server_receiver_dropped!(resp.send(false), "ServerExecuteTask");
+                                        server_receiver_dropped!(resp.send(None), "ServerExecuteTask");
                                     }
                                 }
                             }
@@ -350,19 +905,77 @@ impl Reactor {
                                 didnt_receive!(inner_sender
                                     .send(ReactorMessage::CreateError { error })
                                     .await, "Reactor", "CreateError");
-                                server_receiver_dropped!(resp.send(false), "ServerExecuteTask");
+                                server_receiver_dropped!(resp.send(None), "ServerExecuteTask");
                             }
                         }
                     }
-                    ReactorMessage::ServerAbortTask { task_id, resp } => {
+                    ReactorMessage::ServerAbortTask { id, actor, resp } => {
+                        let (tx, rx) = oneshot::channel();
                         didnt_receive!(executor_sender
-                            .send(ExecutorMessage::Abort { id: task_id, resp })
+                            .send(ExecutorMessage::Abort { id, reason: AbortReason::Manual, resp: tx })
                             .await, "Executor", "Abort");
+                        let aborted = rx.await.unwrap_or(false);
+                        server_receiver_dropped!(resp.send(aborted), "ServerAbortTask");
+                        if !aborted {
+                            return;
+                        }
+                        // The id can be either, the entry is about the task anyway
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::GetExecution { id, resp: tx })
+                            .await, "Database", "GetExecution");
+                        let entry = match rx.await {
+                            Ok(Ok(execution)) => AuditModel::new(
+                                &actor,
+                                "abort",
+                                "task",
+                                Some(execution.task_id),
+                                format!("Aborted execution {}", id),
+                            )
+                            .with_execution(Some(id)),
+                            _ => AuditModel::new(&actor, "abort", "task", Some(id), String::from("Aborted every run of the task")),
+                        };
+                        Self::audit(&db_sender, entry).await;
+                    }
+                    ReactorMessage::ServerGetExecution { execution_id, resp } => {
+                        didnt_receive!(db_sender
+                            .send(DBMessage::GetExecution { id: execution_id, resp })
+                            .await, "Database", "GetExecution");
+                    }
+                    ReactorMessage::ServerNotifyWhenFinished { execution_id, resp } => {
+                        let (tx, rx) = oneshot::channel();
+                        let mut waiters = execution_waiters.lock().await;
+                        // Callers that gave up are cleaned up here
+                        waiters.retain(|_, senders| {
+                            senders.retain(|s| !s.is_closed());
+                            !senders.is_empty()
+                        });
+                        waiters.entry(execution_id).or_default().push(tx);
+                        drop(waiters);
+                        server_receiver_dropped!(resp.send(rx), "ServerNotifyWhenFinished");
                     }
-                    ReactorMessage::ServerDeleteTask { task_id, resp } => {
+                    ReactorMessage::ServerGetExecutions { offset, state, task_id, resp } => {
                         didnt_receive!(db_sender
-                            .send(DBMessage::DeleteTask { id: task_id, resp })
+                            .send(DBMessage::GetExecutions { offset, state, task_id, resp })
+                            .await, "Database", "GetExecutions");
+                    }
+                    ReactorMessage::ServerDeleteTask { task_id, actor, resp } => {
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::DeleteTask { id: task_id, resp: tx })
                             .await, "Database", "DeleteTask");
+                        let result = rx.await.unwrap();
+                        let entry = match &result {
+                            Ok(task) => Some(
+                                AuditModel::new(&actor, "delete", "task", Some(task_id), format!("Deleted task {}", task.task_name))
+                                    .with_diff(Some(task), None),
+                            ),
+                            Err(_) => None,
+                        };
+                        server_receiver_dropped!(resp.send(result), "ServerDeleteTask");
+                        if let Some(entry) = entry {
+                            Self::audit(&db_sender, entry).await;
+                        }
                     }
                     ReactorMessage::UpdateTaskExecution { task_id } => {
                         // Update task
@@ -398,8 +1011,55 @@ impl Reactor {
                         frequency,
                         task_type,
                         task_props,
+                        timeout,
+                        retry_policy,
+                        overlap_policy,
+                        hook_secret,
+                        hook_slug,
+                        hook_method,
+                        hook_filters,
+                        actor,
                         resp,
                     } => {
+                        if let Err(e) = TaskModel::validate_timeout(timeout) {
+                            server_receiver_dropped!(resp.send(Err(e)), "ServerCreateTask");
+                            return;
+                        }
+                        let overlap_policy = match TaskModel::parse_overlap_policy(overlap_policy) {
+                            Ok(p) => p,
+                            Err(e) => {
+                                server_receiver_dropped!(resp.send(Err(e)), "ServerCreateTask");
+                                return;
+                            }
+                        };
+                        let retry_policy = match RetryPolicy::serialize_value(retry_policy) {
+                            Ok(p) => p,
+                            Err(e) => {
+                                server_receiver_dropped!(resp.send(Err(e)), "ServerCreateTask");
+                                return;
+                            }
+                        };
+                        let hook_slug = match TaskModel::parse_hook_slug(hook_slug) {
+                            Ok(s) => s,
+                            Err(e) => {
+                                server_receiver_dropped!(resp.send(Err(e)), "ServerCreateTask");
+                                return;
+                            }
+                        };
+                        let hook_method = match TaskModel::parse_hook_method(hook_method) {
+                            Ok(m) => m,
+                            Err(e) => {
+                                server_receiver_dropped!(resp.send(Err(e)), "ServerCreateTask");
+                                return;
+                            }
+                        };
+                        let hook_filters = match HookFilter::serialize_value(hook_filters) {
+                            Ok(f) => f,
+                            Err(e) => {
+                                server_receiver_dropped!(resp.send(Err(e)), "ServerCreateTask");
+                                return;
+                            }
+                        };
                         let new_id = uuid::Uuid::new_v4();
                         let serde_string = match TaskModel::get_serde_from_props(
                             new_id,
@@ -412,53 +1072,42 @@ impl Reactor {
                                 return;
                             }
                         };
-                        let task = TaskModel::new(
+                        let mut task = TaskModel::new(
                             Some(new_id),
                             task_name,
                             task_type,
                             serde_string,
                             frequency,
                         );
+                        task.timeout = timeout;
+                        task.retry_policy = retry_policy;
+                        task.overlap_policy = overlap_policy;
+                        task.hook_secret = hook_secret.and_then(TaskModel::parse_hook_secret);
+                        task.hook_slug = hook_slug;
+                        task.hook_method = hook_method;
+                        task.hook_filters = hook_filters;
                         let (tx, rx) = tokio::sync::oneshot::channel();
                         didnt_receive!(db_sender
                             .send(DBMessage::CreateTask { task, resp: tx })
                             .await, "Database", "CreateTask");
                         let result = rx.await.unwrap();
+                        let entry = match &result {
+                            Ok(task) => Some(
+                                AuditModel::new(&actor, "create", "task", Some(task.id), format!("Created task {}", task.task_name))
+                                    .with_diff(None, Some(task)),
+                            ),
+                            Err(_) => None,
+                        };
                         server_receiver_dropped!(resp.send(result), "ServerCreateTask");
+                        if let Some(entry) = entry {
+                            Self::audit(&db_sender, entry).await;
+                        }
                     }
                     ReactorMessage::ServerGetActiveTasks { resp } => {
-                        let (e_tx, e_rx) = oneshot::channel();
-                        didnt_receive!(executor_sender
-                            .send(ExecutorMessage::GetActiveTaskIDs { resp: e_tx })
-                            .await, "Executor", "GetActiveTaskIDs");
-                        let active_task_ids = e_rx.await.unwrap();
-                        let mut active_tasks = vec![];
-                        // TODO: Find a better way in future
-                        for task_id in active_task_ids {
-                            let (db_tx, db_rx) = oneshot::channel();
-                            didnt_receive!(db_sender
-                                .clone()
-                                .send(DBMessage::GetTask {
-                                    id: task_id,
-                                    resp: db_tx,
-                                })
-                                .await, "Database", "GetTask");
-                            let task = db_rx.await.unwrap();
-                            match task {
-                                Ok(task) => {
-                                    active_tasks.push(task);
-                                }
-                                Err(e) => {
-                                    let error_str = e.to_string();
-                                    server_receiver_dropped!(resp.send(Err(e)), "ServerGetActiveTasks");
-                                    didnt_receive!(inner_sender.send(ReactorMessage::CreateError {
-                                        error: TaskError::generic(uuid::Uuid::default(), error_str)
-                                    }).await, "Reactor", "CreateError");
-                                    return;
-                                }
-                            };
-                        }
-                        server_receiver_dropped!(resp.send(Ok(active_tasks)), "ServerGetActiveTasks");
+                        // Answered from the executions table, so the runs that are stuck show up too
+                        didnt_receive!(db_sender
+                            .send(DBMessage::GetActiveTasks { resp })
+                            .await, "Database", "GetActiveTasks");
                     }
                     ReactorMessage::ServerGetTask { task_id, resp } => {
                         let (tx, rx) = oneshot::channel();
@@ -472,13 +1121,65 @@ impl Reactor {
                         let res = rx.await.unwrap();
                         server_receiver_dropped!(resp.send(res), "ServerGetTask");
                     }
+                    ReactorMessage::ServerGetTaskBySlug { slug, resp } => {
+                        didnt_receive!(db_sender
+                            .send(DBMessage::GetTaskBySlug { slug, resp })
+                            .await, "Database", "GetTaskBySlug");
+                    }
                     ReactorMessage::ServerUpdateTask {
                         task_id,
                         task_name,
                         frequency,
                         task_props,
+                        timeout,
+                        retry_policy,
+                        overlap_policy,
+                        hook_secret,
+                        hook_slug,
+                        hook_method,
+                        hook_filters,
+                        actor,
                         resp,
                     } => {
+                        if let Err(e) = TaskModel::validate_timeout(timeout) {
+                            server_receiver_dropped!(resp.send(Err(e)), "ServerUpdateTask");
+                            return;
+                        }
+                        let overlap_policy = match TaskModel::parse_overlap_policy(overlap_policy) {
+                            Ok(p) => p,
+                            Err(e) => {
+                                server_receiver_dropped!(resp.send(Err(e)), "ServerUpdateTask");
+                                return;
+                            }
+                        };
+                        let retry_policy = match RetryPolicy::serialize_value(retry_policy) {
+                            Ok(p) => p,
+                            Err(e) => {
+                                server_receiver_dropped!(resp.send(Err(e)), "ServerUpdateTask");
+                                return;
+                            }
+                        };
+                        let hook_slug = match TaskModel::parse_hook_slug(hook_slug) {
+                            Ok(s) => s,
+                            Err(e) => {
+                                server_receiver_dropped!(resp.send(Err(e)), "ServerUpdateTask");
+                                return;
+                            }
+                        };
+                        let hook_method = match TaskModel::parse_hook_method(hook_method) {
+                            Ok(m) => m,
+                            Err(e) => {
+                                server_receiver_dropped!(resp.send(Err(e)), "ServerUpdateTask");
+                                return;
+                            }
+                        };
+                        let hook_filters = match HookFilter::serialize_value(hook_filters) {
+                            Ok(f) => f,
+                            Err(e) => {
+                                server_receiver_dropped!(resp.send(Err(e)), "ServerUpdateTask");
+                                return;
+                            }
+                        };
                         let (task_tx, task_rx) = oneshot::channel();
                         didnt_receive!(inner_sender
                             .clone()
@@ -495,8 +1196,19 @@ impl Reactor {
                                 return;
                             }
                         };
+                        let before = task.clone();
                         task.task_name = task_name;
                         task.frequency = frequency;
+                        task.timeout = timeout;
+                        task.retry_policy = retry_policy;
+                        task.overlap_policy = overlap_policy;
+                        // Left out means the secret stays as it is, since it's never sent to the clients
+                        if let Some(hook_secret) = hook_secret {
+                            task.hook_secret = TaskModel::parse_hook_secret(hook_secret);
+                        }
+                        task.hook_slug = hook_slug;
+                        task.hook_method = hook_method;
+                        task.hook_filters = hook_filters;
                         let serde_string = match TaskModel::get_serde_from_props(
                             task_id,
                             task.task_type.clone(),
@@ -518,7 +1230,24 @@ impl Reactor {
                             .send(DBMessage::UpdateTask { task, resp: db_tx })
                             .await, "Database", "UpdateTask");
                         let result = db_rx.await.unwrap();
+                        let entry = match &result {
+                            Ok(task) => {
+                                let mut summary = format!("Updated task {}", task.task_name);
+                                // Secrets aren't in the diff
+                                if task.hook_secret != before.hook_secret {
+                                    summary.push_str(", changed its hook secret");
+                                }
+                                Some(
+                                    AuditModel::new(&actor, "update", "task", Some(task_id), summary)
+                                        .with_diff(Some(&before), Some(task)),
+                                )
+                            }
+                            Err(_) => None,
+                        };
                         server_receiver_dropped!(resp.send(result), "ServerUpdateTask");
+                        if let Some(entry) = entry {
+                            Self::audit(&db_sender, entry).await;
+                        }
                     }
                     ReactorMessage::ServerGetExecutionReportsForTask {
                         task_id,
@@ -556,6 +1285,291 @@ impl Reactor {
                         let (tx, _rx) = oneshot::channel();
                         didnt_receive!(db_sender.send(DBMessage::CreateError { error, resp: tx }).await, "Database", "CreateError");
                     }
+                    ReactorMessage::CreateApiKey { name, scopes, actor, resp } => {
+                        let (key, secret) = ApiKeyModel::generate(name, &scopes);
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::CreateApiKey { key, resp: tx })
+                            .await, "Database", "CreateApiKey");
+                        let result = rx.await.unwrap();
+                        let entry = result.as_ref().ok().map(|key| {
+                            let summary = format!("Created API key {} with scopes {}", key.name, key.scopes.join(","));
+                            AuditModel::new(&actor, "create", "api_key", Some(key.id), summary)
+                        });
+                        resp.send(result.map(|key| (key, secret))).unwrap_or_default();
+                        if let Some(entry) = entry {
+                            Self::audit(&db_sender, entry).await;
+                        }
+                    }
+                    ReactorMessage::GetApiKeys { resp } => {
+                        didnt_receive!(db_sender
+                            .send(DBMessage::GetApiKeys { resp })
+                            .await, "Database", "GetApiKeys");
+                    }
+                    ReactorMessage::RevokeApiKey { id, actor, resp } => {
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::RevokeApiKey { id, resp: tx })
+                            .await, "Database", "RevokeApiKey");
+                        let result = rx.await.unwrap();
+                        let entry = result.as_ref().ok().map(|key| {
+                            AuditModel::new(&actor, "revoke", "api_key", Some(id), format!("Revoked API key {}", key.name))
+                        });
+                        resp.send(result).unwrap_or_default();
+                        if let Some(entry) = entry {
+                            Self::audit(&db_sender, entry).await;
+                        }
+                    }
+                    ReactorMessage::ServerGetApiKey { key_hash, resp } => {
+                        didnt_receive!(db_sender
+                            .send(DBMessage::GetApiKey { key_hash, resp })
+                            .await, "Database", "GetApiKey");
+                    }
+                    ReactorMessage::CreateUser { username, password, role, actor, resp } => {
+                        // Hashing takes a while on purpose, it shouldn't hold up the runtime
+                        let user = tokio::task::spawn_blocking(move || UserModel::new(username, &password, role))
+                            .await
+                            .unwrap();
+                        let user = match user {
+                            Ok(u) => u,
+                            Err(e) => {
+                                resp.send(Err(e)).unwrap_or_default();
+                                return;
+                            }
+                        };
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::CreateUser { user, resp: tx })
+                            .await, "Database", "CreateUser");
+                        let result = rx.await.unwrap();
+                        let entry = result.as_ref().ok().map(|user| {
+                            let summary = format!("Created user {} with role {}", user.username, user.role);
+                            AuditModel::new(&actor, "create", "user", Some(user.id), summary)
+                        });
+                        resp.send(result).unwrap_or_default();
+                        if let Some(entry) = entry {
+                            Self::audit(&db_sender, entry).await;
+                        }
+                    }
+                    ReactorMessage::GetUsers { resp } => {
+                        didnt_receive!(db_sender
+                            .send(DBMessage::GetUsers { resp })
+                            .await, "Database", "GetUsers");
+                    }
+                    ReactorMessage::UpdateUser { id, password, role, actor, resp } => {
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::GetUser { id, resp: tx })
+                            .await, "Database", "GetUser");
+                        let mut user = match rx.await.unwrap() {
+                            Ok(u) => u,
+                            Err(e) => {
+                                resp.send(Err(e)).unwrap_or_default();
+                                return;
+                            }
+                        };
+                        if let Some(role) = role {
+                            user.role = role.to_string();
+                        }
+                        let password_changed = password.is_some();
+                        if let Some(password) = password {
+                            let hash = tokio::task::spawn_blocking(move || UserModel::hash_password(&password))
+                                .await
+                                .unwrap();
+                            user.password_hash = match hash {
+                                Ok(h) => h,
+                                Err(e) => {
+                                    resp.send(Err(e)).unwrap_or_default();
+                                    return;
+                                }
+                            };
+                        }
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::UpdateUser { user, resp: tx })
+                            .await, "Database", "UpdateUser");
+                        let result = rx.await.unwrap();
+                        if result.is_ok() && password_changed {
+                            let (tx, rx) = oneshot::channel();
+                            didnt_receive!(db_sender
+                                .send(DBMessage::DeleteSessionsForUser { user_id: id, resp: tx })
+                                .await, "Database", "DeleteSessionsForUser");
+                            rx.await.unwrap_or_else(|_| Ok(0)).unwrap_or_default();
+                        }
+                        let entry = result.as_ref().ok().map(|user| {
+                            let mut summary = format!("Updated user {}, role is {}", user.username, user.role);
+                            if password_changed {
+                                summary.push_str(", changed the password");
+                            }
+                            AuditModel::new(&actor, "update", "user", Some(id), summary)
+                        });
+                        resp.send(result).unwrap_or_default();
+                        if let Some(entry) = entry {
+                            Self::audit(&db_sender, entry).await;
+                        }
+                    }
+                    ReactorMessage::DeleteUser { id, actor, resp } => {
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::DeleteUser { id, resp: tx })
+                            .await, "Database", "DeleteUser");
+                        let result = rx.await.unwrap();
+                        let entry = result.as_ref().ok().map(|user| {
+                            AuditModel::new(&actor, "delete", "user", Some(id), format!("Deleted user {}", user.username))
+                        });
+                        resp.send(result).unwrap_or_default();
+                        if let Some(entry) = entry {
+                            Self::audit(&db_sender, entry).await;
+                        }
+                    }
+                    ReactorMessage::ServerLogin { username, password, resp } => {
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::GetUserByUsername { username: username.trim().to_lowercase(), resp: tx })
+                            .await, "Database", "GetUserByUsername");
+                        let user = rx.await.unwrap().ok();
+                        let user = tokio::task::spawn_blocking(move || match user {
+                            Some(user) if user.verify_password(&password) => Some(user),
+                            Some(_) => None,
+                            None => {
+                                // Unknown usernames take as long as wrong passwords
+                                UserModel::hash_password(&password).unwrap_or_default();
+                                None
+                            }
+                        })
+                        .await
+                        .unwrap();
+                        let user = match user {
+                            Some(u) => u,
+                            None => {
+                                info!("Failed login attempt for user {}", username);
+                                resp.send(Err(anyhow::anyhow!("Username or password is wrong"))).unwrap_or_default();
+                                return;
+                            }
+                        };
+                        let (session, token) = SessionModel::generate(user.id, CONFIG.session_lifetime);
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::CreateSession { session, resp: tx })
+                            .await, "Database", "CreateSession");
+                        let result = rx.await.unwrap().map(|session| (user, session, token));
+                        server_receiver_dropped!(resp.send(result), "ServerLogin");
+                    }
+                    ReactorMessage::ServerGetSession { token_hash, resp } => {
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::GetSession { token_hash, resp: tx })
+                            .await, "Database", "GetSession");
+                        let session = match rx.await.unwrap() {
+                            Ok(s) => s,
+                            Err(e) => {
+                                resp.send(Err(e)).unwrap_or_default();
+                                return;
+                            }
+                        };
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::GetUser { id: session.user_id, resp: tx })
+                            .await, "Database", "GetUser");
+                        let result = rx.await.unwrap().map(|user| (user, session));
+                        resp.send(result).unwrap_or_default();
+                    }
+                    ReactorMessage::ServerLogout { session_id, resp } => {
+                        didnt_receive!(db_sender
+                            .send(DBMessage::DeleteSession { id: session_id, resp })
+                            .await, "Database", "DeleteSession");
+                    }
+                    ReactorMessage::ServerGetAuditEntries { offset, target_id, action, actor_type, resp } => {
+                        didnt_receive!(db_sender
+                            .send(DBMessage::GetAuditEntries { offset, target_id, action, actor_type, resp })
+                            .await, "Database", "GetAuditEntries");
+                    }
+                    ReactorMessage::GetSecrets { resp } => {
+                        didnt_receive!(db_sender
+                            .send(DBMessage::GetSecrets { names: None, resp })
+                            .await, "Database", "GetSecrets");
+                    }
+                    ReactorMessage::CreateSecret { name, value, actor, resp } => {
+                        let secret = match &secrets_key {
+                            Some(key) => SecretModel::new(name, &value, key),
+                            None => Err(Self::no_secrets_key()),
+                        };
+                        let secret = match secret {
+                            Ok(s) => s,
+                            Err(e) => {
+                                resp.send(Err(e)).unwrap_or_default();
+                                return;
+                            }
+                        };
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::CreateSecret { secret, resp: tx })
+                            .await, "Database", "CreateSecret");
+                        let result = rx.await.unwrap();
+                        Self::forget_secrets(&cached_redactor).await;
+                        let entry = result.as_ref().ok().map(|secret| {
+                            let summary = format!("Created secret {}", secret.name);
+                            AuditModel::new(&actor, "create", "secret", Some(secret.id), summary)
+                        });
+                        resp.send(result).unwrap_or_default();
+                        if let Some(entry) = entry {
+                            Self::audit(&db_sender, entry).await;
+                        }
+                    }
+                    ReactorMessage::UpdateSecret { name, value, actor, resp } => {
+                        let secrets_key = match &secrets_key {
+                            Some(k) => k,
+                            None => {
+                                resp.send(Err(Self::no_secrets_key())).unwrap_or_default();
+                                return;
+                            }
+                        };
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::GetSecret { name, resp: tx })
+                            .await, "Database", "GetSecret");
+                        let mut secret = match rx.await.unwrap() {
+                            Ok(s) => s,
+                            Err(e) => {
+                                resp.send(Err(e)).unwrap_or_default();
+                                return;
+                            }
+                        };
+                        if let Err(e) = secret.set_value(&value, secrets_key) {
+                            resp.send(Err(e)).unwrap_or_default();
+                            return;
+                        }
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::UpdateSecret { secret, resp: tx })
+                            .await, "Database", "UpdateSecret");
+                        let result = rx.await.unwrap();
+                        Self::forget_secrets(&cached_redactor).await;
+                        let entry = result.as_ref().ok().map(|secret| {
+                            let summary = format!("Changed the value of secret {}", secret.name);
+                            AuditModel::new(&actor, "update", "secret", Some(secret.id), summary)
+                        });
+                        resp.send(result).unwrap_or_default();
+                        if let Some(entry) = entry {
+                            Self::audit(&db_sender, entry).await;
+                        }
+                    }
+                    ReactorMessage::DeleteSecret { name, actor, resp } => {
+                        let (tx, rx) = oneshot::channel();
+                        didnt_receive!(db_sender
+                            .send(DBMessage::DeleteSecret { name, resp: tx })
+                            .await, "Database", "DeleteSecret");
+                        let result = rx.await.unwrap();
+                        Self::forget_secrets(&cached_redactor).await;
+                        let entry = result.as_ref().ok().map(|secret| {
+                            let summary = format!("Deleted secret {}", secret.name);
+                            AuditModel::new(&actor, "delete", "secret", Some(secret.id), summary)
+                        });
+                        resp.send(result).unwrap_or_default();
+                        if let Some(entry) = entry {
+                            Self::audit(&db_sender, entry).await;
+                        }
+                    }
                 }
             });
         }
@@ -597,10 +1611,13 @@ mod tests {
             output_emitter: oe_tx,
             server_receiver: server_receiver,
             inner_sender: r_tx,
+            secrets_key: None,
+            redact_patterns: Arc::default(),
         };
         tokio::spawn(async move {
             let mut fake_db = vec![create_long_task().await];
-            match db_rx.recv().await.unwrap() {
+            while let Some(message) = db_rx.recv().await {
+            match message {
                 DBMessage::GetTask { resp, .. } => {
                     let task = fake_db.pop().unwrap();
                     fake_db.push(create_long_task().await);
@@ -634,8 +1651,15 @@ mod tests {
                 DBMessage::UpdateTask { task, resp } => {
                     resp.send(Ok(task)).unwrap();
                 }
+                DBMessage::CreateExecution { execution, resp } => {
+                    resp.send(Ok(execution)).unwrap();
+                }
+                DBMessage::GetUnfinishedExecutions { resp } => {
+                    resp.send(Ok(vec![])).unwrap();
+                }
                 _ => panic!("Shouldn't happen! But when it does, please update the test :)"),
             };
+            }
         });
         tokio::spawn(async move {
             reactor.listen(r_rx).await;
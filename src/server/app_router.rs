@@ -1,6 +1,7 @@
 use hyper::{Body, Request, Response};
-use routerify::Router;
+use routerify::{ext::RequestExt, Router};
 
+use super::Caller;
 use crate::CONFIG;
 
 const NOT_FOUND_HTML: &str = r#"
@@ -41,14 +42,50 @@ const NOT_ENABLED: &str = r#"
 </html>
 "#;
 
+const LOGIN_HTML: &str = r#"
+<html>
+<style>
+  html, body {
+    width: 100%;
+    height: 100%;
+    font-family: monospace;
+    display: flex;
+    justify-content: center;
+    align-items: center;
+    font-size: large;
+  }
+  form {
+    display: flex;
+    flex-direction: column;
+    gap: 8px;
+  }
+</style>
+<body>
+  <form method="post" action="/login">
+    {message}
+    <input name="username" placeholder="Username" autocomplete="username" required autofocus>
+    <input name="password" type="password" placeholder="Password" autocomplete="current-password" required>
+    <button type="submit">Log in</button>
+  </form>
+</body>
+</html>
+"#;
+
 // NOTE: NOT_ENABLED string is for future use, currently 
 // if panel feature is disabled, StewardX needs to be
 // restarted, so it'll never return NOT_ENABLED
 
-pub async fn app(_req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
+pub async fn app(req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
     if !**&CONFIG.get_features().get("panel").unwrap() {
         return Ok(Response::builder().body(NOT_ENABLED.into()).unwrap());
     }
+    if !matches!(req.context::<Caller>(), Some(Caller::User(..)) | Some(Caller::ApiKey(_))) {
+        return Ok(Response::builder()
+            .status(hyper::StatusCode::SEE_OTHER)
+            .header(hyper::header::LOCATION, "/login")
+            .body(Body::empty())
+            .unwrap());
+    }
     let index_path = &CONFIG.get_index_file_path();
     let index = tokio::fs::read_to_string(index_path).await.unwrap_or_else(|_| {
         NOT_FOUND_HTML.to_string()
@@ -56,6 +93,17 @@ pub async fn app(_req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
     return Ok(Response::builder().body(index.into()).unwrap());
 }
 
+pub async fn login_page(req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
+    let message = match req.uri().query() {
+        Some(q) if q.split('&').any(|p| p == "failed") => "<span>Username or password is wrong.</span>",
+        _ => "",
+    };
+    return Ok(Response::builder()
+        .header(hyper::header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(LOGIN_HTML.replace("{message}", message).into())
+        .unwrap());
+}
+
 pub fn app_router() -> Router<Body, anyhow::Error> {
     Router::builder().any(app).build().unwrap()
 }
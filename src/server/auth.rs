@@ -0,0 +1,251 @@
+use hyper::{Body, Method, Request, Response, StatusCode};
+use routerify::{ext::RequestExt, RouteError};
+use tokio::sync::mpsc::Sender;
+use tracing::error;
+
+use super::{ServerMessage, ServerUtils};
+use crate::models::{Actor, ActorKind, ApiKeyModel, Scope, SessionModel, UserModel};
+
+// Who made the request, the middleware puts it in the request context
+#[derive(Debug, Clone)]
+pub enum Caller {
+    ApiKey(ApiKeyModel),
+    // Logged in through the panel, with the session cookie
+    User(UserModel, SessionModel),
+    // Hook urls can be called without a key, the task's secret has to vouch for them then
+    Hook,
+    // Login page, or the panel before logging in
+    Anonymous,
+}
+
+enum Access {
+    Public,
+    // Sends people who aren't logged in to the login page
+    Panel,
+    Hook,
+    Scoped(Scope),
+    // Any valid key or session, for the urls that don't exist
+    Authenticated,
+}
+
+fn get_access(method: &Method, path: &str) -> Access {
+    let path = path.trim_end_matches('/');
+    let parts: Vec<&str> = path.split('/').skip(1).collect();
+    match (method, parts.as_slice()) {
+        (_, ["app", ..]) => Access::Panel,
+        (&Method::GET, ["login"]) | (&Method::POST, ["login"]) => Access::Public,
+        (_, ["hooks", ..]) | (&Method::POST, ["execute", _]) => Access::Hook,
+        (&Method::POST, ["execute"]) => Access::Scoped(Scope::Execute),
+        (&Method::POST, ["abort"]) | (&Method::POST, ["abort", _]) => Access::Scoped(Scope::Abort),
+        (&Method::GET, ["tasks"]) | (&Method::GET, ["tasks", _]) | (&Method::GET, ["activetasks"]) => {
+            Access::Scoped(Scope::TasksRead)
+        }
+        (&Method::POST, ["tasks"]) | (&Method::POST, ["tasks", _]) | (&Method::DELETE, ["tasks"]) => {
+            Access::Scoped(Scope::TasksWrite)
+        }
+        (&Method::GET, ["execution", _])
+        | (&Method::GET, ["executions"])
+        | (&Method::GET, ["task", _, "reports"])
+        | (&Method::GET, ["reports"])
+        | (&Method::GET, ["reports", _]) => Access::Scoped(Scope::ReportsRead),
+        (&Method::GET, ["audit"]) => Access::Scoped(Scope::AuditRead),
+        (&Method::GET, ["secrets"]) => Access::Scoped(Scope::SecretsRead),
+        (&Method::POST, ["secrets"]) | (&Method::POST, ["secrets", _]) | (&Method::DELETE, ["secrets", _]) => {
+            Access::Scoped(Scope::SecretsWrite)
+        }
+        _ => Access::Authenticated,
+    }
+}
+
+// Either "Authorization: Bearer <key>" or "X-API-Key: <key>"
+fn get_key(req: &Request<Body>) -> Option<String> {
+    let headers = req.headers();
+    if let Some(key) = headers.get("X-API-Key").and_then(|v| v.to_str().ok()) {
+        return Some(key.trim().to_string());
+    }
+    let authorization = headers.get(hyper::header::AUTHORIZATION)?.to_str().ok()?;
+    let key = authorization.strip_prefix("Bearer ")?;
+    return Some(key.trim().to_string());
+}
+
+fn get_session_token(req: &Request<Body>) -> Option<String> {
+    for header in req.headers().get_all(hyper::header::COOKIE) {
+        for cookie in header.to_str().ok()?.split(';') {
+            if let Some((name, value)) = cookie.trim().split_once('=') {
+                if name == SessionModel::COOKIE_NAME && !value.is_empty() {
+                    return Some(value.to_string());
+                }
+            }
+        }
+    }
+    return None;
+}
+
+// Picked up by the error handler below and turned into a response
+macro_rules! denied {
+    ($status: expr, $error: expr) => {
+        return Err(anyhow::anyhow!(serde_json::json!({
+            "status": $status.as_u16(),
+            "error": $error
+        })))
+    };
+}
+
+/// Pre middleware, every request needs an API key or a session with the scope of the route
+pub async fn authenticate(req: Request<Body>) -> Result<Request<Body>, anyhow::Error> {
+    let access = get_access(req.method(), req.uri().path());
+    if let Access::Public = access {
+        req.set_context(Caller::Anonymous);
+        return Ok(req);
+    }
+    let scope = match access {
+        Access::Scoped(scope) => Some(scope),
+        Access::Hook => Some(Scope::Execute),
+        _ => None,
+    };
+    if let Some(key) = get_key(&req) {
+        let key = get_api_key(&req, key).await?;
+        if let Some(scope) = scope {
+            if !key.has_scope(scope) {
+                denied!(
+                    StatusCode::FORBIDDEN,
+                    format!("API key doesn't have the {} scope.", scope.to_string())
+                );
+            }
+        }
+        req.set_context(Caller::ApiKey(key));
+        return Ok(req);
+    }
+    if let Some(token) = get_session_token(&req) {
+        let (user, session) = match get_session(&req, token).await? {
+            Some(s) => s,
+            None => {
+                if let Access::Panel = access {
+                    req.set_context(Caller::Anonymous);
+                    return Ok(req);
+                }
+                denied!(StatusCode::UNAUTHORIZED, "Session is invalid or expired.");
+            }
+        };
+        // Browsers send the cookie along with requests other sites make, they can't read the token though
+        let safe = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+        if !safe {
+            let csrf_token = req.headers().get("X-CSRF-Token").and_then(|v| v.to_str().ok()).unwrap_or("");
+            if !ServerUtils::constant_time_eq(csrf_token.as_bytes(), session.csrf_token.as_bytes()) {
+                denied!(StatusCode::FORBIDDEN, "CSRF token is missing or wrong.");
+            }
+        }
+        if let Some(scope) = scope {
+            if !user.get_role().has_scope(scope) {
+                denied!(
+                    StatusCode::FORBIDDEN,
+                    format!("Role {} doesn't have the {} scope.", user.role, scope.to_string())
+                );
+            }
+        }
+        req.set_context(Caller::User(user, session));
+        return Ok(req);
+    }
+    match access {
+        Access::Hook => req.set_context(Caller::Hook),
+        Access::Panel => req.set_context(Caller::Anonymous),
+        _ => denied!(StatusCode::UNAUTHORIZED, "API key or session is missing."),
+    }
+    return Ok(req);
+}
+
+async fn get_api_key(req: &Request<Body>, key: String) -> Result<ApiKeyModel, anyhow::Error> {
+    let sender = req.data::<Sender<ServerMessage>>().unwrap();
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let key_hash = ApiKeyModel::hash(&key);
+    if sender.send(ServerMessage::GetApiKey { key_hash, resp: tx }).await.is_err() {
+        error!("Reactor didnt receive the GetApiKey message! Either it's failed or didn't start.");
+        denied!(StatusCode::SERVICE_UNAVAILABLE, "Reactor isn't awake.");
+    }
+    return match rx.await {
+        Ok(Ok(key)) => Ok(key),
+        _ => denied!(StatusCode::UNAUTHORIZED, "API key is invalid or revoked."),
+    };
+}
+
+// None when there's no such session, or it's expired
+async fn get_session(req: &Request<Body>, token: String) -> Result<Option<(UserModel, SessionModel)>, anyhow::Error> {
+    let sender = req.data::<Sender<ServerMessage>>().unwrap();
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let token_hash = SessionModel::hash(&token);
+    if sender.send(ServerMessage::GetSession { token_hash, resp: tx }).await.is_err() {
+        error!("Reactor didnt receive the GetSession message! Either it's failed or didn't start.");
+        denied!(StatusCode::SERVICE_UNAVAILABLE, "Reactor isn't awake.");
+    }
+    return match rx.await {
+        Ok(Ok((user, session))) if !session.is_expired() => Ok(Some((user, session))),
+        _ => Ok(None),
+    };
+}
+
+/// Goes into the audit log with the changes the request makes
+pub fn get_actor(req: &Request<Body>) -> Actor {
+    let (kind, id, name) = match req.context::<Caller>() {
+        Some(Caller::ApiKey(key)) => (ActorKind::ApiKey, Some(key.id), key.name),
+        Some(Caller::User(user, _)) => (ActorKind::User, Some(user.id), user.username),
+        // Only the hook urls get to change anything without a key or a session
+        Some(Caller::Hook) | Some(Caller::Anonymous) | None => (ActorKind::Hook, None, String::from("hook")),
+    };
+    return Actor {
+        kind,
+        id,
+        name,
+        source_ip: Some(req.remote_addr().ip().to_string()),
+        request: format!("{} {}", req.method(), req.uri().path()),
+    };
+}
+
+/// Errors are JSON objects, they can carry the status of the response
+pub async fn error_handler(err: RouteError) -> Response<Body> {
+    let message = err.to_string();
+    let (status, body) = match serde_json::from_str::<serde_json::Value>(&message) {
+        Ok(serde_json::Value::Object(mut body)) => {
+            let status = body
+                .remove("status")
+                .and_then(|s| s.as_u64())
+                .and_then(|s| StatusCode::from_u16(s as u16).ok())
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            (status, serde_json::Value::Object(body))
+        }
+        _ => (StatusCode::INTERNAL_SERVER_ERROR, serde_json::json!({ "error": message })),
+    };
+    let mut response = Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json");
+    if status == StatusCode::UNAUTHORIZED {
+        response = response.header(hyper::header::WWW_AUTHENTICATE, "Bearer");
+    }
+    return response.body(body.to_string().into()).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_routes_to_scopes() {
+        let scope = |method: Method, path: &str| match get_access(&method, path) {
+            Access::Scoped(scope) => Some(scope),
+            _ => None,
+        };
+        assert_eq!(scope(Method::GET, "/tasks/"), Some(Scope::TasksRead));
+        assert_eq!(scope(Method::DELETE, "/tasks"), Some(Scope::TasksWrite));
+        assert_eq!(scope(Method::POST, "/execute"), Some(Scope::Execute));
+        assert_eq!(scope(Method::POST, "/abort/1"), Some(Scope::Abort));
+        assert_eq!(scope(Method::GET, "/task/1/reports"), Some(Scope::ReportsRead));
+        assert_eq!(scope(Method::GET, "/audit"), Some(Scope::AuditRead));
+        assert_eq!(scope(Method::GET, "/secrets"), Some(Scope::SecretsRead));
+        assert_eq!(scope(Method::DELETE, "/secrets/DB_PASSWORD"), Some(Scope::SecretsWrite));
+        assert!(matches!(get_access(&Method::POST, "/execute/1"), Access::Hook));
+        assert!(matches!(get_access(&Method::GET, "/hooks/github/deploy"), Access::Hook));
+        assert!(matches!(get_access(&Method::GET, "/app/index.html"), Access::Panel));
+        assert!(matches!(get_access(&Method::POST, "/login"), Access::Public));
+        assert!(matches!(get_access(&Method::POST, "/logout"), Access::Authenticated));
+        assert!(matches!(get_access(&Method::PUT, "/tasks"), Access::Authenticated));
+    }
+}
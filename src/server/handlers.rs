@@ -6,9 +6,14 @@ use routerify::{ext::RequestExt};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tokio::sync::mpsc::Sender;
-use tracing::error;
+use tracing::{error, info};
 use uuid::Uuid;
 
+use crate::{
+    models::{ExecutionState, HookPayload, SessionModel, TaskError, TaskModel},
+    CONFIG,
+};
+
 #[macro_export]
 macro_rules! response_json {
     (body: $body:expr) => {
@@ -105,14 +110,203 @@ pub async fn get_task(req: Request<Body>) -> Result<Response<Body>, anyhow::Erro
     }
 }
 
+// Callers can wait for the result with ?wait=true&timeout=30s, or have it posted to ?callback_url=...
+struct ResultMode {
+    wait: Option<std::time::Duration>,
+    callback_url: Option<url::Url>,
+}
+
+const DEFAULT_WAIT: u64 = 30;
+const MAX_WAIT: u64 = 3600;
+
+fn get_result_mode(req: &Request<Body>) -> Result<ResultMode, String> {
+    let query = match super::ServerUtils::get_qs(&req.uri().to_string()) {
+        Ok(q) => q,
+        Err(_) => return Err(String::from("Malformed query.")),
+    };
+    let wait = match query.get("wait").map(|w| w.as_str()) {
+        Some("true") | Some("1") => {
+            let timeout = match query.get("timeout") {
+                Some(t) => match super::ServerUtils::parse_duration(t) {
+                    Some(t) => t,
+                    None => return Err(format!("Malformed timeout '{}', use something like 30s, 5m or 1h.", t)),
+                },
+                None => std::time::Duration::from_secs(DEFAULT_WAIT),
+            };
+            if timeout.as_secs() > MAX_WAIT {
+                return Err(format!("Timeout can't be longer than {} seconds.", MAX_WAIT));
+            }
+            Some(timeout)
+        }
+        Some("false") | Some("0") | None => None,
+        Some(w) => return Err(format!("Malformed wait '{}', it's either true or false.", w)),
+    };
+    let callback_url = match query.get("callback_url") {
+        Some(url) => match url::Url::parse(url) {
+            Ok(u) if u.scheme() == "http" || u.scheme() == "https" => u,
+            _ => return Err(format!("Malformed callback url '{}'.", url)),
+        },
+        None => return Ok(ResultMode { wait, callback_url: None }),
+    };
+    if cfg!(not(feature = "http")) {
+        return Err(String::from("Callback urls need StewardX to be built with the http feature."));
+    }
+    if let Err(reason) = super::ServerUtils::check_callback_url(&callback_url, &CONFIG.callback_hosts) {
+        return Err(format!("Callback url '{}' isn't allowed, {}.", callback_url, reason));
+    }
+    return Ok(ResultMode { wait, callback_url: Some(callback_url) });
+}
+
+async fn get_execution_state(sender: &Sender<ServerMessage>, execution_id: Uuid) -> Option<ExecutionState> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    sender.send(ServerMessage::GetExecution { execution_id, resp: tx }).await.ok()?;
+    let execution = rx.await.ok()?.ok()?;
+    return ExecutionState::from_str(&execution.state).ok();
+}
+
+// Waits for the Reactor to say the run is finished, gives up at the deadline and returns the last state it saw
+async fn wait_for_result(
+    sender: &Sender<ServerMessage>,
+    execution_id: Uuid,
+    deadline: tokio::time::Instant,
+) -> Result<Value, Option<ExecutionState>> {
+    // Registered before the state is checked, so a run that finishes in between isn't missed
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    if sender.send(ServerMessage::NotifyWhenFinished { execution_id, resp: tx }).await.is_err() {
+        return Err(None);
+    }
+    let finished_rx = match rx.await {
+        Ok(r) => r,
+        Err(_) => return Err(None),
+    };
+    // The execution might not be stored yet right after it's requested
+    let mut state = get_execution_state(sender, execution_id).await;
+    if !state.map_or(false, |s| s.is_finished()) {
+        if !matches!(tokio::time::timeout_at(deadline, finished_rx).await, Ok(Ok(()))) {
+            return Err(get_execution_state(sender, execution_id).await.or(state));
+        }
+        state = get_execution_state(sender, execution_id).await;
+    }
+    // Not there even though it's finished, it couldn't be stored
+    let finished = match state.filter(|s| s.is_finished()) {
+        Some(s) => s,
+        None => return Err(state),
+    };
+    // Reports are stored before the run is marked as finished
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    if sender.send(ServerMessage::GetExecutionReport { report_id: execution_id, resp: tx }).await.is_err() {
+        return Err(state);
+    }
+    let mut result = serde_json::json!({
+        "status": "success",
+        "execution_id": execution_id,
+        "state": finished.to_string(),
+    });
+    if let Ok(Ok(report)) = rx.await {
+        result["successful"] = serde_json::json!(report.successful);
+        result["exit_code"] = serde_json::json!(report.exit_code);
+        result["signal"] = serde_json::json!(report.signal);
+        result["http_status"] = serde_json::json!(report.http_status);
+        result["http_headers"] = serde_json::json!(report.http_headers);
+        result["attempt"] = serde_json::json!(report.attempt);
+        result["output"] = serde_json::json!(report.output);
+    }
+    return Ok(result);
+}
+
+// Receivers can check it against the hook secret of the task, like StewardX checks the hooks
+#[cfg(feature = "http")]
+const CALLBACK_SIGNATURE_HEADER: &str = "X-StewardX-Signature-256";
+
+#[cfg(feature = "http")]
+async fn post_result(sender: Sender<ServerMessage>, task_id: Uuid, execution_id: Uuid, callback_url: url::Url) {
+    let deadline = tokio::time::Instant::now() + tokio::time::Duration::from_secs(MAX_WAIT);
+    let result = match wait_for_result(&sender, execution_id, deadline).await {
+        Ok(r) => r,
+        Err(state) => {
+            let state = state.map_or(String::from("unknown"), |s| s.to_string());
+            error!("Result of execution {} isn't posted to {}, its state is {}", execution_id, callback_url, state);
+            return;
+        }
+    };
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let hook_secret = match sender.send(ServerMessage::GetTask { task_id, resp: tx }).await {
+        Ok(_) => rx.await.ok().and_then(|t| t.ok()).and_then(|t| t.hook_secret),
+        Err(_) => None,
+    };
+    let body = result.to_string();
+    // Redirects could lead it to the addresses that were just ruled out
+    let client = match reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()).build() {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Result of execution {} isn't posted, client couldn't be built: {}", execution_id, e);
+            return;
+        }
+    };
+    let mut request = client
+        .post(callback_url.as_str())
+        .timeout(std::time::Duration::from_secs(10))
+        .header(hyper::header::CONTENT_TYPE, "application/json");
+    if let Some(secret) = hook_secret {
+        match super::ServerUtils::sign_body(&secret, body.as_bytes()) {
+            Ok(signature) => request = request.header(CALLBACK_SIGNATURE_HEADER, signature),
+            Err(e) => error!("Result of execution {} couldn't be signed: {}", execution_id, e),
+        }
+    }
+    let response = request.body(body).send().await.and_then(|r| r.error_for_status());
+    if let Err(e) = response {
+        error!("Result of execution {} couldn't be posted to {}: {}", execution_id, callback_url, e);
+    }
+}
+
+// Responds to a run that's just requested, how depends on what the caller asked for
+async fn execution_requested(
+    sender: &Sender<ServerMessage>,
+    task_id: Uuid,
+    execution_id: Uuid,
+    mode: ResultMode,
+) -> Result<Response<Body>, anyhow::Error> {
+    #[cfg(feature = "http")]
+    if let Some(callback_url) = mode.callback_url {
+        tokio::spawn(post_result(sender.clone(), task_id, execution_id, callback_url));
+    }
+    if let Some(timeout) = mode.wait {
+        let deadline = tokio::time::Instant::now() + timeout;
+        return match wait_for_result(sender, execution_id, deadline).await {
+            Ok(result) => response_json!(body: &result),
+            Err(state) => response_json!(
+                status: hyper::StatusCode::ACCEPTED,
+                body: &serde_json::json!({
+                     "status": "success",
+                     "execution_id": execution_id,
+                     "state": state.unwrap_or(ExecutionState::Queued).to_string()
+                 })
+            ),
+        };
+    }
+    return response_json!(
+        body: &serde_json::json!({
+             "status": "success",
+             "execution_id": execution_id
+         })
+    );
+}
+
 /// End point to execute a task
-/// "task_id" parameter is required
+/// "task_id" parameter is required, responds with the id of the execution
 pub async fn exec_task(mut req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
     #[derive(Debug, Serialize, Deserialize)]
     struct RequestBody {
         task_id: Uuid,
     }
     let (tx, rx) = tokio::sync::oneshot::channel();
+    let actor = super::auth::get_actor(&req);
+    let mode = match get_result_mode(&req) {
+        Ok(m) => m,
+        Err(error) => {
+            return response_json!(status: hyper::StatusCode::BAD_REQUEST, body: &serde_json::json!({ "error": error }));
+        }
+    };
     let body = req.body_mut();
     if let Some(Ok(body)) = body.data().await {
         if let Ok(json_value) =
@@ -121,14 +315,10 @@ pub async fn exec_task(mut req: Request<Body>) -> Result<Response<Body>, anyhow:
             let sender = req.data::<Sender<ServerMessage>>().unwrap();
             let task_id = json_value.task_id;
             reactor_failed!(sender
-                .send(ServerMessage::ExecuteTask { task_id, resp: tx })
+                .send(ServerMessage::ExecuteTask { task_id, payload: None, actor, resp: tx })
                 .await, "ExecuteTask");
-            if let Ok(_) = rx.await {
-                return response_json!(
-                    body: &serde_json::json!({
-                         "status": "success"
-                     })
-                );
+            if let Ok(Some(execution_id)) = rx.await {
+                return execution_requested(sender, task_id, execution_id, mode).await;
             } else {
                 return response_json!(
                     status: hyper::StatusCode::NOT_FOUND,
@@ -142,11 +332,81 @@ pub async fn exec_task(mut req: Request<Body>) -> Result<Response<Body>, anyhow:
     empty_malformed_body!()
 }
 
+// Body, allowed headers and query params of a hook request, limits are in config
+async fn read_hook_payload(req: &mut Request<Body>) -> Result<HookPayload, (hyper::StatusCode, String)> {
+    let limit = CONFIG.hook_payload_limit;
+    let too_large = (hyper::StatusCode::PAYLOAD_TOO_LARGE, format!("Payload can't be bigger than {} bytes.", limit));
+    let headers = req.headers();
+    let content_type = headers
+        .get(hyper::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let content_length = headers
+        .get(hyper::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if content_length.map_or(false, |l| l > limit) {
+        return Err(too_large);
+    }
+    let selected_headers = CONFIG
+        .hook_headers
+        .iter()
+        .filter_map(|name| {
+            let value = headers.get(name.as_str())?.to_str().ok()?;
+            Some((name.to_lowercase(), value.to_string()))
+        })
+        .collect();
+    let query = match super::ServerUtils::get_qs(&req.uri().to_string()) {
+        Ok(q) => q,
+        Err(_) => return Err((hyper::StatusCode::BAD_REQUEST, String::from("Malformed query."))),
+    };
+    // Content-Length might be missing or lying, so it's counted while reading
+    let mut body = Vec::new();
+    while let Some(chunk) = req.body_mut().data().await {
+        let chunk = match chunk {
+            Ok(c) => c,
+            Err(e) => return Err((hyper::StatusCode::BAD_REQUEST, e.to_string())),
+        };
+        if body.len() + chunk.len() > limit {
+            return Err(too_large);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    if !body.is_empty() && !CONFIG.hook_content_types.is_empty() {
+        let essence = content_type
+            .as_deref()
+            .and_then(|c| c.split(';').next())
+            .map(|c| c.trim().to_string())
+            .unwrap_or_default();
+        if !CONFIG.hook_content_types.iter().any(|allowed| allowed.eq_ignore_ascii_case(&essence)) {
+            return Err((
+                hyper::StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("Content type '{}' isn't allowed, possible values: {}", essence, CONFIG.hook_content_types.join(", ")),
+            ));
+        }
+    }
+    Ok(HookPayload {
+        body,
+        content_type,
+        headers: selected_headers,
+        query,
+    })
+}
+
+/// End point for hooks, the request is passed to the run as its payload
 pub async fn exec_task_url(req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
-    let (tx, rx) = tokio::sync::oneshot::channel();
     let sender = req.data::<Sender<ServerMessage>>().unwrap();
     let task_id = match req.param("id") {
-        Some(id) => Uuid::from_str(id).unwrap(),
+        // Anyone can call hook urls, a malformed id is their mistake
+        Some(id) => match Uuid::from_str(id) {
+            Ok(id) => id,
+            Err(_) => {
+                return response_json!(
+                    status: hyper::StatusCode::BAD_REQUEST,
+                    body: &serde_json::json!({ "error": "Invalid id" })
+                );
+            }
+        },
         None => {
             let obj = serde_json::json!({
                 "error": "Missing url parameter: id."
@@ -157,15 +417,109 @@ pub async fn exec_task_url(req: Request<Body>) -> Result<Response<Body>, anyhow:
             return response_json!(status: hyper::StatusCode::BAD_REQUEST, body: &obj);
         }
     };
+    let (tx, rx) = tokio::sync::oneshot::channel();
     reactor_failed!(sender
-        .send(ServerMessage::ExecuteTask { task_id, resp: tx })
-        .await, "ExecuteTask");
-    if let Ok(_) = rx.await {
+        .send(ServerMessage::GetTask { task_id, resp: tx })
+        .await, "GetTask");
+    match rx.await {
+        Ok(Ok(task)) => return execute_hook(req, task).await,
+        _ => {
+            return response_json!(
+                status: hyper::StatusCode::NOT_FOUND,
+                body: &serde_json::json!({
+                     "status": "error"
+                 })
+            );
+        }
+    }
+}
+
+/// Same as the end point above, but the task is found by its hook slug and method
+/// Everything after /hooks/ is the slug
+pub async fn exec_task_hook(req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
+    let sender = req.data::<Sender<ServerMessage>>().unwrap();
+    let slug = req.uri().path().trim_start_matches("/hooks").trim_matches('/').to_string();
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    reactor_failed!(sender
+        .send(ServerMessage::GetTaskBySlug { slug, resp: tx })
+        .await, "GetTaskBySlug");
+    let task = match rx.await {
+        Ok(Ok(task)) => task,
+        _ => {
+            return response_json!(
+                status: hyper::StatusCode::NOT_FOUND,
+                body: &serde_json::json!({
+                     "status": "error"
+                 })
+            );
+        }
+    };
+    if req.method().as_str() != task.hook_method {
         return response_json!(
+            status: hyper::StatusCode::METHOD_NOT_ALLOWED,
             body: &serde_json::json!({
-                 "status": "success"
+                 "error": format!("Hook only accepts {} requests.", task.hook_method)
              })
         );
+    }
+    return execute_hook(req, task).await;
+}
+
+/// When the task has a secret, the request has to be signed with it or carry it as a token
+async fn execute_hook(mut req: Request<Body>, task: TaskModel) -> Result<Response<Body>, anyhow::Error> {
+    let task_id = task.id;
+    let mode = match get_result_mode(&req) {
+        Ok(m) => m,
+        Err(error) => {
+            return response_json!(status: hyper::StatusCode::BAD_REQUEST, body: &serde_json::json!({ "error": error }));
+        }
+    };
+    let payload = match read_hook_payload(&mut req).await {
+        Ok(p) => p,
+        Err((status, error)) => {
+            error!("Hook request for {} is rejected: {}", req.uri().path(), error);
+            return response_json!(status: status, body: &serde_json::json!({ "error": error }));
+        }
+    };
+    let sender = req.data::<Sender<ServerMessage>>().unwrap();
+    // Without an API key, only the secret of the task can let the request in
+    if let (Some(super::Caller::Hook), None) = (req.context::<super::Caller>(), &task.hook_secret) {
+        return response_json!(
+            status: hyper::StatusCode::UNAUTHORIZED,
+            body: &serde_json::json!({ "error": "API key is missing, and the task doesn't have a hook secret." })
+        );
+    }
+    if let Some(secret) = &task.hook_secret {
+        if let Err(reason) = super::ServerUtils::verify_hook_secret(secret, req.headers(), &payload.body) {
+            error!("Hook request for task {} is rejected: {}", task_id, reason);
+            reactor_failed!(sender
+                .send(ServerMessage::CreateError { error: TaskError::unauthorized_hook(task_id, reason) })
+                .await, "CreateError");
+            return response_json!(
+                status: hyper::StatusCode::UNAUTHORIZED,
+                body: &serde_json::json!({ "error": "Hook request couldn't be verified." })
+            );
+        }
+    }
+    if let Some(filter) = task.get_hook_filter() {
+        if let Err(reason) = filter.check(req.headers(), &payload.body) {
+            info!("Hook request for task {} is ignored: {}", task_id, reason);
+            return response_json!(
+                status: hyper::StatusCode::ACCEPTED,
+                body: &serde_json::json!({
+                     "status": "ignored",
+                     "reason": reason
+                 })
+            );
+        }
+    }
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let actor = super::auth::get_actor(&req);
+    reactor_failed!(sender
+        .send(ServerMessage::ExecuteTask { task_id, payload: Some(payload), actor, resp: tx })
+        .await, "ExecuteTask");
+    if let Ok(Some(execution_id)) = rx.await {
+        return execution_requested(sender, task_id, execution_id, mode).await;
     } else {
         return response_json!(
             status: hyper::StatusCode::NOT_FOUND,
@@ -176,21 +530,28 @@ pub async fn exec_task_url(req: Request<Body>) -> Result<Response<Body>, anyhow:
     }
 }
 
+/// End point to abort a run
+/// Either "execution_id" or "task_id" is required, the latter aborts every run of the task
 pub async fn abort_task(mut req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
     #[derive(Debug, Serialize, Deserialize)]
     struct RequestBody {
-        task_id: Uuid,
+        task_id: Option<Uuid>,
+        execution_id: Option<Uuid>,
     }
     let (tx, rx) = tokio::sync::oneshot::channel();
+    let actor = super::auth::get_actor(&req);
     let body = req.body_mut();
     if let Some(Ok(body)) = body.data().await {
         if let Ok(json_value) =
             serde_json::from_slice(&body) as Result<RequestBody, serde_json::Error>
         {
             let sender = req.data::<Sender<ServerMessage>>().unwrap();
-            let task_id = json_value.task_id;
+            let id = match json_value.execution_id.or(json_value.task_id) {
+                Some(id) => id,
+                None => empty_malformed_body!(),
+            };
             reactor_failed!(sender
-                .send(ServerMessage::AbortTask { task_id, resp: tx })
+                .send(ServerMessage::AbortTask { id, actor, resp: tx })
                 .await, "AbortTask");
             if let Ok(result) = rx.await {
                 let status;
@@ -214,10 +575,12 @@ pub async fn abort_task(mut req: Request<Body>) -> Result<Response<Body>, anyhow
     empty_malformed_body!()
 }
 
+/// Id can be an execution id or a task id
 pub async fn abort_task_url(req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
     let (tx, rx) = tokio::sync::oneshot::channel();
+    let actor = super::auth::get_actor(&req);
     let sender = req.data::<Sender<ServerMessage>>().unwrap();
-    let task_id = match req.param("id") {
+    let id = match req.param("id") {
         Some(id) => Uuid::from_str(id).unwrap(),
         None => {
             let obj = serde_json::json!({
@@ -230,9 +593,9 @@ pub async fn abort_task_url(req: Request<Body>) -> Result<Response<Body>, anyhow
         }
     };
     reactor_failed!(sender
-        .send(ServerMessage::AbortTask { task_id, resp: tx })
+        .send(ServerMessage::AbortTask { id, actor, resp: tx })
         .await, "AbortTask");
-    if let Ok(_) = rx.await {
+    if let Ok(true) = rx.await {
         return response_json!(
             body: &serde_json::json!({
                  "status": "success"
@@ -248,12 +611,93 @@ pub async fn abort_task_url(req: Request<Body>) -> Result<Response<Body>, anyhow
     }
 }
 
+pub async fn get_execution(req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let sender = req.data::<Sender<ServerMessage>>().unwrap();
+    let execution_id = match req.param("id").and_then(|id| Uuid::from_str(id).ok()) {
+        Some(id) => id,
+        None => {
+            let obj = serde_json::json!({
+                "error": "Missing or malformed url parameter: id."
+            });
+            let obj = obj.to_string();
+            return response_json!(status: hyper::StatusCode::BAD_REQUEST, body: &obj);
+        }
+    };
+    reactor_failed!(sender
+        .send(ServerMessage::GetExecution { execution_id, resp: tx })
+        .await, "GetExecution");
+    match rx.await.unwrap() {
+        Ok(execution) => response_json!(body: &execution),
+        Err(e) => {
+            error!("{}", e.to_string());
+            response_json!(
+                status: hyper::StatusCode::NOT_FOUND,
+                body: &serde_json::json!({
+                     "error": "Execution couldn't be found."
+                 })
+            )
+        }
+    }
+}
+
+/// Query parameters "offset", "state" and "task_id" are optional
+pub async fn get_executions(req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let sender = req.data::<Sender<ServerMessage>>().unwrap();
+    let query_map = match super::ServerUtils::get_qs(&req.uri().to_string()) {
+        Ok(q) => q,
+        Err(e) => {
+            eprintln!("{}", e.to_string());
+            let obj = serde_json::json!({
+                "error": "Malformed query."
+            });
+            let obj = obj.to_string();
+            return response_json!(status: hyper::StatusCode::BAD_REQUEST, body: &obj);
+        }
+    };
+    let offset = query_map.get("offset").and_then(|x| x.parse::<i64>().ok());
+    let state = match query_map.get("state").map(|s| ExecutionState::from_str(s)).transpose() {
+        Ok(s) => s,
+        Err(e) => {
+            let obj = serde_json::json!({
+                "error": e.to_string()
+            });
+            let obj = obj.to_string();
+            return response_json!(status: hyper::StatusCode::BAD_REQUEST, body: &obj);
+        }
+    };
+    let task_id = match query_map.get("task_id").map(|id| Uuid::from_str(id)).transpose() {
+        Ok(id) => id,
+        Err(_) => {
+            let obj = serde_json::json!({
+                "error": "Malformed query parameter: task_id."
+            });
+            let obj = obj.to_string();
+            return response_json!(status: hyper::StatusCode::BAD_REQUEST, body: &obj);
+        }
+    };
+    reactor_failed!(sender
+        .send(ServerMessage::GetExecutions { offset, state, task_id, resp: tx })
+        .await, "GetExecutions");
+    match rx.await.unwrap() {
+        Ok(executions) => response_json!(body: &executions),
+        Err(e) => {
+            error!("{}", e.to_string());
+            return Err(anyhow::anyhow!(serde_json::json!({
+                "error": "DB Error."
+            })));
+        }
+    }
+}
+
 pub async fn delete_task(mut req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
     #[derive(Debug, Serialize, Deserialize)]
     struct RequestBody {
         task_id: Uuid,
     }
     let (tx, rx) = tokio::sync::oneshot::channel();
+    let actor = super::auth::get_actor(&req);
     let body = req.body_mut();
     if let Some(Ok(body)) = body.data().await {
         if let Ok(json_value) =
@@ -262,7 +706,7 @@ pub async fn delete_task(mut req: Request<Body>) -> Result<Response<Body>, anyho
             let sender = req.data::<Sender<ServerMessage>>().unwrap();
             let task_id = json_value.task_id;
             reactor_failed!(sender
-                .send(ServerMessage::DeleteTask { task_id, resp: tx })
+                .send(ServerMessage::DeleteTask { task_id, actor, resp: tx })
                 .await, "DeleteTask");
             if let Ok(_result) = rx.await {
                 let status = "success";
@@ -290,8 +734,23 @@ pub async fn create_task(mut req: Request<Body>) -> Result<Response<Body>, anyho
         frequency: String,
         task_type: String,
         task_props: Value,
+        #[serde(default)]
+        timeout: Option<i64>,
+        #[serde(default)]
+        retry_policy: Option<Value>,
+        #[serde(default)]
+        overlap_policy: Option<String>,
+        #[serde(default)]
+        hook_secret: Option<String>,
+        #[serde(default)]
+        hook_slug: Option<String>,
+        #[serde(default)]
+        hook_method: Option<String>,
+        #[serde(default)]
+        hook_filters: Option<Value>,
     }
     let (tx, rx) = tokio::sync::oneshot::channel();
+    let actor = super::auth::get_actor(&req);
     let body = req.body_mut();
     if let Some(Ok(body)) = body.data().await {
         if let Ok(json_value) =
@@ -304,6 +763,14 @@ pub async fn create_task(mut req: Request<Body>) -> Result<Response<Body>, anyho
                     frequency: json_value.frequency,
                     task_type: json_value.task_type,
                     task_props: json_value.task_props,
+                    timeout: json_value.timeout,
+                    retry_policy: json_value.retry_policy,
+                    overlap_policy: json_value.overlap_policy,
+                    hook_secret: json_value.hook_secret,
+                    hook_slug: json_value.hook_slug,
+                    hook_method: json_value.hook_method,
+                    hook_filters: json_value.hook_filters,
+                    actor,
                     resp: tx,
                 })
                 .await, "CreateTask");
@@ -371,8 +838,23 @@ pub async fn update_task(mut req: Request<Body>) -> Result<Response<Body>, anyho
         frequency: String,
         task_type: String,
         task_props: Value,
+        #[serde(default)]
+        timeout: Option<i64>,
+        #[serde(default)]
+        retry_policy: Option<Value>,
+        #[serde(default)]
+        overlap_policy: Option<String>,
+        #[serde(default)]
+        hook_secret: Option<String>,
+        #[serde(default)]
+        hook_slug: Option<String>,
+        #[serde(default)]
+        hook_method: Option<String>,
+        #[serde(default)]
+        hook_filters: Option<Value>,
     }
     let (tx, rx) = tokio::sync::oneshot::channel();
+    let actor = super::auth::get_actor(&req);
     let task_id = match req.param("id") {
         Some(id) => Uuid::from_str(id).unwrap(),
         None => {
@@ -396,6 +878,14 @@ pub async fn update_task(mut req: Request<Body>) -> Result<Response<Body>, anyho
                     task_name: json_value.task_name,
                     frequency: json_value.frequency,
                     task_props: json_value.task_props,
+                    timeout: json_value.timeout,
+                    retry_policy: json_value.retry_policy,
+                    overlap_policy: json_value.overlap_policy,
+                    hook_secret: json_value.hook_secret,
+                    hook_slug: json_value.hook_slug,
+                    hook_method: json_value.hook_method,
+                    hook_filters: json_value.hook_filters,
+                    actor,
                     resp: tx,
                 })
                 .await, "UpdateTask");
@@ -549,3 +1039,277 @@ pub async fn get_reports(req: Request<Body>) -> Result<Response<Body>, anyhow::E
         }
     };
 }
+
+/// Newest first, filtered on ?target_id, ?action and ?actor_type
+pub async fn get_audit(req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let sender = req.data::<Sender<ServerMessage>>().unwrap();
+    let query_map = match super::ServerUtils::get_qs(&req.uri().to_string()) {
+        Ok(q) => q,
+        Err(e) => {
+            eprintln!("{}", e.to_string());
+            let obj = serde_json::json!({
+                "error": "Malformed query."
+            });
+            let obj = obj.to_string();
+            return response_json!(status: hyper::StatusCode::BAD_REQUEST, body: &obj);
+        }
+    };
+    let offset = query_map.get("offset").and_then(|x| x.parse::<i64>().ok());
+    let target_id = match query_map.get("target_id").map(|x| Uuid::from_str(x)) {
+        Some(Ok(id)) => Some(id),
+        Some(Err(_)) => {
+            let obj = serde_json::json!({
+                "error": "target_id should be a UUID."
+            });
+            let obj = obj.to_string();
+            return response_json!(status: hyper::StatusCode::BAD_REQUEST, body: &obj);
+        }
+        None => None,
+    };
+    reactor_failed!(sender
+        .send(ServerMessage::GetAuditEntries {
+            offset,
+            target_id,
+            action: query_map.get("action").cloned(),
+            actor_type: query_map.get("actor_type").cloned(),
+            resp: tx,
+        })
+        .await, "GetAuditEntries");
+    let result = rx.await.unwrap();
+    match result {
+        Ok(entries) => {
+            return response_json!(body: &entries);
+        }
+        Err(e) => {
+            error!("{}", e.to_string());
+            return Err(anyhow::anyhow!(serde_json::json!({
+                "error": "DB Error."
+            })));
+        }
+    };
+}
+
+/// Names and dates only, values are never sent back
+pub async fn get_secrets(req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let sender = req.data::<Sender<ServerMessage>>().unwrap();
+    reactor_failed!(sender
+        .send(ServerMessage::GetSecrets { resp: tx })
+        .await, "GetSecrets");
+    let result = rx.await.unwrap();
+    match result {
+        Ok(secrets) => {
+            return response_json!(body: &secrets);
+        }
+        Err(e) => {
+            error!("{}", e.to_string());
+            return Err(anyhow::anyhow!(serde_json::json!({
+                "error": "DB Error."
+            })));
+        }
+    };
+}
+
+/// Takes the name and the value, or only the value when the name is in the url
+pub async fn set_secret(mut req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
+    #[derive(Debug, Deserialize)]
+    struct RequestBody {
+        #[serde(default)]
+        name: Option<String>,
+        value: String,
+    }
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let actor = super::auth::get_actor(&req);
+    let name_in_url = req.param("name").cloned();
+    let body = req.body_mut();
+    if let Some(Ok(body)) = body.data().await {
+        if let Ok(json_value) = serde_json::from_slice::<RequestBody>(&body) {
+            let sender = req.data::<Sender<ServerMessage>>().unwrap();
+            let message = match (name_in_url, json_value.name) {
+                (Some(name), _) => ServerMessage::UpdateSecret { name, value: json_value.value, actor, resp: tx },
+                (None, Some(name)) => ServerMessage::CreateSecret { name, value: json_value.value, actor, resp: tx },
+                (None, None) => {
+                    let obj = serde_json::json!({
+                        "error": "Name of the secret is missing."
+                    });
+                    let obj = obj.to_string();
+                    return response_json!(status: hyper::StatusCode::BAD_REQUEST, body: &obj);
+                }
+            };
+            reactor_failed!(sender.send(message).await, "SetSecret");
+            return match rx.await.unwrap() {
+                Ok(secret) => response_json!(body: &secret),
+                Err(e) => {
+                    let obj = serde_json::json!({
+                        "error": e.to_string()
+                    });
+                    let obj = obj.to_string();
+                    response_json!(status: hyper::StatusCode::BAD_REQUEST, body: &obj)
+                }
+            };
+        }
+    }
+    empty_malformed_body!()
+}
+
+pub async fn delete_secret(req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    let sender = req.data::<Sender<ServerMessage>>().unwrap();
+    let actor = super::auth::get_actor(&req);
+    let name = req.param("name").cloned().unwrap_or_default();
+    reactor_failed!(sender
+        .send(ServerMessage::DeleteSecret { name, actor, resp: tx })
+        .await, "DeleteSecret");
+    match rx.await.unwrap() {
+        Ok(secret) => {
+            return response_json!(body: &secret);
+        }
+        Err(e) => {
+            let obj = serde_json::json!({
+                "error": e.to_string()
+            });
+            let obj = obj.to_string();
+            return response_json!(status: hyper::StatusCode::NOT_FOUND, body: &obj);
+        }
+    };
+}
+
+fn session_cookie(token: &str, max_age: u64) -> String {
+    let cookie = format!(
+        "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Strict",
+        SessionModel::COOKIE_NAME,
+        token,
+        max_age
+    );
+    // Browsers only send it over HTTPS then
+    if CONFIG.tls.is_some() {
+        return format!("{}; Secure", cookie);
+    }
+    return cookie;
+}
+
+/// Takes a JSON body, or the form of the login page
+/// Form logins are redirected to the panel, JSON ones get the CSRF token to send with their requests
+pub async fn login(mut req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
+    #[derive(Debug, Deserialize)]
+    struct RequestBody {
+        username: String,
+        password: String,
+    }
+    let is_form = req
+        .headers()
+        .get(hyper::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("application/x-www-form-urlencoded"))
+        .unwrap_or(false);
+    let body = match req.body_mut().data().await {
+        Some(Ok(body)) => body,
+        _ => empty_malformed_body!(),
+    };
+    let credentials = if is_form {
+        let form: std::collections::HashMap<String, String> = url::form_urlencoded::parse(&body).into_owned().collect();
+        match (form.get("username"), form.get("password")) {
+            (Some(username), Some(password)) => RequestBody {
+                username: username.clone(),
+                password: password.clone(),
+            },
+            _ => empty_malformed_body!(),
+        }
+    } else {
+        match serde_json::from_slice::<RequestBody>(&body) {
+            Ok(c) => c,
+            Err(_) => empty_malformed_body!(),
+        }
+    };
+    let sender = req.data::<Sender<ServerMessage>>().unwrap();
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    reactor_failed!(sender
+        .send(ServerMessage::Login {
+            username: credentials.username,
+            password: credentials.password,
+            resp: tx,
+        })
+        .await, "Login");
+    let (user, session, token) = match rx.await.unwrap() {
+        Ok(s) => s,
+        Err(e) => {
+            if is_form {
+                return Ok(Response::builder()
+                    .status(hyper::StatusCode::SEE_OTHER)
+                    .header(hyper::header::LOCATION, "/login?failed")
+                    .body(Body::empty())
+                    .unwrap());
+            }
+            return response_json!(
+                status: hyper::StatusCode::UNAUTHORIZED,
+                body: &serde_json::json!({ "error": e.to_string() })
+            );
+        }
+    };
+    info!("User {} logged in", user.username);
+    let cookie = session_cookie(&token, CONFIG.session_lifetime);
+    if is_form {
+        return Ok(Response::builder()
+            .status(hyper::StatusCode::SEE_OTHER)
+            .header(hyper::header::LOCATION, "/app")
+            .header(hyper::header::SET_COOKIE, cookie)
+            .body(Body::empty())
+            .unwrap());
+    }
+    let body = serde_json::json!({
+        "status": "success",
+        "user": user,
+        "csrf_token": session.csrf_token,
+        "expires_at": session.expires_at,
+    });
+    return Ok(Response::builder()
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .header(hyper::header::SET_COOKIE, cookie)
+        .body(body.to_string().into())
+        .unwrap());
+}
+
+pub async fn logout(req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
+    let session_id = match req.context::<super::Caller>() {
+        Some(super::Caller::User(_, session)) => session.id,
+        _ => {
+            return response_json!(
+                status: hyper::StatusCode::BAD_REQUEST,
+                body: &serde_json::json!({ "error": "Only sessions can log out, revoke API keys through the socket." })
+            );
+        }
+    };
+    let sender = req.data::<Sender<ServerMessage>>().unwrap();
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    reactor_failed!(sender
+        .send(ServerMessage::Logout { session_id, resp: tx })
+        .await, "Logout");
+    if let Ok(Err(e)) = rx.await {
+        error!("{}", e.to_string());
+        return Err(anyhow::anyhow!(serde_json::json!({
+            "error": "DB Error."
+        })));
+    }
+    return Ok(Response::builder()
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .header(hyper::header::SET_COOKIE, session_cookie("", 0))
+        .body(serde_json::json!({ "status": "success" }).to_string().into())
+        .unwrap());
+}
+
+/// The panel asks who's logged in, and for the CSRF token it has to send back
+pub async fn get_me(req: Request<Body>) -> Result<Response<Body>, anyhow::Error> {
+    return match req.context::<super::Caller>() {
+        Some(super::Caller::User(user, session)) => response_json!(body: &serde_json::json!({
+            "user": user,
+            "csrf_token": session.csrf_token,
+            "expires_at": session.expires_at,
+        })),
+        Some(super::Caller::ApiKey(key)) => response_json!(body: &serde_json::json!({ "api_key": key })),
+        _ => response_json!(
+            status: hyper::StatusCode::UNAUTHORIZED,
+            body: &serde_json::json!({ "error": "Not logged in." })
+        ),
+    };
+}
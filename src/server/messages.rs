@@ -1,7 +1,10 @@
 use uuid::Uuid;
 
 use crate::{
-    models::{ExecutionReport, TaskModel},
+    models::{
+        Actor, ApiKeyModel, AuditModel, ExecutionModel, ExecutionReport, ExecutionState, HookPayload, SecretModel, SessionModel, TaskError,
+        TaskModel, UserModel,
+    },
     types::OneShotMessageResponse,
 };
 
@@ -15,16 +18,40 @@ pub enum ServerMessage {
         task_id: Uuid,
         resp: OneShotMessageResponse<anyhow::Result<TaskModel>>,
     },
+    GetTaskBySlug {
+        slug: String,
+        resp: OneShotMessageResponse<anyhow::Result<TaskModel>>,
+    },
     ExecuteTask {
         task_id: Uuid,
-        resp: OneShotMessageResponse<bool>,
+        // Only when it's triggered through the hook url
+        payload: Option<HookPayload>,
+        actor: Actor,
+        resp: OneShotMessageResponse<Option<Uuid>>,
     },
     AbortTask {
-        task_id: Uuid,
+        id: Uuid,
+        actor: Actor,
         resp: OneShotMessageResponse<bool>,
     },
+    GetExecution {
+        execution_id: Uuid,
+        resp: OneShotMessageResponse<anyhow::Result<ExecutionModel>>,
+    },
+    // Responds once the waiter is registered, the receiver fires when the run is finished
+    NotifyWhenFinished {
+        execution_id: Uuid,
+        resp: OneShotMessageResponse<tokio::sync::oneshot::Receiver<()>>,
+    },
+    GetExecutions {
+        offset: Option<i64>,
+        state: Option<ExecutionState>,
+        task_id: Option<Uuid>,
+        resp: OneShotMessageResponse<anyhow::Result<Vec<ExecutionModel>>>,
+    },
     DeleteTask {
         task_id: Uuid,
+        actor: Actor,
         resp: OneShotMessageResponse<anyhow::Result<TaskModel>>,
     },
     CreateTask {
@@ -32,6 +59,14 @@ pub enum ServerMessage {
         frequency: String,
         task_type: String,
         task_props: serde_json::Value,
+        timeout: Option<i64>,
+        retry_policy: Option<serde_json::Value>,
+        overlap_policy: Option<String>,
+        hook_secret: Option<String>,
+        hook_slug: Option<String>,
+        hook_method: Option<String>,
+        hook_filters: Option<serde_json::Value>,
+        actor: Actor,
         resp: OneShotMessageResponse<anyhow::Result<TaskModel>>,
     },
     GetActiveTasks {
@@ -42,6 +77,14 @@ pub enum ServerMessage {
         task_name: String,
         frequency: String,
         task_props: serde_json::Value,
+        timeout: Option<i64>,
+        retry_policy: Option<serde_json::Value>,
+        overlap_policy: Option<String>,
+        hook_secret: Option<String>,
+        hook_slug: Option<String>,
+        hook_method: Option<String>,
+        hook_filters: Option<serde_json::Value>,
+        actor: Actor,
         resp: OneShotMessageResponse<anyhow::Result<TaskModel>>,
     },
     GetExecutionReportsForTask {
@@ -57,6 +100,57 @@ pub enum ServerMessage {
         report_id: Uuid,
         resp: OneShotMessageResponse<anyhow::Result<ExecutionReport>>,
     },
+    CreateError {
+        error: TaskError,
+    },
+    // Finds a key that isn't revoked by its hash
+    GetApiKey {
+        key_hash: String,
+        resp: OneShotMessageResponse<anyhow::Result<ApiKeyModel>>,
+    },
+    // Responds with the new session and its token when the password is right
+    Login {
+        username: String,
+        password: String,
+        resp: OneShotMessageResponse<anyhow::Result<(UserModel, SessionModel, String)>>,
+    },
+    // Finds a session that hasn't expired by the hash of its token
+    GetSession {
+        token_hash: String,
+        resp: OneShotMessageResponse<anyhow::Result<(UserModel, SessionModel)>>,
+    },
+    Logout {
+        session_id: Uuid,
+        resp: OneShotMessageResponse<anyhow::Result<u64>>,
+    },
+    GetAuditEntries {
+        offset: Option<i64>,
+        // Task, execution, key or user the entries are about
+        target_id: Option<Uuid>,
+        action: Option<String>,
+        actor_type: Option<String>,
+        resp: OneShotMessageResponse<anyhow::Result<Vec<AuditModel>>>,
+    },
+    GetSecrets {
+        resp: OneShotMessageResponse<anyhow::Result<Vec<SecretModel>>>,
+    },
+    CreateSecret {
+        name: String,
+        value: String,
+        actor: Actor,
+        resp: OneShotMessageResponse<anyhow::Result<SecretModel>>,
+    },
+    UpdateSecret {
+        name: String,
+        value: String,
+        actor: Actor,
+        resp: OneShotMessageResponse<anyhow::Result<SecretModel>>,
+    },
+    DeleteSecret {
+        name: String,
+        actor: Actor,
+        resp: OneShotMessageResponse<anyhow::Result<SecretModel>>,
+    },
 }
 
 impl ServerMessage {
@@ -64,8 +158,12 @@ impl ServerMessage {
         return match self {
             ServerMessage::GetTasks { .. } => "GetTasks",
             ServerMessage::GetTask { .. } => "GetTask",
+            ServerMessage::GetTaskBySlug { .. } => "GetTaskBySlug",
             ServerMessage::ExecuteTask { .. } => "ExecuteTask",
             ServerMessage::AbortTask { .. } => "AbortTask",
+            ServerMessage::GetExecution { .. } => "GetExecution",
+            ServerMessage::NotifyWhenFinished { .. } => "NotifyWhenFinished",
+            ServerMessage::GetExecutions { .. } => "GetExecutions",
             ServerMessage::DeleteTask { .. } => "DeleteTask",
             ServerMessage::CreateTask { .. } => "CreateTask",
             ServerMessage::GetActiveTasks { .. } => "GetActiveTasks",
@@ -73,6 +171,16 @@ impl ServerMessage {
             ServerMessage::GetExecutionReportsForTask { .. } => "GetExecutionReportsForTask",
             ServerMessage::GetExecutionReports { .. } => "GetExecutionReports",
             ServerMessage::GetExecutionReport { .. } => "GetExecutionReport",
+            ServerMessage::CreateError { .. } => "CreateError",
+            ServerMessage::GetApiKey { .. } => "GetApiKey",
+            ServerMessage::Login { .. } => "Login",
+            ServerMessage::GetSession { .. } => "GetSession",
+            ServerMessage::Logout { .. } => "Logout",
+            ServerMessage::GetAuditEntries { .. } => "GetAuditEntries",
+            ServerMessage::GetSecrets { .. } => "GetSecrets",
+            ServerMessage::CreateSecret { .. } => "CreateSecret",
+            ServerMessage::UpdateSecret { .. } => "UpdateSecret",
+            ServerMessage::DeleteSecret { .. } => "DeleteSecret",
         };
     }
 }
@@ -2,21 +2,26 @@ use hyper::Server as HyperServer;
 use std::{net::SocketAddr, str::FromStr};
 use tokio::sync::mpsc::Sender;
 
-use routerify::{Router, RouterService};
+use routerify::{Middleware, Router, RouterService};
 
+mod auth;
 mod handlers;
 mod messages;
+#[cfg(feature = "tls")]
+mod tls;
 mod utils;
 use handlers::{
     abort_task, create_task, delete_task, exec_task, exec_task_url, get_active_tasks, get_report,
-    get_reports, get_reports_for_task, get_task, get_tasks, update_task, abort_task_url
+    get_audit, get_reports, get_reports_for_task, get_task, get_tasks, update_task, abort_task_url,
+    get_execution, get_executions, exec_task_hook, login, logout, get_me, get_secrets, set_secret, delete_secret,
 };
+pub use auth::Caller;
 pub use messages::ServerMessage;
 use tracing::info;
 use utils as ServerUtils;
 
 mod app_router;
-use app_router::app_router;
+use app_router::{app_router, login_page};
 
 use crate::CONFIG;
 
@@ -33,16 +38,23 @@ impl Server {
             // Specify the state data which will be available to every route handlers,
             // error handler and middlewares.
             .data(self.message_sender.clone())
-            // .middleware(Middleware::pre(logger))
+            .middleware(Middleware::pre(auth::authenticate))
             .post("/execute", exec_task)
             .post("/execute/:id", exec_task_url)
+            .any_method("/hooks/*", exec_task_hook)
             .post("/abort", abort_task)
             .post("/abort/:id", abort_task_url)
+            .get("/execution/:id", get_execution)
+            .get("/executions", get_executions)
             .get("/activetasks", get_active_tasks)
             .get("/task/:id/reports", get_reports_for_task)
             .get("/reports", get_reports)
-            .get("/reports/:id", get_report);
-            // .err_handler_with_info(error_handler)
+            .get("/audit", get_audit)
+            .get("/reports/:id", get_report)
+            .post("/login", login)
+            .post("/logout", logout)
+            .get("/me", get_me)
+            .err_handler(auth::error_handler);
         
         #[cfg(feature = "server-crud")]
         if CONFIG.get_features().get("server_crud").unwrap().eq(&true) {
@@ -51,21 +63,49 @@ impl Server {
                 .get("/tasks/:id", get_task)
                 .post("/tasks", create_task)
                 .post("/tasks/:id", update_task)
-                .delete("/tasks", delete_task);
+                .delete("/tasks", delete_task)
+                .get("/secrets", get_secrets)
+                .post("/secrets", set_secret)
+                .post("/secrets/:name", set_secret)
+                .delete("/secrets/:name", delete_secret);
         }
 
         #[cfg(feature = "panel")]
         if CONFIG.get_features().get("panel").unwrap().eq(&true) {
-            router = router.scope("/app", app_router());
+            router = router.get("/login", login_page).scope("/app", app_router());
         }
         let router = router.build().unwrap();
-        let service = RouterService::new(router).unwrap();
         let addr = SocketAddr::from_str(format!("{}:{}", host, port).as_str())
             .expect("Invalid host or port.");
+        // Builds without the tls feature refuse a config with TLS when it's loaded
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &CONFIG.tls {
+            return Self::listen_tls(router, addr, tls.clone()).await;
+        }
+        let service = RouterService::new(router).unwrap();
         let server = HyperServer::bind(&addr).serve(service);
         info!("Server started listening on {}", addr);
         if let Err(err) = server.await {
             eprintln!("Server error: {}", err);
         }
     }
+    #[cfg(feature = "tls")]
+    async fn listen_tls(router: Router<hyper::Body, anyhow::Error>, addr: SocketAddr, tls: crate::config::TlsConfig) {
+        // RouterService only takes plain connections, so the services are built here with the remote address
+        let builder = routerify::RequestServiceBuilder::new(router).unwrap();
+        let accept = match tls::TlsAccept::bind(&addr, tls).await {
+            Ok(a) => a,
+            Err(e) => panic!("Couldn't start the TLS server, {}", e),
+        };
+        let make_service = hyper::service::make_service_fn(move |conn: &tokio_rustls::server::TlsStream<tokio::net::TcpStream>| {
+            let remote_addr = conn.get_ref().0.peer_addr().unwrap_or(addr);
+            let service = builder.build(remote_addr);
+            async move { Ok::<_, std::convert::Infallible>(service) }
+        });
+        let server = HyperServer::builder(accept).serve(make_service);
+        info!("Server started listening on {} with TLS", addr);
+        if let Err(err) = server.await {
+            eprintln!("Server error: {}", err);
+        }
+    }
 }
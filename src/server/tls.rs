@@ -0,0 +1,175 @@
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    pin::Pin,
+    sync::{Arc, RwLock},
+    task::{Context, Poll},
+    time::{Duration, SystemTime},
+};
+
+use hyper::server::accept::Accept;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+};
+use tokio_rustls::{
+    rustls::{
+        internal::pemfile, AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient, NoClientAuth,
+        RootCertStore, ServerConfig,
+    },
+    server::TlsStream,
+    TlsAcceptor,
+};
+use tracing::{error, info};
+
+use crate::config::{Config, TlsConfig};
+
+// Clients that don't finish the handshake in time are dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+type SharedConfig = Arc<RwLock<Arc<ServerConfig>>>;
+
+/// Connections that went through the handshake, hyper takes them from here
+pub struct TlsAccept {
+    inner: Receiver<TlsStream<TcpStream>>,
+}
+
+impl Accept for TlsAccept {
+    type Conn = TlsStream<TcpStream>;
+    type Error = std::io::Error;
+
+    fn poll_accept(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        match self.get_mut().inner.poll_recv(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Some(stream)) => Poll::Ready(Some(Ok(stream))),
+            Poll::Ready(None) => Poll::Ready(None),
+        }
+    }
+}
+
+impl TlsAccept {
+    pub async fn bind(addr: &SocketAddr, tls: TlsConfig) -> Result<Self, anyhow::Error> {
+        let config: SharedConfig = Arc::new(RwLock::new(Arc::new(load_server_config(&tls)?)));
+        let listener = TcpListener::bind(addr).await?;
+        let (tx, rx) = mpsc::channel(128);
+        tokio::spawn(accept(listener, config.clone(), tx));
+        tokio::spawn(watch(tls, config));
+        return Ok(Self { inner: rx });
+    }
+}
+
+async fn accept(listener: TcpListener, config: SharedConfig, tx: Sender<TlsStream<TcpStream>>) {
+    loop {
+        let (stream, addr) = match listener.accept().await {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Couldn't accept a connection, {}", e);
+                continue;
+            }
+        };
+        // The config is picked per connection, so reloads apply to the next one
+        let acceptor = TlsAcceptor::from(config.read().unwrap().clone());
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    tx.send(stream).await.unwrap_or_default();
+                }
+                Ok(Err(e)) => info!("TLS handshake with {} failed, {}", addr, e),
+                Err(_) => info!("TLS handshake with {} timed out", addr),
+            }
+        });
+    }
+}
+
+// Certificates are renewed in place, so the files are checked every now and then
+async fn watch(tls: TlsConfig, config: SharedConfig) {
+    let mut modified = get_modified(&tls);
+    let mut interval = tokio::time::interval(Duration::from_secs(tls.reload_interval.max(1)));
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let current = get_modified(&tls);
+        if current == modified {
+            continue;
+        }
+        match load_server_config(&tls) {
+            Ok(server_config) => {
+                *config.write().unwrap() = Arc::new(server_config);
+                modified = current;
+                info!("TLS certificates are reloaded");
+            }
+            // Probably caught in the middle of a renewal, the old ones are kept until the next try
+            Err(e) => error!("Couldn't reload the TLS certificates, {}", e),
+        }
+    }
+}
+
+fn get_paths(tls: &TlsConfig) -> Vec<PathBuf> {
+    let mut paths = vec![Config::resolve_path(&tls.cert_path), Config::resolve_path(&tls.key_path)];
+    if let Some(client_ca_path) = &tls.client_ca_path {
+        paths.push(Config::resolve_path(client_ca_path));
+    }
+    return paths;
+}
+
+fn get_modified(tls: &TlsConfig) -> Vec<Option<SystemTime>> {
+    get_paths(tls)
+        .iter()
+        .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+fn open(path: &PathBuf) -> Result<std::io::BufReader<std::fs::File>, anyhow::Error> {
+    match std::fs::File::open(path) {
+        Ok(f) => Ok(std::io::BufReader::new(f)),
+        Err(e) => Err(anyhow::anyhow!("Couldn't open {}, {}", path.display(), e)),
+    }
+}
+
+pub fn load_server_config(tls: &TlsConfig) -> Result<ServerConfig, anyhow::Error> {
+    let cert_path = Config::resolve_path(&tls.cert_path);
+    let certs = pemfile::certs(&mut open(&cert_path)?).unwrap_or_default();
+    if certs.is_empty() {
+        return Err(anyhow::anyhow!("No certificates found in {}", cert_path.display()));
+    }
+    let key_path = Config::resolve_path(&tls.key_path);
+    let mut keys = pemfile::pkcs8_private_keys(&mut open(&key_path)?).unwrap_or_default();
+    if keys.is_empty() {
+        keys = pemfile::rsa_private_keys(&mut open(&key_path)?).unwrap_or_default();
+    }
+    let key = match keys.into_iter().next() {
+        Some(k) => k,
+        None => {
+            return Err(anyhow::anyhow!(
+                "No PKCS#8 or RSA private key found in {}",
+                key_path.display()
+            ))
+        }
+    };
+    let verifier = match &tls.client_ca_path {
+        Some(client_ca_path) => {
+            let client_ca_path = Config::resolve_path(client_ca_path);
+            let mut roots = RootCertStore::empty();
+            match roots.add_pem_file(&mut open(&client_ca_path)?) {
+                Ok((added, _)) if added > 0 => {}
+                _ => return Err(anyhow::anyhow!("No CA certificates found in {}", client_ca_path.display())),
+            }
+            if tls.client_cert_required {
+                AllowAnyAuthenticatedClient::new(roots)
+            } else {
+                AllowAnyAnonymousOrAuthenticatedClient::new(roots)
+            }
+        }
+        None => NoClientAuth::new(),
+    };
+    let mut config = ServerConfig::new(verifier);
+    config.set_protocols(&[b"http/1.1".to_vec()]);
+    if let Err(e) = config.set_single_cert(certs, key) {
+        return Err(anyhow::anyhow!("Certificate and key don't work together, {}", e));
+    }
+    return Ok(config);
+}
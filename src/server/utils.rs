@@ -1,5 +1,8 @@
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
 use std::collections::HashMap;
-use url::Url;
+use std::net::IpAddr;
+use url::{Host, Url};
 
 pub fn get_qs(from: &str) -> anyhow::Result<HashMap<String, String>> {
     let parsed_url = match Url::parse(from) {
@@ -14,3 +17,166 @@ pub fn get_qs(from: &str) -> anyhow::Result<HashMap<String, String>> {
     let hash_query: HashMap<_, _> = parsed_url.query_pairs().into_owned().collect();
     return Ok(hash_query);
 }
+
+// Durations like "30s", "5m", "1h" or "500ms", plain numbers are seconds
+pub fn parse_duration(from: &str) -> Option<std::time::Duration> {
+    let from = from.trim();
+    let split = from.find(|c: char| !c.is_ascii_digit()).unwrap_or_else(|| from.len());
+    let (number, unit) = from.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let millis = match unit {
+        "ms" => number,
+        "" | "s" => number.checked_mul(1000)?,
+        "m" => number.checked_mul(60 * 1000)?,
+        "h" => number.checked_mul(60 * 60 * 1000)?,
+        _ => return None,
+    };
+    return Some(std::time::Duration::from_millis(millis));
+}
+
+// Checks a hook request against the secret of the task, either a GitHub style
+// signature of the body or the secret itself as a token
+pub fn verify_hook_secret(
+    secret: &str,
+    headers: &hyper::HeaderMap,
+    body: &[u8],
+) -> Result<(), &'static str> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    if let Some(signature) = header("X-Hub-Signature-256") {
+        let signature = match signature.strip_prefix("sha256=").map(hex::decode) {
+            Some(Ok(s)) => s,
+            _ => return Err("signature is malformed"),
+        };
+        let mut mac = match Hmac::<Sha256>::new_from_slice(secret.as_bytes()) {
+            Ok(m) => m,
+            Err(_) => return Err("secret can't be used as a key"),
+        };
+        mac.update(body);
+        return mac.verify(&signature).map_err(|_| "signature doesn't match");
+    }
+    if let Some(token) = header("X-StewardX-Token").or_else(|| header("X-Gitlab-Token")) {
+        return match constant_time_eq(token.as_bytes(), secret.as_bytes()) {
+            true => Ok(()),
+            false => Err("token doesn't match"),
+        };
+    }
+    return Err("signature or token is missing");
+}
+
+// Signature of a body StewardX sends, in the same format as the hooks it receives
+pub fn sign_body(secret: &str, body: &[u8]) -> Result<String, &'static str> {
+    let mut mac = match Hmac::<Sha256>::new_from_slice(secret.as_bytes()) {
+        Ok(m) => m,
+        Err(_) => return Err("secret can't be used as a key"),
+    };
+    mac.update(body);
+    return Ok(format!("sha256={}", hex::encode(mac.finalize().into_bytes())));
+}
+
+// Loopback, private, link-local and the other addresses that aren't reachable from the internet
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let octets = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                // Shared address space of the carrier-grade NATs, 100.64.0.0/10
+                || (octets[0] == 100 && (octets[1] & 0xc0) == 64)
+                || octets[0] == 0
+                || octets[0] >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let segments = ip.segments();
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local, fc00::/7
+                || (segments[0] & 0xfe00) == 0xfc00
+                // Link-local, fe80::/10
+                || (segments[0] & 0xffc0) == 0xfe80
+                // Documentation, 2001:db8::/32
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8))
+        }
+    }
+}
+
+// Callbacks carry the output of the run, so only the allowed hosts or public addresses can get them
+pub fn check_callback_url(url: &Url, allowed_hosts: &[String]) -> Result<(), String> {
+    let host = match url.host() {
+        Some(h) => h,
+        None => return Err(String::from("it doesn't have a host")),
+    };
+    let host_str = url.host_str().unwrap_or_default();
+    if !allowed_hosts.is_empty() {
+        if allowed_hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(host_str)) {
+            return Ok(());
+        }
+        return Err(format!("{} isn't one of the callback_hosts", host_str));
+    }
+    return match host {
+        Host::Ipv4(ip) if !is_public_ip(IpAddr::V4(ip)) => Err(format!("{} isn't a public address", ip)),
+        Host::Ipv6(ip) if !is_public_ip(IpAddr::V6(ip)) => Err(format!("{} isn't a public address", ip)),
+        Host::Ipv4(_) | Host::Ipv6(_) => Ok(()),
+        // The client resolves it again when it posts, by then it can point anywhere
+        Host::Domain(domain) => Err(format!("{} has to be one of the callback_hosts", domain)),
+    };
+}
+
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_duration() {
+        assert_eq!(parse_duration("30s"), Some(std::time::Duration::from_secs(30)));
+        assert_eq!(parse_duration("45"), Some(std::time::Duration::from_secs(45)));
+        assert_eq!(parse_duration("2m"), Some(std::time::Duration::from_secs(120)));
+        assert_eq!(parse_duration("500ms"), Some(std::time::Duration::from_millis(500)));
+        assert_eq!(parse_duration("1d"), None);
+        assert_eq!(parse_duration("s"), None);
+    }
+    #[test]
+    fn verifies_hook_secret() {
+        let body = br#"{"ref":"refs/heads/main"}"#;
+        let signature = sign_body("s3cret", body).unwrap();
+
+        let mut headers = hyper::HeaderMap::new();
+        assert_eq!(verify_hook_secret("s3cret", &headers, body), Err("signature or token is missing"));
+        headers.insert("X-Hub-Signature-256", signature.parse().unwrap());
+        assert_eq!(verify_hook_secret("s3cret", &headers, body), Ok(()));
+        assert_eq!(verify_hook_secret("s3cret", &headers, b"{}"), Err("signature doesn't match"));
+        assert_eq!(verify_hook_secret("other", &headers, body), Err("signature doesn't match"));
+
+        let mut headers = hyper::HeaderMap::new();
+        headers.insert("X-StewardX-Token", "s3cret".parse().unwrap());
+        assert_eq!(verify_hook_secret("s3cret", &headers, body), Ok(()));
+        assert_eq!(verify_hook_secret("s3cre", &headers, body), Err("token doesn't match"));
+    }
+    #[test]
+    fn checks_callback_url() {
+        let url = |u: &str| Url::parse(u).unwrap();
+        for private in &["http://127.0.0.1:8080/", "http://169.254.169.254/latest", "http://10.1.2.3/", "http://[::1]/", "http://[fd00::1]/", "http://[::ffff:192.168.1.1]/", "http://localhost/"] {
+            assert_eq!(check_callback_url(&url(private), &[]).is_err(), true, "{}", private);
+        }
+        assert_eq!(check_callback_url(&url("https://93.184.216.34/hook"), &[]), Ok(()));
+        assert_eq!(check_callback_url(&url("https://example.com/hook"), &[]).is_err(), true);
+        let allowed = vec![String::from("ci.internal")];
+        assert_eq!(check_callback_url(&url("http://ci.internal:8080/done"), &allowed), Ok(()));
+        assert_eq!(check_callback_url(&url("https://93.184.216.34/hook"), &allowed).is_err(), true);
+    }
+}
@@ -1,13 +1,234 @@
 mod socket_listener;
 pub mod unix_utils;
-use std::convert::Infallible;
+use std::{convert::Infallible, os::unix::fs::PermissionsExt, str::FromStr};
 
-use hyper::{Body, Request, Response, Server, service::{make_service_fn, service_fn}};
+use hyper::{Body, Request, Response, Server, StatusCode, service::{make_service_fn, service_fn}};
+use tokio::sync::mpsc::Sender;
+use uuid::Uuid;
 
-async fn handle_request(req: Request<Body>) -> Result<Response<Body>, Infallible> {
+use crate::{models::{Actor, ActorKind, Role, Scope}, reactor::ReactorMessage, types::ReactorSender};
+
+// Socket users are told apart by the credentials of their process
+fn get_actor(req: &Request<Body>, peer: &str) -> Actor {
+    let host = req.headers().get("host").and_then(|h| h.to_str().ok()).unwrap_or("");
+    Actor {
+        kind: ActorKind::Socket,
+        id: None,
+        name: peer.to_string(),
+        source_ip: None,
+        request: format!("{} {}{}", req.method(), host, req.uri().path()),
+    }
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string().into())
+        .unwrap()
+}
+
+// curl --unix-socket /tmp/stewardx.sock "http://apikeys/create?name=ci&scopes=tasks:read,execute"
+// curl --unix-socket /tmp/stewardx.sock "http://apikeys/revoke?id=#key id#"
+// curl --unix-socket /tmp/stewardx.sock http://apikeys
+async fn handle_api_keys(req: Request<Body>, reactor_tx: ReactorSender, peer: String) -> Response<Body> {
+    let actor = get_actor(&req, &peer);
+    let query: std::collections::HashMap<String, String> = url::form_urlencoded::parse(req.uri().query().unwrap_or("").as_bytes())
+        .into_owned()
+        .collect();
+    let bad_request = |error: String| json_response(StatusCode::BAD_REQUEST, serde_json::json!({ "error": error }));
+    let result = match req.uri().path().trim_matches('/') {
+        "" => {
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            reactor_tx.send(ReactorMessage::GetApiKeys { resp: tx }).await.unwrap_or_default();
+            rx.await.map(|keys| keys.map(|keys| serde_json::json!(keys)))
+        }
+        "create" => {
+            let name = match query.get("name") {
+                Some(n) if !n.trim().is_empty() => n.trim().to_string(),
+                _ => return bad_request(String::from("Name of the key is missing.")),
+            };
+            let scopes = match Scope::parse_list(query.get("scopes").map(|s| s.as_str()).unwrap_or("")) {
+                Ok(s) => s,
+                Err(e) => return bad_request(e.to_string()),
+            };
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            reactor_tx.send(ReactorMessage::CreateApiKey { name, scopes, actor, resp: tx }).await.unwrap_or_default();
+            // The only time the key is shown
+            rx.await.map(|created| created.map(|(model, key)| {
+                let mut created = serde_json::json!(model);
+                created["key"] = serde_json::json!(key);
+                created
+            }))
+        }
+        "revoke" => {
+            let id = match query.get("id").and_then(|id| Uuid::from_str(id).ok()) {
+                Some(id) => id,
+                None => return bad_request(String::from("Id of the key is missing or malformed.")),
+            };
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            reactor_tx.send(ReactorMessage::RevokeApiKey { id, actor, resp: tx }).await.unwrap_or_default();
+            rx.await.map(|revoked| revoked.map(|model| serde_json::json!(model)))
+        }
+        _ => return bad_request(String::from("Possible commands: apikeys, apikeys/create, apikeys/revoke")),
+    };
+    match result {
+        Ok(Ok(body)) => json_response(StatusCode::OK, body),
+        Ok(Err(e)) => json_response(StatusCode::BAD_REQUEST, serde_json::json!({ "error": e.to_string() })),
+        Err(_) => json_response(StatusCode::INTERNAL_SERVER_ERROR, serde_json::json!({ "error": "Reactor isn't awake." })),
+    }
+}
+
+// Passwords go in the body so they don't end up in the shell history:
+// curl --unix-socket /tmp/stewardx.sock http://users/create --data @- <<< '{"username":"me","password":"...","role":"admin"}'
+// curl --unix-socket /tmp/stewardx.sock http://users/update --data '{"id":"#user id#","role":"viewer"}'
+// curl --unix-socket /tmp/stewardx.sock "http://users/delete?id=#user id#"
+// curl --unix-socket /tmp/stewardx.sock http://users
+async fn handle_users(mut req: Request<Body>, reactor_tx: ReactorSender, peer: String) -> Response<Body> {
+    #[derive(serde::Deserialize)]
+    struct UserBody {
+        id: Option<Uuid>,
+        username: Option<String>,
+        password: Option<String>,
+        role: Option<String>,
+    }
+    let bad_request = |error: String| json_response(StatusCode::BAD_REQUEST, serde_json::json!({ "error": error }));
+    let actor = get_actor(&req, &peer);
+    let path = req.uri().path().trim_matches('/').to_string();
+    let query: std::collections::HashMap<String, String> = url::form_urlencoded::parse(req.uri().query().unwrap_or("").as_bytes())
+        .into_owned()
+        .collect();
+    let body = match path.as_str() {
+        "create" | "update" => {
+            let bytes = hyper::body::to_bytes(req.body_mut()).await.unwrap_or_default();
+            match serde_json::from_slice::<UserBody>(&bytes) {
+                Ok(b) => Some(b),
+                Err(e) => return bad_request(format!("Body is malformed, {}", e)),
+            }
+        }
+        _ => None,
+    };
+    let role = match body.as_ref().and_then(|b| b.role.as_ref()).map(|r| Role::from_str(r)) {
+        Some(Ok(r)) => Some(r),
+        Some(Err(e)) => return bad_request(e.to_string()),
+        None => None,
+    };
+    let result = match (path.as_str(), body) {
+        ("", _) => {
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            reactor_tx.send(ReactorMessage::GetUsers { resp: tx }).await.unwrap_or_default();
+            rx.await.map(|users| users.map(|users| serde_json::json!(users)))
+        }
+        ("create", Some(body)) => {
+            let (username, password, role) = match (body.username, body.password, role) {
+                (Some(u), Some(p), Some(r)) => (u, p, r),
+                _ => return bad_request(String::from("Username, password and role are required.")),
+            };
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            reactor_tx.send(ReactorMessage::CreateUser { username, password, role, actor, resp: tx }).await.unwrap_or_default();
+            rx.await.map(|user| user.map(|user| serde_json::json!(user)))
+        }
+        ("update", Some(body)) => {
+            let id = match body.id {
+                Some(id) => id,
+                None => return bad_request(String::from("Id of the user is missing.")),
+            };
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            reactor_tx
+                .send(ReactorMessage::UpdateUser { id, password: body.password, role, actor, resp: tx })
+                .await
+                .unwrap_or_default();
+            rx.await.map(|user| user.map(|user| serde_json::json!(user)))
+        }
+        ("delete", _) => {
+            let id = match query.get("id").and_then(|id| Uuid::from_str(id).ok()) {
+                Some(id) => id,
+                None => return bad_request(String::from("Id of the user is missing or malformed.")),
+            };
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            reactor_tx.send(ReactorMessage::DeleteUser { id, actor, resp: tx }).await.unwrap_or_default();
+            rx.await.map(|user| user.map(|user| serde_json::json!(user)))
+        }
+        _ => return bad_request(String::from("Possible commands: users, users/create, users/update, users/delete")),
+    };
+    match result {
+        Ok(Ok(body)) => json_response(StatusCode::OK, body),
+        Ok(Err(e)) => json_response(StatusCode::BAD_REQUEST, serde_json::json!({ "error": e.to_string() })),
+        Err(_) => json_response(StatusCode::INTERNAL_SERVER_ERROR, serde_json::json!({ "error": "Reactor isn't awake." })),
+    }
+}
+
+// Values go in the body too, and they're never shown again:
+// curl --unix-socket /tmp/stewardx.sock http://secrets/create --data @- <<< '{"name":"DB_PASSWORD","value":"..."}'
+// curl --unix-socket /tmp/stewardx.sock http://secrets/update --data @- <<< '{"name":"DB_PASSWORD","value":"..."}'
+// curl --unix-socket /tmp/stewardx.sock "http://secrets/delete?name=DB_PASSWORD"
+// curl --unix-socket /tmp/stewardx.sock http://secrets
+async fn handle_secrets(mut req: Request<Body>, reactor_tx: ReactorSender, peer: String) -> Response<Body> {
+    #[derive(serde::Deserialize)]
+    struct SecretBody {
+        name: String,
+        value: String,
+    }
+    let bad_request = |error: String| json_response(StatusCode::BAD_REQUEST, serde_json::json!({ "error": error }));
+    let actor = get_actor(&req, &peer);
+    let path = req.uri().path().trim_matches('/').to_string();
+    let query: std::collections::HashMap<String, String> = url::form_urlencoded::parse(req.uri().query().unwrap_or("").as_bytes())
+        .into_owned()
+        .collect();
+    let body = match path.as_str() {
+        "create" | "update" => {
+            let bytes = hyper::body::to_bytes(req.body_mut()).await.unwrap_or_default();
+            match serde_json::from_slice::<SecretBody>(&bytes) {
+                Ok(b) => Some(b),
+                Err(e) => return bad_request(format!("Body is malformed, {}", e)),
+            }
+        }
+        _ => None,
+    };
+    let result = match (path.as_str(), body) {
+        ("", _) => {
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            reactor_tx.send(ReactorMessage::GetSecrets { resp: tx }).await.unwrap_or_default();
+            rx.await.map(|secrets| secrets.map(|secrets| serde_json::json!(secrets)))
+        }
+        ("create", Some(body)) => {
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            reactor_tx
+                .send(ReactorMessage::CreateSecret { name: body.name, value: body.value, actor, resp: tx })
+                .await
+                .unwrap_or_default();
+            rx.await.map(|secret| secret.map(|secret| serde_json::json!(secret)))
+        }
+        ("update", Some(body)) => {
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            reactor_tx
+                .send(ReactorMessage::UpdateSecret { name: body.name, value: body.value, actor, resp: tx })
+                .await
+                .unwrap_or_default();
+            rx.await.map(|secret| secret.map(|secret| serde_json::json!(secret)))
+        }
+        ("delete", _) => {
+            let name = match query.get("name") {
+                Some(n) if !n.trim().is_empty() => n.trim().to_string(),
+                _ => return bad_request(String::from("Name of the secret is missing.")),
+            };
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            reactor_tx.send(ReactorMessage::DeleteSecret { name, actor, resp: tx }).await.unwrap_or_default();
+            rx.await.map(|secret| secret.map(|secret| serde_json::json!(secret)))
+        }
+        _ => return bad_request(String::from("Possible commands: secrets, secrets/create, secrets/update, secrets/delete")),
+    };
+    match result {
+        Ok(Ok(body)) => json_response(StatusCode::OK, body),
+        Ok(Err(e)) => json_response(StatusCode::BAD_REQUEST, serde_json::json!({ "error": e.to_string() })),
+        Err(_) => json_response(StatusCode::INTERNAL_SERVER_ERROR, serde_json::json!({ "error": "Reactor isn't awake." })),
+    }
+}
+
+async fn handle_request(req: Request<Body>, shutdown_tx: Sender<()>, reactor_tx: ReactorSender, peer: String) -> Result<Response<Body>, Infallible> {
     // We look for the host header for now, might change in future
     // This way we can just curl into it like: curl --unix-socket /tmp/stewardx.sock http:/stop
-    let possible_hosts = ["stop"];
+    let possible_hosts = ["stop", "apikeys", "users", "secrets"];
     let headers = req.headers();
     let host = headers.get("host");
     let no_host = Ok(Response::new(format!("Possible commands: {}", possible_hosts.join(", ")).into()));
@@ -17,11 +238,18 @@ async fn handle_request(req: Request<Body>) -> Result<Response<Body>, Infallible
                 Ok(o) => {
                     match o {
                         "stop" => {
-                            tokio::spawn(async {
-                                tokio::time::sleep(tokio::time::Duration::from_secs_f32(0.25)).await;
-                                unix_utils::exit();
-                            });
-                            return Ok(Response::new("Goodbye!".into()));
+                            // Fails if a shutdown is already going on
+                            shutdown_tx.try_send(()).unwrap_or_default();
+                            return Ok(Response::new("Shutting down, goodbye!".into()));
+                        }
+                        "apikeys" => {
+                            return Ok(handle_api_keys(req, reactor_tx, peer).await);
+                        }
+                        "users" => {
+                            return Ok(handle_users(req, reactor_tx, peer).await);
+                        }
+                        "secrets" => {
+                            return Ok(handle_secrets(req, reactor_tx, peer).await);
                         }
                         _ => {
                             return no_host;
@@ -42,13 +270,27 @@ async fn handle_request(req: Request<Body>) -> Result<Response<Body>, Infallible
 pub struct SocketManager {}
 
 impl SocketManager {
-    pub async fn listen() {
+    pub async fn listen(shutdown_tx: Sender<()>, reactor_tx: ReactorSender) {
+        let socket_path = unix_utils::get_socket_path();
         let listener = socket_listener::UDSAccept {
-            inner: tokio::net::UnixListener::bind(unix_utils::get_socket_path().to_str().unwrap()).unwrap(),
+            inner: tokio::net::UnixListener::bind(socket_path.to_str().unwrap()).unwrap(),
         };
-        let make_svc = make_service_fn(|_conn| async {
-            // service_fn converts our function into a `Service`
-            Ok::<_, Infallible>(service_fn(handle_request))
+        // API keys and users are handed out here, only the user running StewardX should get in
+        std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        let make_svc = make_service_fn(move |conn: &tokio::net::UnixStream| {
+            let shutdown_tx = shutdown_tx.clone();
+            let reactor_tx = reactor_tx.clone();
+            let peer = match conn.peer_cred() {
+                Ok(cred) => match cred.pid() {
+                    Some(pid) => format!("uid {} (pid {})", cred.uid(), pid),
+                    None => format!("uid {}", cred.uid()),
+                },
+                Err(_) => String::from("unknown"),
+            };
+            async move {
+                // service_fn converts our function into a `Service`
+                Ok::<_, Infallible>(service_fn(move |req| handle_request(req, shutdown_tx.clone(), reactor_tx.clone(), peer.clone())))
+            }
         });
     
         let server = Server::builder(listener).serve(make_svc);
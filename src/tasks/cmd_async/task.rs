@@ -1,31 +1,64 @@
 use async_trait::async_trait;
-use futures::StreamExt;
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 use tracing::{instrument, error};
+use std::collections::HashMap;
+use std::ffi::OsString;
 use std::process::Stdio;
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use tokio::{
-    io::{AsyncBufReadExt, BufReader},
-    process::Child,
-};
-use tokio_stream::wrappers::LinesStream;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::UnboundedReceiverStream;
 use uuid::Uuid;
 
 use crate::{
-    models::TaskError,
-    traits::{BoxedStream, Executable, FromJson, GetSerdeFromProps},
+    models::{ExecutionContext, ExecutionProcess, ExitStatus, HookPayload, OutputLine, OutputStream, SecretModel, TaskError},
+    traits::{BoxedStream, Executable, FromJson, GetSerdeFromProps, TaskExecution},
 };
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CmdTask {
     pub id: Uuid,
+    // Split into words like a shell would, but it runs without one unless there's a shell
     pub command: Box<String>,
+    // Runs as it is, command is only there to show it when this isn't empty
+    #[serde(default)]
+    pub argv: Vec<String>,
+    // Program and its arguments that the command is given to, like ["/bin/sh", "-c"]
+    #[serde(default)]
+    pub shell: Option<Vec<String>>,
+    // Absolute path, StewardX's own working directory when it's empty
+    #[serde(default)]
+    pub cwd: Option<String>,
+    // Variables to set, the ones with a null value are unset
+    #[serde(default)]
+    pub env: HashMap<String, Option<String>>,
+    // Starts from an empty environment, otherwise only StewardX's own variables are left out
+    #[serde(default)]
+    pub clear_env: bool,
+    #[serde(default)]
+    pub uid: Option<u32>,
+    #[serde(default)]
+    pub gid: Option<u32>,
+    // In seconds, how long the process group has after SIGTERM before it's killed
+    #[serde(default = "CmdTask::default_kill_grace_period")]
+    pub kill_grace_period: u64,
+    #[serde(skip)]
+    context_env: Vec<(String, String)>,
+    // Put in place of the {{secret:NAME}} references of the command and the env
+    #[serde(skip)]
+    secrets: Arc<HashMap<String, String>>,
+    // Written to stdin when the run is triggered through the hook url
+    #[serde(skip)]
+    payload: Option<Arc<HookPayload>>,
+    // The child process is owned by its waiter, we can only ask it to kill the process
     #[serde(skip)]
-    child_handle: Arc<Mutex<Option<Child>>>,
+    kill_tx: Option<oneshot::Sender<()>>,
 }
 
 impl CmdTask {
+    const SHELL_SECRETS: &'static str =
+        "a shell script can't reference secrets, pass them in 'env' and use them like \"$NAME\" in the script";
     pub fn new(id: Uuid, command: Box<String>) -> Self {
         let id_literal = id.to_string();
         let cmd_literal = command.to_string();
@@ -37,23 +70,237 @@ impl CmdTask {
         Self {
             id,
             command,
-            child_handle: Arc::default(),
+            argv: Vec::default(),
+            shell: None,
+            cwd: None,
+            env: HashMap::default(),
+            clear_env: false,
+            uid: None,
+            gid: None,
+            kill_grace_period: Self::default_kill_grace_period(),
+            context_env: Vec::default(),
+            secrets: Arc::default(),
+            payload: None,
+            kill_tx: None,
         }
     }
-    pub fn parse_cmd(id: &uuid::Uuid, command: &str) -> Result<(String, Vec<String>), TaskError> {
-        let mut s = command.split(" ");
-        let err = TaskError::invalid_cmd(*id, command.to_string());
-        let prog = match s.next() {
-            Some(x) => x,
-            None => return Err(err),
+    fn default_kill_grace_period() -> u64 {
+        10
+    }
+    pub fn default_shell() -> Vec<String> {
+        vec![String::from("/bin/sh"), String::from("-c")]
+    }
+    // Splits the command into words like a shell would, without expanding anything
+    pub fn split_words(command: &str) -> Result<Vec<String>, &'static str> {
+        let mut words = vec![];
+        let mut word: Option<String> = None;
+        let mut chars = command.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                c if c.is_whitespace() => {
+                    if let Some(w) = word.take() {
+                        words.push(w);
+                    }
+                }
+                '\'' => {
+                    let w = word.get_or_insert_with(String::new);
+                    loop {
+                        match chars.next() {
+                            Some('\'') => break,
+                            Some(c) => w.push(c),
+                            None => return Err("single quote is never closed"),
+                        }
+                    }
+                }
+                '"' => {
+                    let w = word.get_or_insert_with(String::new);
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            // Only these can be escaped between double quotes
+                            Some('\\') => match chars.next() {
+                                Some(c @ '"') | Some(c @ '\\') | Some(c @ '$') | Some(c @ '`') => w.push(c),
+                                Some(c) => {
+                                    w.push('\\');
+                                    w.push(c);
+                                }
+                                None => return Err("double quote is never closed"),
+                            },
+                            Some(c) => w.push(c),
+                            None => return Err("double quote is never closed"),
+                        }
+                    }
+                }
+                '\\' => match chars.next() {
+                    Some(c) => word.get_or_insert_with(String::new).push(c),
+                    None => return Err("it ends with a backslash"),
+                },
+                c => word.get_or_insert_with(String::new).push(c),
+            }
+        }
+        if let Some(w) = word {
+            words.push(w);
+        }
+        return Ok(words);
+    }
+    // Program to run and its arguments, depending on the mode of the task
+    pub fn get_program(&self) -> Result<(String, Vec<String>), TaskError> {
+        let invalid = |reason| TaskError::invalid_cmd(self.id, self.command.to_string(), reason);
+        let mut words = if !self.argv.is_empty() {
+            self.argv.clone()
+        } else if let Some(shell) = &self.shell {
+            if self.command.trim().is_empty() {
+                return Err(invalid("there's nothing to run"));
+            }
+            // Pasted into a script, a value with quotes in it would run as code
+            if !SecretModel::references(&self.command).is_empty() {
+                return Err(invalid(Self::SHELL_SECRETS));
+            }
+            let mut words = shell.clone();
+            words.push(self.command.to_string());
+            words
+        } else {
+            Self::split_words(&self.command).map_err(invalid)?
         };
-        let a: Vec<&str> = s.collect();
-        let b: Vec<String> = a.into_iter().map(|x| return x.to_string()).collect();
-        return Ok((prog.to_string(), b));
+        if words.is_empty() || words[0].is_empty() {
+            return Err(invalid("there's nothing to run"));
+        }
+        let program = words.remove(0);
+        return Ok((program, words));
+    }
+    // Parent is the environment of StewardX, it's passed in so the tests don't have to change the real one
+    fn set_environment<I>(&self, cmd: &mut tokio::process::Command, parent: I)
+    where
+        I: IntoIterator<Item = (OsString, OsString)>,
+    {
+        cmd.env_clear();
+        if !self.clear_env {
+            // Database credentials and the like aren't any job's business
+            cmd.envs(parent.into_iter().filter(|(key, _)| !key.to_string_lossy().starts_with("STEWARDX_")));
+        }
+        for (key, value) in &self.env {
+            match value {
+                Some(value) => cmd.env(key, SecretModel::resolve(value, &self.secrets)),
+                None => cmd.env_remove(key),
+            };
+        }
+        // Last, so the task can rely on them
+        cmd.envs(self.context_env.iter().cloned());
+    }
+    fn validate_env(env: &HashMap<String, Option<String>>) -> Result<(), anyhow::Error> {
+        for (key, value) in env {
+            if key.is_empty() || key.contains('=') || key.contains('\0') {
+                return Err(anyhow::anyhow!("Invalid environment variable name: '{}'", key));
+            }
+            if value.as_ref().map_or(false, |v| v.contains('\0')) {
+                return Err(anyhow::anyhow!("Value of environment variable '{}' can't contain a NUL byte", key));
+            }
+        }
+        Ok(())
+    }
+    fn lines_of<R>(handle: Option<R>, stream: OutputStream) -> BoxedStream
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+    {
+        match handle {
+            Some(handle) => {
+                // Read as bytes, tools like compilers and curl don't always write valid UTF-8
+                let lines = futures::stream::unfold(Some(BufReader::new(handle)), move |reader| async move {
+                    let mut reader = reader?;
+                    let mut buf = vec![];
+                    match reader.read_until(b'\n', &mut buf).await {
+                        Ok(0) => None,
+                        Ok(_) => {
+                            if buf.ends_with(b"\n") {
+                                buf.pop();
+                                if buf.ends_with(b"\r") {
+                                    buf.pop();
+                                }
+                            }
+                            let line = OutputLine::new(stream, String::from_utf8_lossy(&buf).into_owned());
+                            Some((line, Some(reader)))
+                        }
+                        Err(e) => {
+                            let line = OutputLine::system(format!("Couldn't read {}: {}", stream.as_str(), e));
+                            Some((line, None))
+                        }
+                    }
+                });
+                Box::new(Box::pin(lines))
+            }
+            None => {
+                error!("Couldn't get {} handle of cmd task.", stream.as_str());
+                Box::new(tokio_stream::empty())
+            }
+        }
     }
     pub fn get_task_type() -> String {
         String::from("CmdTask")
     }
+    // Asks the whole process group to stop, kills it if it doesn't in time.
+    // Returns the exit status of the child and the last signal that was sent.
+    async fn terminate(
+        child: &mut tokio::process::Child,
+        pid: u32,
+        grace_period: u64,
+    ) -> (std::io::Result<std::process::ExitStatus>, i32) {
+        // Child is the leader of its group, negative pid means the group
+        let group = -(pid as libc::pid_t);
+        unsafe { libc::kill(group, libc::SIGTERM) };
+        let deadline = tokio::time::Instant::now() + tokio::time::Duration::from_secs(grace_period);
+        let status = match tokio::time::timeout_at(deadline, child.wait()).await {
+            Ok(status) => status,
+            Err(_) => {
+                unsafe { libc::kill(group, libc::SIGKILL) };
+                return (child.wait().await, libc::SIGKILL);
+            }
+        };
+        // Rest of the group gets what's left of the grace period
+        while unsafe { libc::kill(group, 0) } == 0 {
+            if tokio::time::Instant::now() >= deadline {
+                unsafe { libc::kill(group, libc::SIGKILL) };
+                return (status, libc::SIGKILL);
+            }
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+        return (status, libc::SIGTERM);
+    }
+    // Kills a process left behind by a previous StewardX, false if it's already gone.
+    // A process that started after the run did is someone else's, the pid got reused.
+    pub fn kill_orphan(pid: u32, started_before: NaiveDateTime) -> bool {
+        let pid = pid as libc::pid_t;
+        // Signal 0 only checks if the process is there
+        if unsafe { libc::kill(pid, 0) } != 0 {
+            return false;
+        }
+        if let Some(started_at) = Self::process_started_at(pid) {
+            // Boot time is in seconds, so a second of slack
+            if started_at > started_before + chrono::Duration::seconds(1) {
+                return false;
+            }
+        }
+        // Runs started before the process groups only have the process itself
+        return unsafe { libc::kill(-pid, libc::SIGKILL) == 0 || libc::kill(pid, libc::SIGKILL) == 0 };
+    }
+    // Only possible where there's /proc
+    fn process_started_at(pid: libc::pid_t) -> Option<NaiveDateTime> {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        // Name of the process may contain spaces, fields after it are safe to split
+        let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+        let start_ticks: i64 = fields.get(19)?.parse().ok()?;
+        let boot_time: i64 = std::fs::read_to_string("/proc/stat")
+            .ok()?
+            .lines()
+            .find_map(|l| l.strip_prefix("btime "))?
+            .trim()
+            .parse()
+            .ok()?;
+        let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) } as i64;
+        if ticks_per_second <= 0 {
+            return None;
+        }
+        Some(NaiveDateTime::from_timestamp(boot_time + start_ticks / ticks_per_second, 0))
+    }
 }
 
 impl ToString for CmdTask {
@@ -70,38 +317,108 @@ impl Executable for CmdTask {
             task_id = %self.id,
         )
     )]
-    async fn exec(&mut self) -> Result<BoxedStream, TaskError> {
-        let (prog, args) = CmdTask::parse_cmd(&self.id, &self.command).unwrap();
-        let mut cmd = tokio::process::Command::new(prog);
+    async fn exec(&mut self) -> Result<TaskExecution, TaskError> {
+        let (prog, args) = self.get_program()?;
+        // After the split, so a value with spaces stays a single argument
+        let mut cmd = tokio::process::Command::new(SecretModel::resolve(&prog, &self.secrets));
         for arg in args {
-            cmd.arg(arg);
+            cmd.arg(SecretModel::resolve(&arg, &self.secrets));
+        }
+        self.set_environment(&mut cmd, std::env::vars_os());
+        if let Some(cwd) = &self.cwd {
+            cmd.current_dir(cwd);
         }
+        if let Some(uid) = self.uid {
+            cmd.uid(uid);
+        }
+        if let Some(gid) = self.gid {
+            cmd.gid(gid);
+        }
+        match self.payload {
+            Some(_) => cmd.stdin(Stdio::piped()),
+            None => cmd.stdin(Stdio::null()),
+        };
         cmd.stdout(Stdio::piped());
-        let child = match cmd.spawn() {
+        cmd.stderr(Stdio::piped());
+        // Own process group, so an abort reaches everything the command started
+        unsafe {
+            cmd.pre_exec(|| {
+                if libc::setpgid(0, 0) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let mut child = match cmd.spawn() {
             Ok(c) => c,
             Err(e) => {
                 error!("Execution failed, error while trying to spawn the command: {}", e);
-                panic!("{}", e.to_string());
-                // return Err(TaskError::TaskExecution(self.get_id(), e.to_string()));
-            }
-        };
-        self.child_handle = Arc::new(Mutex::new(Some(child)));
-        let child = self.child_handle.clone();
-        let mut child = child.lock().await;
-        let child = child.as_mut().unwrap();
-        let child_out = &mut child.stdout;
-        let stdout = match child_out.take() {
-            Some(s) => s,
-            None => {
-                error!("Couldn't get stdout handle of cmd task.");
-                return Ok(Box::new(tokio_stream::empty()));
+                return Err(TaskError::spawn_error(self.id, e.to_string()));
             }
         };
-        let reader = BufReader::new(stdout);
-        let lines = reader.lines();
-        let stream = LinesStream::new(lines);
-        let stream = stream.map(|l| l.unwrap());
-        Ok(Box::new(stream))
+        let pid = child.id();
+        if let (Some(mut stdin), Some(payload)) = (child.stdin.take(), self.payload.clone()) {
+            tokio::spawn(async move {
+                // Commands that don't read all of it close the pipe early, that's fine
+                let _ = stdin.write_all(&payload.body).await;
+            });
+        }
+        let stdout = CmdTask::lines_of(child.stdout.take(), OutputStream::Stdout);
+        let stderr = CmdTask::lines_of(child.stderr.take(), OutputStream::Stderr);
+        // Lines of our own, about how the process was stopped
+        let (system_tx, system_rx) = mpsc::unbounded_channel();
+        let system = UnboundedReceiverStream::new(system_rx);
+        let stream: BoxedStream = Box::new(futures::stream::select(futures::stream::select(stdout, stderr), system));
+        let (kill_tx, kill_rx) = oneshot::channel::<()>();
+        let (completion_tx, completion_rx) = oneshot::channel();
+        let grace_period = self.kill_grace_period;
+        tokio::spawn(async move {
+            // If kill_tx is dropped without sending, the branch is disabled and we keep waiting
+            let status = tokio::select! {
+                status = child.wait() => Some(status),
+                Ok(_) = kill_rx => None,
+            };
+            let (status, signal) = match (status, pid) {
+                (Some(status), _) => (status, None),
+                (None, Some(pid)) => {
+                    let (status, signal) = CmdTask::terminate(&mut child, pid, grace_period).await;
+                    let line = match signal {
+                        libc::SIGKILL => format!("Process group didn't stop within {} seconds of SIGTERM, sent SIGKILL.", grace_period),
+                        _ => String::from("Sent SIGTERM to the process group, it stopped in time."),
+                    };
+                    let _ = system_tx.send(OutputLine::system(line));
+                    (status, Some(signal))
+                }
+                (None, None) => {
+                    if let Err(e) = child.kill().await {
+                        error!("Couldn't kill the process: {}", e);
+                    }
+                    (child.wait().await, Some(libc::SIGKILL))
+                }
+            };
+            drop(system_tx);
+            let status = match status {
+                Ok(s) => {
+                    let mut status = ExitStatus::from_process(s);
+                    // The signal that finally stopped an aborted run, even if the process exited on its own after it
+                    if signal.is_some() {
+                        status.signal = signal;
+                    }
+                    status
+                }
+                Err(e) => {
+                    error!("Couldn't get the exit status of the process: {}", e);
+                    ExitStatus::unknown()
+                }
+            };
+            let _ = completion_tx.send(status);
+        });
+        self.kill_tx = Some(kill_tx);
+        let execution = TaskExecution::new(stream, completion_rx);
+        match pid {
+            Some(pid) => Ok(execution.with_process(ExecutionProcess::Pid(pid))),
+            None => Ok(execution),
+        }
     }
 
     fn get_id(&self) -> Uuid {
@@ -111,6 +428,11 @@ impl Executable for CmdTask {
     fn get_type(&self) -> String {
         Self::get_task_type()
     }
+    fn set_context(&mut self, context: &ExecutionContext) {
+        self.context_env = context.to_env();
+        self.secrets = context.secrets.clone();
+        self.payload = context.payload.clone();
+    }
     #[instrument(
         name = "Aborting CmdTask",
         skip(self),
@@ -119,12 +441,11 @@ impl Executable for CmdTask {
         )
     )]
     async fn abort(&mut self) -> bool {
-        let handle = &mut self.child_handle.lock().await;
-        let handle = handle.as_mut().unwrap();
-        match handle.kill().await {
-            Ok(_) => return true,
-            Err(_) => return false,
-        };
+        match self.kill_tx.take() {
+            // Fails if the process has already exited
+            Some(kill_tx) => kill_tx.send(()).is_ok(),
+            None => false,
+        }
     }
 }
 
@@ -155,11 +476,84 @@ impl FromJson for CmdTask {
 
 impl GetSerdeFromProps for CmdTask {
     fn get_serde_from_props(id: Uuid, value: serde_json::Value) -> Result<String, anyhow::Error> {
-        let command = value["command"].as_str();
-        if command.is_none() {
-            return Err(Self::prop_not_found("command"));
+        let argv = &value["argv"];
+        let mut cmd_task = if !argv.is_null() {
+            if !value["shell"].is_null() {
+                return Err(anyhow::anyhow!("Property 'shell' can't be used with 'argv'"));
+            }
+            let argv = match serde_json::from_value::<Vec<String>>(argv.clone()) {
+                Ok(a) => a,
+                Err(_) => return Err(anyhow::anyhow!("Property 'argv' must be an array of strings")),
+            };
+            let mut cmd_task = crate::tasks::CmdTask::new(id, Box::new(argv.join(" ")));
+            cmd_task.argv = argv;
+            cmd_task
+        } else {
+            let command = match value["command"].as_str() {
+                Some(c) => c,
+                None => return Err(Self::prop_not_found("command")),
+            };
+            let mut cmd_task = crate::tasks::CmdTask::new(id, Box::new(command.to_string()));
+            cmd_task.shell = match &value["shell"] {
+                serde_json::Value::Null | serde_json::Value::Bool(false) => None,
+                serde_json::Value::Bool(true) => Some(Self::default_shell()),
+                serde_json::Value::String(shell) => Some(vec![shell.to_string(), String::from("-c")]),
+                shell => match serde_json::from_value::<Vec<String>>(shell.clone()) {
+                    Ok(shell) => Some(shell),
+                    Err(_) => {
+                        return Err(anyhow::anyhow!("Property 'shell' must be a boolean, a path or an array of strings"))
+                    }
+                },
+            };
+            if cmd_task.shell.is_some() && !SecretModel::references(command).is_empty() {
+                return Err(anyhow::anyhow!("Property 'command' is invalid, {}", Self::SHELL_SECRETS));
+            }
+            cmd_task
+        };
+        if let Some(cwd) = value["cwd"].as_str() {
+            if !std::path::Path::new(cwd).is_absolute() {
+                return Err(anyhow::anyhow!("Property 'cwd' must be an absolute path"));
+            }
+            cmd_task.cwd = Some(cwd.to_string());
+        } else if !value["cwd"].is_null() {
+            return Err(anyhow::anyhow!("Property 'cwd' must be a string"));
+        }
+        let env = &value["env"];
+        if !env.is_null() {
+            cmd_task.env = match serde_json::from_value::<HashMap<String, Option<String>>>(env.clone()) {
+                Ok(e) => e,
+                Err(_) => return Err(anyhow::anyhow!("Property 'env' must be an object of strings or nulls")),
+            };
+            Self::validate_env(&cmd_task.env)?;
+        }
+        let clear_env = &value["clear_env"];
+        if !clear_env.is_null() {
+            match clear_env.as_bool() {
+                Some(c) => cmd_task.clear_env = c,
+                None => return Err(anyhow::anyhow!("Property 'clear_env' must be a boolean")),
+            }
+        }
+        for (name, id) in [("uid", &mut cmd_task.uid), ("gid", &mut cmd_task.gid)] {
+            let prop = &value[name];
+            if prop.is_null() {
+                continue;
+            }
+            match prop.as_u64() {
+                Some(i) if i <= u32::MAX as u64 => *id = Some(i as u32),
+                _ => return Err(anyhow::anyhow!("Property '{}' must be a numeric id", name)),
+            }
+        }
+        // Rejected now, instead of when it's executed
+        if let Err(e) = cmd_task.get_program() {
+            return Err(anyhow::anyhow!(e.to_string()));
+        }
+        let grace_period = &value["kill_grace_period"];
+        if !grace_period.is_null() {
+            match grace_period.as_u64() {
+                Some(g) => cmd_task.kill_grace_period = g,
+                None => return Err(anyhow::anyhow!("Property 'kill_grace_period' must be a number of seconds")),
+            }
         }
-        let cmd_task = crate::tasks::CmdTask::new(id, Box::new(command.unwrap().to_string()));
         return Ok(cmd_task.to_string());
     }
 }
@@ -168,6 +562,7 @@ impl GetSerdeFromProps for CmdTask {
 mod tests {
     use super::*;
     use crate::tasks::CmdTask;
+    use futures::StreamExt;
 
     async fn create_long_task() -> CmdTask {
         let sleep_and_print_and_create_file_command = r#"
@@ -204,19 +599,191 @@ mod tests {
         let mut task = create_long_task().await;
         let result = task.exec().await;
         let expected_output = format!("Hey hey hey");
-        let mut output_stream = result.unwrap();
+        let mut output_stream = result.unwrap().output;
         let output = output_stream.next().await;
-        assert_eq!(output.unwrap(), expected_output);
+        let output = output.unwrap();
+        assert_eq!(output.line, expected_output);
+        assert_eq!(output.stream, OutputStream::Stdout);
         cleanup().await;
     }
     #[tokio::test]
+    async fn captures_stderr() {
+        let mut task = CmdTask::new(Uuid::new_v4(), Box::new("ls /stewardx-does-not-exist".into()));
+        let output: Vec<OutputLine> = task.exec().await.unwrap().output.collect().await;
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].stream, OutputStream::Stderr);
+    }
+    #[tokio::test]
+    async fn reads_invalid_utf8() {
+        let mut task = CmdTask::new(Uuid::new_v4(), Box::new(r"printf 'a\377b\nok\n' >&2".into()));
+        task.shell = Some(vec![String::from("/bin/sh"), String::from("-c")]);
+        let execution = task.exec().await.unwrap();
+        let output: Vec<OutputLine> = execution.output.collect().await;
+        let lines: Vec<&str> = output.iter().map(|o| o.line.as_str()).collect();
+        assert_eq!(lines, vec!["a\u{FFFD}b", "ok"]);
+        assert_eq!(output[0].stream, OutputStream::Stderr);
+        assert_eq!(execution.completion.await.unwrap().successful, true);
+    }
+    #[tokio::test]
+    async fn captures_exit_status() {
+        let mut task = CmdTask::new(Uuid::new_v4(), Box::new("/bin/false".into()));
+        let status = task.exec().await.unwrap().completion.await.unwrap();
+        assert_eq!(status.successful, false);
+        assert_eq!(status.exit_code, Some(1));
+        let mut task = CmdTask::new(Uuid::new_v4(), Box::new("/bin/true".into()));
+        let status = task.exec().await.unwrap().completion.await.unwrap();
+        assert_eq!(status.successful, true);
+        assert_eq!(status.exit_code, Some(0));
+    }
+    #[tokio::test]
     async fn can_abort() {
         let mut task = create_long_task().await;
         let mut e = task.exec().await.unwrap();
         let a = task.abort().await;
         assert_eq!(a, true);
-        let none_output = e.next().await;
-        assert_eq!(none_output, None);
+        // Only the line about how it was stopped
+        assert_eq!(e.output.next().await.unwrap().stream, OutputStream::System);
+        let none_output = e.output.next().await;
+        assert_eq!(none_output.is_none(), true);
+        let status = e.completion.await.unwrap();
+        assert_eq!(status.successful, false);
+        assert_eq!(status.signal, Some(libc::SIGTERM));
         cleanup().await;
     }
+    #[test]
+    fn splits_words() {
+        let words = CmdTask::split_words(r#"grep -e "a \"b\"" 'c d' e\ f"#).unwrap();
+        assert_eq!(words, vec!["grep", "-e", "a \"b\"", "c d", "e f"]);
+        assert_eq!(CmdTask::split_words("echo 'oops").is_err(), true);
+        let id = Uuid::new_v4();
+        let unclosed = serde_json::json!({ "command": "echo \"oops" });
+        let error = CmdTask::get_serde_from_props(id, unclosed).unwrap_err().to_string();
+        assert_eq!(error.contains("InvalidCmd"), true);
+        let empty = serde_json::json!({ "argv": [] });
+        assert_eq!(CmdTask::get_serde_from_props(id, empty).is_err(), true);
+    }
+    #[tokio::test]
+    async fn runs_in_shell() {
+        let props = serde_json::json!({ "command": "echo hey | tr a-z A-Z && echo \"$0\"", "shell": true });
+        let mut task = CmdTask::from_json(CmdTask::get_serde_from_props(Uuid::new_v4(), props).unwrap()).unwrap();
+        let output: Vec<String> = task.exec().await.unwrap().output.map(|o| o.line).collect().await;
+        assert_eq!(output, vec!["HEY", "/bin/sh"]);
+    }
+    #[tokio::test]
+    async fn sets_environment() {
+        let props = serde_json::json!({
+            "command": "echo \"$FOO:$HOME:$STEWARDX_TEST_SECRET:$(pwd)\" && echo \"$STEWARDX_TASK_NAME $STEWARDX_TRIGGER $STEWARDX_ATTEMPT\"",
+            "shell": true,
+            "cwd": "/tmp",
+            "env": { "FOO": "bar", "HOME": null, "STEWARDX_TRIGGER": "overridden" }
+        });
+        let serde_string = CmdTask::get_serde_from_props(Uuid::new_v4(), props).unwrap();
+        let mut task = CmdTask::from_json(serde_string.clone()).unwrap();
+        let model = crate::models::TaskModel::new(Some(task.id), "env test".into(), CmdTask::get_task_type(), serde_string, "Hook".into());
+        task.set_context(&ExecutionContext::new(&model, crate::now!(), crate::models::Trigger::Retry, 2));
+        let output: Vec<String> = task.exec().await.unwrap().output.map(|o| o.line).collect().await;
+        assert_eq!(output, vec!["bar:::/tmp", "env test retry 2"]);
+        // Variables of StewardX itself are left out
+        let parent = vec![("STEWARDX_TEST_SECRET", "secret"), ("PATH", "/usr/bin:/bin"), ("LANG", "C")]
+            .into_iter()
+            .map(|(k, v)| (OsString::from(k), OsString::from(v)));
+        let mut cmd = tokio::process::Command::new("env");
+        task.set_environment(&mut cmd, parent);
+        let env = String::from_utf8(cmd.output().await.unwrap().stdout).unwrap();
+        assert_eq!(env.contains("LANG=C"), true);
+        assert_eq!(env.contains("STEWARDX_TEST_SECRET"), false);
+        assert_eq!(env.contains("STEWARDX_TASK_NAME=env test"), true);
+        let id = Uuid::new_v4();
+        let relative = serde_json::json!({ "command": "pwd", "cwd": "tmp" });
+        assert_eq!(CmdTask::get_serde_from_props(id, relative).is_err(), true);
+        let bad_env = serde_json::json!({ "command": "env", "env": { "A=B": "c" } });
+        assert_eq!(CmdTask::get_serde_from_props(id, bad_env).is_err(), true);
+        let bad_uid = serde_json::json!({ "command": "id", "uid": "root" });
+        assert_eq!(CmdTask::get_serde_from_props(id, bad_uid).is_err(), true);
+    }
+    #[tokio::test]
+    async fn resolves_secrets() {
+        let mut secrets = HashMap::new();
+        secrets.insert(String::from("GREETING"), String::from("hello world"));
+        secrets.insert(String::from("TOKEN"), String::from("t0ken"));
+        let pwned = std::env::temp_dir().join(format!("pwned-{}", Uuid::new_v4()));
+        secrets.insert(String::from("QUOTED"), format!("'; touch {}; '", pwned.display()));
+        let mut output = vec![];
+        for props in vec![
+            serde_json::json!({ "command": "printf '%s|%s\\n' {{secret:GREETING}} {{secret:MISSING}}" }),
+            serde_json::json!({ "command": "printenv TOKEN", "env": { "TOKEN": "Bearer {{secret:TOKEN}}" } }),
+            serde_json::json!({ "command": "echo \"$QUOTED\"", "shell": true, "env": { "QUOTED": "{{secret:QUOTED}}" } }),
+        ] {
+            let serde_string = CmdTask::get_serde_from_props(Uuid::new_v4(), props).unwrap();
+            let mut task = CmdTask::from_json(serde_string.clone()).unwrap();
+            let model = crate::models::TaskModel::new(Some(task.id), "secrets".into(), CmdTask::get_task_type(), serde_string, "Hook".into());
+            let context = ExecutionContext::new(&model, crate::now!(), crate::models::Trigger::Manual, 1).with_secrets(secrets.clone());
+            task.set_context(&context);
+            output.extend(task.exec().await.unwrap().output.map(|o| o.line).collect::<Vec<String>>().await);
+            // Only resolved for the run, the stored task keeps the references
+            assert_eq!(task.to_string().contains("{{secret:"), true);
+        }
+        // A value with spaces stays a single argument
+        assert_eq!(output[..2], ["hello world|{{secret:MISSING}}", "Bearer t0ken"]);
+        // Shell scripts only get them through the environment, where quotes are just text
+        assert_eq!(output[2], format!("'; touch {}; '", pwned.display()));
+        assert_eq!(pwned.exists(), false);
+        let props = serde_json::json!({ "command": "echo '{{secret:QUOTED}}'", "shell": true });
+        assert_eq!(CmdTask::get_serde_from_props(Uuid::new_v4(), props).is_err(), true);
+        let mut task = CmdTask::new(Uuid::new_v4(), Box::new("echo '{{secret:QUOTED}}'".into()));
+        task.shell = Some(CmdTask::default_shell());
+        assert_eq!(task.get_program().is_err(), true);
+    }
+    #[tokio::test]
+    async fn reads_payload_from_stdin() {
+        let mut task = CmdTask::new(Uuid::new_v4(), Box::new("cat".into()));
+        let model = crate::models::TaskModel::new(Some(task.id), "hook".into(), CmdTask::get_task_type(), task.to_string(), "Hook".into());
+        let payload = HookPayload {
+            body: b"first\nsecond".to_vec(),
+            ..HookPayload::default()
+        };
+        let context = ExecutionContext::new(&model, crate::now!(), crate::models::Trigger::Hook, 1)
+            .with_payload(Some(Arc::new(payload)));
+        task.set_context(&context);
+        let output: Vec<String> = task.exec().await.unwrap().output.map(|o| o.line).collect().await;
+        assert_eq!(output, vec!["first", "second"]);
+    }
+    async fn create_script_task(file_name: &str, script: &str) -> CmdTask {
+        tokio::fs::write(file_name, script).await.unwrap();
+        CmdTask::new(Uuid::new_v4(), Box::new(format!("/bin/bash {}", file_name)))
+    }
+    #[tokio::test]
+    async fn aborts_process_group() {
+        // Grandchild holds stdout, the stream only ends if it's stopped too
+        let mut task = create_script_task("temp_group_script.sh", "sleep 5 &\necho started\nwait\n").await;
+        let mut e = task.exec().await.unwrap();
+        assert_eq!(e.output.next().await.unwrap().line, "started");
+        assert_eq!(task.abort().await, true);
+        let rest = tokio::time::timeout(std::time::Duration::from_secs(2), e.output.collect::<Vec<OutputLine>>()).await;
+        assert_eq!(rest.unwrap()[0].stream, OutputStream::System);
+        assert_eq!(e.completion.await.unwrap().signal, Some(libc::SIGTERM));
+        let _ = tokio::fs::remove_file("temp_group_script.sh").await;
+    }
+    #[tokio::test]
+    async fn kills_after_grace_period() {
+        let mut task = create_script_task("temp_trap_script.sh", "trap '' TERM\necho started\nsleep 5\n").await;
+        task.kill_grace_period = 1;
+        let mut e = task.exec().await.unwrap();
+        assert_eq!(e.output.next().await.unwrap().line, "started");
+        assert_eq!(task.abort().await, true);
+        let status = e.completion.await.unwrap();
+        assert_eq!(status.signal, Some(libc::SIGKILL));
+        let _ = tokio::fs::remove_file("temp_trap_script.sh").await;
+    }
+    #[test]
+    fn kills_orphans() {
+        let mut child = std::process::Command::new("sleep").arg("5").spawn().unwrap();
+        let pid = child.id();
+        let long_ago = NaiveDateTime::from_timestamp(0, 0);
+        // Started after the run, so it can't be its process
+        assert_eq!(CmdTask::kill_orphan(pid, long_ago), false);
+        assert_eq!(CmdTask::kill_orphan(pid, crate::now!()), true);
+        let status = child.wait().unwrap();
+        assert_eq!(std::os::unix::process::ExitStatusExt::signal(&status), Some(9));
+    }
 }
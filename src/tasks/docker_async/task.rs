@@ -1,51 +1,136 @@
-use futures::StreamExt;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures::{Stream, StreamExt};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use shiplift::{tty::TtyChunk, ContainerOptions, PullOptions};
+use shiplift::{tty::TtyChunk, ContainerOptions, LogsOptions, PullOptions};
 use tempfile::{Builder, TempDir};
+use tracing::error;
 use uuid::Uuid;
 
-use crate::models::TaskError;
+use crate::models::{
+    ExecutionContext, ExecutionProcess, ExitStatus, HookPayload, OutputLine, OutputStream, SecretModel, TaskError,
+};
 use crate::{
-    traits::{BoxedStream, Executable, FromJson, GetSerdeFromProps},
+    traits::{Executable, FromJson, GetSerdeFromProps, TaskExecution},
+    types::BoxedStream,
     GLOBAL_DOCKER,
 };
 
 use super::DockerImageType;
 
+type ChunkStream = Pin<Box<dyn Stream<Item = shiplift::Result<TtyChunk>> + Send>>;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DockerTask {
     pub id: Uuid,
     pub image: DockerImageType,
     pub env: Vec<String>,
     container_id: String,
+    // Added after env, as KEY=value
+    #[serde(skip)]
+    context_env: Vec<String>,
+    // Put in place of the {{secret:NAME}} references of the env
+    #[serde(skip)]
+    secrets: Arc<HashMap<String, String>>,
+    // Mounted as a file when the run is triggered through the hook url
+    #[serde(skip)]
+    payload: Option<Arc<HookPayload>>,
+    // Holds the payload file, it's removed when the task is dropped
+    #[serde(skip)]
+    payload_dir: Option<TempDir>,
 }
 
+// Where the hook payload is in the container
+const PAYLOAD_PATH: &str = "/stewardx/payload";
+
 impl DockerTask {
+    // Docker cuts the output wherever it likes, a line is only let out once its newline arrives
+    fn lines_of(chunks: ChunkStream) -> BoxedStream {
+        let lines = futures::stream::unfold(Some((chunks, vec![], vec![])), |state| async move {
+            let (mut chunks, mut stdout, mut stderr) = state?;
+            let lines = match chunks.next().await {
+                Some(Ok(TtyChunk::StdOut(bytes))) => Self::complete_lines(&mut stdout, &bytes, OutputStream::Stdout),
+                Some(Ok(TtyChunk::StdErr(bytes))) => Self::complete_lines(&mut stderr, &bytes, OutputStream::Stderr),
+                Some(Ok(TtyChunk::StdIn(_))) => unreachable!(),
+                Some(Err(e)) => vec![OutputLine::system(e.to_string())],
+                None => {
+                    // Whatever is left didn't end with a newline
+                    let rest = vec![(stdout, OutputStream::Stdout), (stderr, OutputStream::Stderr)]
+                        .into_iter()
+                        .filter(|(buf, _)| !buf.is_empty())
+                        .map(|(buf, stream)| OutputLine::new(stream, String::from_utf8_lossy(&buf).into_owned()))
+                        .collect();
+                    return Some((rest, None));
+                }
+            };
+            return Some((lines, Some((chunks, stdout, stderr))));
+        });
+        return Box::new(Box::pin(lines.flat_map(futures::stream::iter)));
+    }
+    // Takes the lines the carried bytes and the chunk finish, keeps the rest for the next chunk
+    fn complete_lines(carry: &mut Vec<u8>, bytes: &[u8], stream: OutputStream) -> Vec<OutputLine> {
+        carry.extend_from_slice(bytes);
+        let end = match carry.iter().rposition(|b| *b == b'\n') {
+            Some(i) => i + 1,
+            None => return vec![],
+        };
+        let complete: Vec<u8> = carry.drain(..end).collect();
+        let mut lines: Vec<&[u8]> = complete.split(|b| *b == b'\n').collect();
+        // Nothing comes after the last newline
+        lines.pop();
+        return lines
+            .into_iter()
+            .map(|l| OutputLine::new(stream, String::from_utf8_lossy(l.strip_suffix(b"\r").unwrap_or(l)).into_owned()))
+            .collect();
+    }
     pub fn new(id: Uuid, image: DockerImageType, env: Vec<String>) -> Self {
         Self {
             id,
             image,
             env,
             container_id: String::default(),
+            context_env: Vec::default(),
+            secrets: Arc::default(),
+            payload: None,
+            payload_dir: None,
         }
     }
     pub fn create_temp_dir(named: String) -> Result<TempDir, anyhow::Error> {
         let tmp_dir = Builder::new().prefix(&named).tempdir()?;
         Ok(tmp_dir)
     }
-}
-
-impl ToString for DockerTask {
-    fn to_string(&self) -> String {
-        // println!("{:?}", self);
-        serde_json::to_string(&self).unwrap()
+    // Used after a restart, exec follows the container instead of creating a new one
+    pub fn reattach(&mut self, container_id: String) {
+        self.container_id = container_id;
     }
-}
-
-#[async_trait]
-impl Executable for DockerTask {
-    async fn exec(&mut self) -> Result<BoxedStream, TaskError> {
+    // Exit status of a container left behind by a previous StewardX, None if it's still running
+    pub async fn orphan_status(container_id: &str) -> Result<Option<ExitStatus>, anyhow::Error> {
+        let details = GLOBAL_DOCKER.containers().get(container_id).inspect().await?;
+        if details.state.running {
+            return Ok(None);
+        }
+        Ok(Some(ExitStatus::from_container(details.state.exit_code)))
+    }
+    async fn write_payload(&mut self) -> Result<Option<String>, TaskError> {
+        let payload = match &self.payload {
+            Some(p) => p.clone(),
+            None => return Ok(None),
+        };
+        let payload_dir = match Self::create_temp_dir(format!("{}-payload", self.id)) {
+            Ok(d) => d,
+            Err(e) => return Err(TaskError::generic(self.id, e.to_string())),
+        };
+        let path = payload_dir.path().join("payload");
+        if let Err(e) = tokio::fs::write(&path, &payload.body).await {
+            return Err(TaskError::generic(self.id, format!("Couldn't write the hook payload: {}", e)));
+        }
+        self.payload_dir = Some(payload_dir);
+        return Ok(Some(format!("{}:{}:ro", path.to_string_lossy(), PAYLOAD_PATH)));
+    }
+    async fn create_container(&mut self) -> Result<String, TaskError> {
         let docker = &GLOBAL_DOCKER;
         // let mut stream = docker.images().build(BuildOptions::builder())
         let image = match &self.image {
@@ -96,34 +181,70 @@ impl Executable for DockerTask {
             }
             .to_string(),
         };
-        let options = ContainerOptions::builder(&image).env(&self.env).build();
+        let mut env: Vec<String> = self
+            .env
+            .iter()
+            .map(|e| SecretModel::resolve(e, &self.secrets))
+            .chain(self.context_env.iter().cloned())
+            .collect();
+        let mut builder = ContainerOptions::builder(&image);
+        let volume = self.write_payload().await?;
+        if let Some(volume) = &volume {
+            env.push(format!("STEWARDX_HOOK_PAYLOAD_FILE={}", PAYLOAD_PATH));
+            builder.volumes(vec![volume.as_str()]);
+        }
+        let options = builder.env(env).build();
         let info = docker.containers().create(&options).await.unwrap();
-        let id = info.id;
+        return Ok(info.id);
+    }
+}
+
+impl ToString for DockerTask {
+    fn to_string(&self) -> String {
+        // println!("{:?}", self);
+        serde_json::to_string(&self).unwrap()
+    }
+}
+
+#[async_trait]
+impl Executable for DockerTask {
+    async fn exec(&mut self) -> Result<TaskExecution, TaskError> {
+        let docker = &GLOBAL_DOCKER;
+        let reattached = !self.container_id.is_empty();
+        if !reattached {
+            self.container_id = self.create_container().await?;
+        }
+        let id = self.container_id.clone();
         // println!("Container built, id: {}", &id);
-        self.container_id = id.clone();
         let container = docker.containers().get(&id);
-        if let Err(a) = container.start().await {
-            return Err(TaskError::generic(self.id, a.to_string()));
-        }
+        let reader: ChunkStream = if reattached {
+            // Logs have the output that was written while nobody was attached
+            let options = LogsOptions::builder().follow(true).stdout(true).stderr(true).build();
+            Box::pin(container.logs(&options))
+        } else {
+            if let Err(a) = container.start().await {
+                return Err(TaskError::generic(self.id, a.to_string()));
+            }
+            let tty_multiplexer = container.attach().await.unwrap();
+            let (reader, _writer) = tty_multiplexer.split();
+            Box::pin(reader)
+        };
+        let stream = Self::lines_of(reader);
 
-        let tty_multiplexer = container.attach().await.unwrap();
-        let (reader, _writer) = tty_multiplexer.split();
-        let stream = reader.map(|result| {
-            println!("{:?}", result);
-            match result {
-                Ok(chunk) => {
-                    let output = match chunk {
-                        TtyChunk::StdOut(bytes) => std::str::from_utf8(&bytes).unwrap().to_string(),
-                        TtyChunk::StdErr(bytes) => std::str::from_utf8(&bytes).unwrap().to_string(),
-                        TtyChunk::StdIn(_) => unreachable!(),
-                    };
-                    return output;
+        let (completion_tx, completion_rx) = tokio::sync::oneshot::channel();
+        let process = ExecutionProcess::Container(id.clone());
+        tokio::spawn(async move {
+            let status = match docker.containers().get(&id).wait().await {
+                Ok(exit) => ExitStatus::from_container(exit.status_code),
+                Err(e) => {
+                    error!("Couldn't wait for container {}: {}", id, e);
+                    ExitStatus::unknown()
                 }
-                Err(e) => return e.to_string(),
-            }
+            };
+            let _ = completion_tx.send(status);
         });
 
-        return Ok(Box::new(stream));
+        return Ok(TaskExecution::new(stream, completion_rx).with_process(process));
     }
 
     async fn abort(&mut self) -> bool {
@@ -143,6 +264,15 @@ impl Executable for DockerTask {
     fn get_type(&self) -> String {
         String::from("DockerTask")
     }
+    fn set_context(&mut self, context: &ExecutionContext) {
+        self.context_env = context
+            .to_env()
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        self.secrets = context.secrets.clone();
+        self.payload = context.payload.clone();
+    }
 }
 
 impl FromJson for DockerTask {
@@ -221,3 +351,28 @@ impl GetSerdeFromProps for DockerTask {
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn joins_lines_across_chunks() {
+        let chunks: Vec<shiplift::Result<TtyChunk>> = vec![
+            Ok(TtyChunk::StdOut(b"pass: hun".to_vec())),
+            Ok(TtyChunk::StdErr(b"warn\r\n".to_vec())),
+            Ok(TtyChunk::StdOut(b"ter2\nnext\nno newline".to_vec())),
+        ];
+        let lines = DockerTask::lines_of(Box::pin(futures::stream::iter(chunks)));
+        let lines = lines.map(|l| (l.stream, l.line)).collect::<Vec<_>>().await;
+        assert_eq!(
+            lines,
+            vec![
+                (OutputStream::Stderr, String::from("warn")),
+                (OutputStream::Stdout, String::from("pass: hunter2")),
+                (OutputStream::Stdout, String::from("next")),
+                (OutputStream::Stdout, String::from("no newline")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,4 @@
+mod task;
+mod tls_options;
+pub use task::HttpTask;
+pub use tls_options::HttpTlsOptions;
@@ -0,0 +1,363 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{
+    header::{HeaderName, HeaderValue},
+    Certificate, Client, Identity, Method, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::oneshot, task::JoinHandle};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tracing::{error, instrument};
+use uuid::Uuid;
+
+use crate::{
+    models::{ExitStatus, OutputLine, TaskError},
+    traits::{Executable, FromJson, GetSerdeFromProps, TaskExecution},
+};
+
+use super::HttpTlsOptions;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HttpTask {
+    pub id: Uuid,
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    // In seconds
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    #[serde(default)]
+    pub tls: HttpTlsOptions,
+    // When empty, every 2xx status is considered successful
+    #[serde(default)]
+    pub success_statuses: Vec<u16>,
+    #[serde(skip)]
+    request_handle: Option<JoinHandle<()>>,
+}
+
+impl HttpTask {
+    pub fn new(id: Uuid, method: String, url: String) -> Self {
+        tracing::info_span!(
+            "Creating a new HttpTask",
+            id = %id,
+            method = %method,
+            url = %url
+        );
+        Self {
+            id,
+            method,
+            url,
+            headers: HashMap::default(),
+            body: None,
+            timeout: None,
+            tls: HttpTlsOptions::default(),
+            success_statuses: Vec::default(),
+            request_handle: None,
+        }
+    }
+    pub fn get_task_type() -> String {
+        String::from("HttpTask")
+    }
+    pub fn is_successful_status(success_statuses: &[u16], status: StatusCode) -> bool {
+        if success_statuses.is_empty() {
+            return status.is_success();
+        }
+        return success_statuses.contains(&status.as_u16());
+    }
+    async fn build_client(&self) -> Result<Client, TaskError> {
+        let mut builder = Client::builder().danger_accept_invalid_certs(self.tls.accept_invalid_certs);
+        if let Some(path) = &self.tls.ca_certificate {
+            let pem = match tokio::fs::read(path).await {
+                Ok(p) => p,
+                Err(e) => {
+                    return Err(TaskError::generic(self.id, format!("Couldn't read CA certificate {}: {}", path, e)));
+                }
+            };
+            let certificate = match Certificate::from_pem(&pem) {
+                Ok(c) => c,
+                Err(e) => return Err(TaskError::generic(self.id, e.to_string())),
+            };
+            builder = builder.add_root_certificate(certificate);
+        }
+        if let Some(path) = &self.tls.client_identity {
+            let pem = match tokio::fs::read(path).await {
+                Ok(p) => p,
+                Err(e) => {
+                    return Err(TaskError::generic(self.id, format!("Couldn't read client identity {}: {}", path, e)));
+                }
+            };
+            let identity = match Identity::from_pem(&pem) {
+                Ok(i) => i,
+                Err(e) => return Err(TaskError::generic(self.id, e.to_string())),
+            };
+            builder = builder.identity(identity);
+        }
+        match builder.build() {
+            Ok(client) => Ok(client),
+            Err(e) => Err(TaskError::generic(self.id, e.to_string())),
+        }
+    }
+}
+
+impl ToString for HttpTask {
+    fn to_string(&self) -> String {
+        serde_json::to_string(&self).unwrap()
+    }
+}
+
+#[async_trait]
+impl Executable for HttpTask {
+    #[instrument(
+        name = "Executing HttpTask",
+        skip(self),
+        fields(
+            task_id = %self.id,
+        )
+    )]
+    async fn exec(&mut self) -> Result<TaskExecution, TaskError> {
+        let client = self.build_client().await?;
+        let method = match Method::from_bytes(self.method.as_bytes()) {
+            Ok(m) => m,
+            Err(e) => return Err(TaskError::generic(self.id, e.to_string())),
+        };
+        let mut request = client.request(method, &self.url);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        if let Some(body) = &self.body {
+            request = request.body(body.clone());
+        }
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(Duration::from_secs(timeout));
+        }
+        let success_statuses = self.success_statuses.clone();
+        let (output_tx, output_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (completion_tx, completion_rx) = oneshot::channel();
+        // Request runs in the background so it can be aborted like any other task
+        let handle = tokio::spawn(async move {
+            let response = match request.send().await {
+                Ok(r) => r,
+                Err(e) => {
+                    error!("Execution failed, error while sending the request: {}", e);
+                    let _ = output_tx.send(OutputLine::system(e.to_string()));
+                    let _ = completion_tx.send(ExitStatus::unknown());
+                    return;
+                }
+            };
+            let status = response.status();
+            // Status and headers go to the report, only the body is output
+            let mut headers: BTreeMap<String, String> = BTreeMap::new();
+            for (name, value) in response.headers() {
+                let value = value.to_str().unwrap_or("<binary>");
+                headers
+                    .entry(name.to_string())
+                    .and_modify(|v| {
+                        v.push_str(", ");
+                        v.push_str(value);
+                    })
+                    .or_insert_with(|| value.to_string());
+            }
+            let successful = match response.text().await {
+                Ok(body) => {
+                    for line in body.lines() {
+                        let _ = output_tx.send(OutputLine::stdout(line.to_string()));
+                    }
+                    Self::is_successful_status(&success_statuses, status)
+                }
+                Err(e) => {
+                    let _ = output_tx.send(OutputLine::system(e.to_string()));
+                    false
+                }
+            };
+            let exit_status = ExitStatus::new(successful).with_http(Some(status.as_u16() as i32), Some(headers));
+            let _ = completion_tx.send(exit_status);
+        });
+        self.request_handle = Some(handle);
+        Ok(TaskExecution::new(Box::new(UnboundedReceiverStream::new(output_rx)), completion_rx))
+    }
+
+    fn get_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn get_type(&self) -> String {
+        Self::get_task_type()
+    }
+
+    async fn abort(&mut self) -> bool {
+        match self.request_handle.take() {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl FromJson for HttpTask {
+    fn from_json(json: String) -> Result<Self, TaskError> {
+        match serde_json::from_str::<Self>(&json) {
+            Ok(task) => {
+                return Ok(task);
+            }
+            Err(_) => {
+                // Serde string might be double encoded
+                if let Ok(serde_json::Value::String(inner)) = serde_json::from_str::<serde_json::Value>(&json) {
+                    if let Ok(task) = serde_json::from_str::<Self>(&inner) {
+                        return Ok(task);
+                    }
+                }
+            }
+        }
+        return Err(TaskError::malformed_serde(uuid::Uuid::default(), json));
+    }
+}
+
+impl GetSerdeFromProps for HttpTask {
+    fn get_serde_from_props(id: Uuid, value: serde_json::Value) -> Result<String, anyhow::Error> {
+        let url = match value["url"].as_str() {
+            Some(u) => u,
+            None => return Err(Self::prop_not_found("url")),
+        };
+        if let Err(e) = url::Url::parse(url) {
+            return Err(anyhow::anyhow!("Invalid url '{}': {}", url, e));
+        }
+        let method = value["method"].as_str().unwrap_or("GET").to_uppercase();
+        if Method::from_bytes(method.as_bytes()).is_err() {
+            return Err(anyhow::anyhow!("Invalid HTTP method: {}", method));
+        }
+        let mut task = Self::new(id, method, url.to_string());
+        if let Some(headers) = value["headers"].as_object() {
+            for (name, header_value) in headers {
+                let header_value = match header_value.as_str() {
+                    Some(v) => v,
+                    None => return Err(anyhow::anyhow!("Value of header '{}' must be a string", name)),
+                };
+                if HeaderName::from_bytes(name.as_bytes()).is_err() {
+                    return Err(anyhow::anyhow!("Invalid header name: {}", name));
+                }
+                if HeaderValue::from_str(header_value).is_err() {
+                    return Err(anyhow::anyhow!("Invalid value for header '{}'", name));
+                }
+                task.headers.insert(name.to_string(), header_value.to_string());
+            }
+        }
+        task.body = match &value["body"] {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(s) => Some(s.to_string()),
+            // JSON bodies are sent as they are
+            other => Some(other.to_string()),
+        };
+        let timeout = &value["timeout"];
+        if !timeout.is_null() {
+            match timeout.as_u64() {
+                Some(t) => task.timeout = Some(t),
+                None => return Err(anyhow::anyhow!("Property 'timeout' must be a positive number of seconds")),
+            }
+        }
+        let tls = &value["tls"];
+        if !tls.is_null() {
+            task.tls = match serde_json::from_value::<HttpTlsOptions>(tls.clone()) {
+                Ok(t) => t,
+                Err(e) => return Err(anyhow::anyhow!("Malformed 'tls' property: {}", e)),
+            };
+        }
+        if let Some(statuses) = value["success_statuses"].as_array() {
+            for status in statuses {
+                match status.as_u64() {
+                    Some(s) if (100..600).contains(&s) => task.success_statuses.push(s as u16),
+                    _ => return Err(anyhow::anyhow!("Invalid status code in 'success_statuses': {}", status)),
+                }
+            }
+        }
+        return Ok(task.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Body, Response, Server,
+    };
+    use std::{convert::Infallible, net::SocketAddr};
+
+    async fn serve(status: u16) -> SocketAddr {
+        let make_svc = make_service_fn(move |_conn| async move {
+            Ok::<_, Infallible>(service_fn(move |_req| async move {
+                Ok::<_, Infallible>(
+                    Response::builder()
+                        .status(status)
+                        .header("x-stewardx", "yes")
+                        .body(Body::from("Hey hey hey"))
+                        .unwrap(),
+                )
+            }))
+        });
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_svc);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        addr
+    }
+    fn create_task(addr: SocketAddr) -> HttpTask {
+        HttpTask::new(Uuid::new_v4(), "GET".into(), format!("http://{}/health", addr))
+    }
+    #[tokio::test]
+    async fn can_execute() {
+        let addr = serve(200).await;
+        let mut task = create_task(addr);
+        let execution = task.exec().await.unwrap();
+        let output: Vec<String> = execution.output.map(|o| o.line).collect().await;
+        assert_eq!(output, vec![String::from("Hey hey hey")]);
+        let status = execution.completion.await.unwrap();
+        assert_eq!(status.successful, true);
+        assert_eq!(status.http_status, Some(200));
+        assert_eq!(status.http_headers.unwrap().get("x-stewardx").unwrap(), "yes");
+    }
+    #[tokio::test]
+    async fn unexpected_status_fails() {
+        let addr = serve(503).await;
+        let mut task = create_task(addr);
+        let execution = task.exec().await.unwrap();
+        let output: Vec<String> = execution.output.map(|o| o.line).collect().await;
+        assert_eq!(output.last().unwrap(), "Hey hey hey");
+        let status = execution.completion.await.unwrap();
+        assert_eq!(status.successful, false);
+        assert_eq!(status.http_status, Some(503));
+    }
+    #[tokio::test]
+    async fn custom_success_statuses() {
+        let addr = serve(404).await;
+        let mut task = create_task(addr);
+        task.success_statuses = vec![404];
+        let status = task.exec().await.unwrap().completion.await.unwrap();
+        assert_eq!(status.successful, true);
+    }
+    #[test]
+    fn validates_props() {
+        let id = Uuid::new_v4();
+        let missing_url = serde_json::json!({ "method": "GET" });
+        assert_eq!(HttpTask::get_serde_from_props(id, missing_url).is_err(), true);
+        let bad_status = serde_json::json!({ "url": "http://localhost", "success_statuses": [42] });
+        assert_eq!(HttpTask::get_serde_from_props(id, bad_status).is_err(), true);
+        let props = serde_json::json!({
+            "url": "http://localhost/hook",
+            "method": "post",
+            "headers": { "Content-Type": "application/json" },
+            "body": { "hello": "stewardx" },
+            "timeout": 5
+        });
+        let task = HttpTask::from_json(HttpTask::get_serde_from_props(id, props).unwrap()).unwrap();
+        assert_eq!(task.method, "POST");
+        assert_eq!(task.body.unwrap(), r#"{"hello":"stewardx"}"#);
+        assert_eq!(task.timeout, Some(5));
+    }
+}
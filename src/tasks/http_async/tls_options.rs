@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpTlsOptions {
+    /// Skips certificate verification, useful for self-signed health endpoints
+    pub accept_invalid_certs: bool,
+    /// Path of a PEM encoded certificate to trust besides the bundled roots
+    pub ca_certificate: Option<String>,
+    /// Path of a PEM file containing the client certificate and its private key
+    pub client_identity: Option<String>,
+}
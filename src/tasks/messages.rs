@@ -2,17 +2,20 @@ use uuid::Uuid;
 
 use crate::{
     models::{ExecutionReport, TaskError},
-    traits::BoxedStream,
-    types::{OneShotMessageResponse, OutputSender},
+    types::{OneShotMessageResponse, OutputSender, TaskExecution},
 };
 
+use super::Redactor;
+
 pub enum TaskWatcherMessage {
     // TASK_EXECUTION_SUCCESSFUL {
     //     output_stream: BoxedStream,
     // },
     WatchExecution {
         task_id: Uuid,
-        exec_process: Result<BoxedStream, TaskError>,
+        execution_id: Uuid,
+        exec_process: Result<TaskExecution, TaskError>,
+        redactor: Redactor,
         output_resp: OutputSender,
         resp: OneShotMessageResponse<ExecutionReport>,
     },
@@ -1,6 +1,8 @@
 mod errors;
 mod frequency;
 mod messages;
+mod overlap_policy;
+mod redactor;
 mod watcher;
 #[cfg(feature = "cmd")]
 mod cmd_async;
@@ -10,10 +12,16 @@ pub use cmd_async::CmdTask;
 mod docker_async;
 #[cfg(feature = "docker")]
 pub use docker_async::{DockerImageType, DockerTask};
+#[cfg(feature = "http")]
+mod http_async;
+#[cfg(feature = "http")]
+pub use http_async::HttpTask;
 
 // pub use errors::TaskError;
 pub use frequency::Frequency;
 pub use messages::TaskWatcherMessage;
+pub use overlap_policy::OverlapPolicy;
+pub use redactor::Redactor;
 pub use watcher::TaskWatcher;
 
 #[macro_export]
@@ -23,6 +31,8 @@ macro_rules! ModelToTask {
         use crate::tasks::CmdTask;
         #[cfg(feature = "docker")]
         use crate::tasks::DockerTask;
+        #[cfg(feature = "http")]
+        use crate::tasks::HttpTask;
 
         use crate::traits::FromJson;
         use crate::types::BoxedTask;
@@ -35,7 +45,10 @@ macro_rules! ModelToTask {
             "DockerTask" => Some(Box::new(
                 DockerTask::from_json($r.serde_string.clone()).unwrap(),
             )),
-            // "HttpTask" => Some(Box::new(HttpTask::from_json($r.serde_string).unwrap())),
+            #[cfg(feature = "http")]
+            "HttpTask" => Some(Box::new(
+                HttpTask::from_json($r.serde_string.clone()).unwrap(),
+            )),
             _ => None,
         };
         $m = task;
@@ -0,0 +1,48 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+// What to do when a task is triggered while it's still running
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OverlapPolicy {
+    Allow,
+    Skip,
+    // Runs once the current one finishes, at most one run waits at a time
+    Queue,
+    // Aborts the running one and starts fresh
+    Replace,
+}
+
+impl Default for OverlapPolicy {
+    fn default() -> Self {
+        OverlapPolicy::Allow
+    }
+}
+
+impl ToString for OverlapPolicy {
+    fn to_string(&self) -> String {
+        match &self {
+            OverlapPolicy::Allow => return String::from("Allow"),
+            OverlapPolicy::Skip => return String::from("Skip"),
+            OverlapPolicy::Queue => return String::from("Queue"),
+            OverlapPolicy::Replace => return String::from("Replace"),
+        }
+    }
+}
+
+impl FromStr for OverlapPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Allow" => Ok(OverlapPolicy::Allow),
+            "Skip" => Ok(OverlapPolicy::Skip),
+            "Queue" => Ok(OverlapPolicy::Queue),
+            "Replace" => Ok(OverlapPolicy::Replace),
+            _ => Err(anyhow::anyhow!(
+                "Unknown overlap policy {}, possible values: Allow, Skip, Queue, Replace",
+                s
+            )),
+        }
+    }
+}
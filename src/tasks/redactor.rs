@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use regex::Regex;
+use tracing::warn;
+
+use crate::models::OutputLine;
+use crate::types::BoxedStream;
+
+// Sits between the output of a task and the TaskWatcher, so nothing after it sees the secrets
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    // Longest first, so a value that contains another one is masked as a whole
+    values: Vec<String>,
+    patterns: Arc<Vec<Regex>>,
+    // Set when the secrets couldn't be loaded, then none of the output is let through
+    withheld: bool,
+}
+
+impl Redactor {
+    pub const MASK: &'static str = "***";
+    pub const WITHHELD: &'static str = "Output withheld, the secrets couldn't be loaded to redact it";
+    // Only this group is masked when a pattern has it, so the text around the value stays readable
+    const VALUE_GROUP: &'static str = "value";
+    // Shorter ones would mask bits of every other word
+    const MIN_VALUE_LENGTH: usize = 4;
+
+    pub fn new<I: IntoIterator<Item = String>>(values: I, patterns: Arc<Vec<Regex>>) -> Self {
+        // Output comes line by line, so the lines of a multiline value are masked one by one
+        let mut values: Vec<String> = values
+            .into_iter()
+            .flat_map(|v| v.lines().map(|l| l.trim().to_string()).collect::<Vec<_>>())
+            .filter(|v| !v.is_empty())
+            .collect();
+        let short = values.iter().filter(|v| v.len() < Self::MIN_VALUE_LENGTH).count();
+        if short > 0 {
+            warn!("{} secret values are shorter than {} bytes, they aren't masked in the output", short, Self::MIN_VALUE_LENGTH);
+            values.retain(|v| v.len() >= Self::MIN_VALUE_LENGTH);
+        }
+        values.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        values.dedup();
+        return Self { values, patterns, withheld: false };
+    }
+    pub fn withholding() -> Self {
+        return Self { values: vec![], patterns: Arc::default(), withheld: true };
+    }
+    pub fn compile_patterns(patterns: &[String]) -> Result<Vec<Regex>, anyhow::Error> {
+        let mut compiled = vec![];
+        for pattern in patterns {
+            match Regex::new(pattern) {
+                Ok(r) => compiled.push(r),
+                Err(e) => return Err(anyhow::anyhow!("Redaction pattern {} is invalid, {}", pattern, e)),
+            }
+        }
+        return Ok(compiled);
+    }
+    pub fn redact(&self, text: &str) -> String {
+        if self.withheld {
+            return Self::MASK.to_string();
+        }
+        let mut redacted = text.to_string();
+        for value in &self.values {
+            if redacted.contains(value.as_str()) {
+                redacted = redacted.replace(value.as_str(), Self::MASK);
+            }
+        }
+        for pattern in self.patterns.iter() {
+            redacted = Self::redact_pattern(pattern, &redacted);
+        }
+        return redacted;
+    }
+    pub fn redact_line(&self, mut line: OutputLine) -> OutputLine {
+        line.line = self.redact(&line.line);
+        return line;
+    }
+    pub fn redact_stream(self, output: BoxedStream) -> BoxedStream {
+        if self.withheld {
+            // Still read to the end, so the task doesn't block on a full pipe
+            let notice = futures::stream::iter(vec![OutputLine::system(Self::WITHHELD.to_string())]);
+            return Box::new(notice.chain(output.filter(|_| futures::future::ready(false))));
+        }
+        if self.values.is_empty() && self.patterns.is_empty() {
+            return output;
+        }
+        return Box::new(output.map(move |line| self.redact_line(line)));
+    }
+    fn redact_pattern(pattern: &Regex, text: &str) -> String {
+        let mut redacted = String::with_capacity(text.len());
+        let mut last = 0;
+        for captures in pattern.captures_iter(text) {
+            let found = match captures.name(Self::VALUE_GROUP) {
+                Some(m) => m,
+                None => captures.get(0).unwrap(),
+            };
+            // Empty matches would put masks between every character
+            if found.as_str().is_empty() {
+                continue;
+            }
+            redacted.push_str(&text[last..found.start()]);
+            redacted.push_str(Self::MASK);
+            last = found.end();
+        }
+        redacted.push_str(&text[last..]);
+        return redacted;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_values_and_patterns() {
+        let patterns = vec![
+            String::from(r"(?i)bearer\s+(?P<value>[a-z0-9._~+/-]+=*)"),
+            String::from(r"sx_[0-9a-f]{64}"),
+        ];
+        let patterns = Arc::new(Redactor::compile_patterns(&patterns).unwrap());
+        let values = vec![
+            String::from("hunter2"),
+            String::from("hunter2 and more"),
+            String::from("-----BEGIN KEY-----\nAbCdEf\n-----END KEY-----\n"),
+            String::new(),
+            String::from("a"),
+        ];
+        let redactor = Redactor::new(values, patterns);
+        assert_eq!(redactor.redact("password is hunter2, hunter2 and more"), "password is ***, ***");
+        assert_eq!(redactor.redact("AbCdEf"), "***");
+        let line = format!("curl -H 'Authorization: Bearer abc.def-123=' -H 'X-API-Key: sx_{}'", "0f".repeat(32));
+        assert_eq!(redactor.redact(&line), "curl -H 'Authorization: Bearer ***' -H 'X-API-Key: ***'");
+        assert_eq!(redactor.redact("nothing to hide, a value"), "nothing to hide, a value");
+        assert!(Redactor::compile_patterns(&[String::from("(unclosed")]).is_err());
+    }
+    #[tokio::test]
+    async fn withholds_everything() {
+        let output: BoxedStream = Box::new(futures::stream::iter(vec![OutputLine::stdout(String::from("hunter2"))]));
+        let lines = Redactor::withholding().redact_stream(output).map(|l| l.line).collect::<Vec<_>>().await;
+        assert_eq!(lines, vec![Redactor::WITHHELD]);
+        assert_eq!(Redactor::withholding().redact("hunter2"), Redactor::MASK);
+    }
+}
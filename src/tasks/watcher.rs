@@ -1,7 +1,7 @@
 use tokio_stream::StreamExt;
 use tracing::{info, instrument, warn};
 
-use crate::models::{ExecutionReport, OutputModel};
+use crate::models::{ExecutionReport, ExitStatus, OutputLine, OutputModel};
 
 use super::TaskWatcherMessage;
 
@@ -17,14 +17,18 @@ impl TaskWatcher {
                 match message {
                     TaskWatcherMessage::WatchExecution {
                         task_id,
+                        execution_id,
                         exec_process,
+                        redactor,
                         output_resp,
                         resp,
                     } => match exec_process {
-                        Ok(mut stream) => {
+                        Ok(execution) => {
+                            let mut stream = redactor.clone().redact_stream(execution.output);
                             let mut output_vec = vec![];
-                            while let Some(output) = stream.next().await {
-                                match output_resp.send(OutputModel::new(task_id, output.clone())) {
+                            while let Some(mut output) = stream.next().await {
+                                output.sequence = output_vec.len() as i64;
+                                match output_resp.send(OutputModel::new(task_id, execution_id, output.clone())) {
                                     Err(_) => {
                                         warn!("Output received for task: {}, but nothing listens for it.", task_id);
                                     },
@@ -32,12 +36,31 @@ impl TaskWatcher {
                                 };
                                 output_vec.push(output);
                             }
-                            let exec_report = ExecutionReport::new(task_id, true, output_vec);
+                            // Output is finished, now the task can tell us how it ended
+                            let mut status = execution.completion.await.unwrap_or_else(|_| {
+                                warn!("Task {} finished without reporting its exit status.", task_id);
+                                ExitStatus::unknown()
+                            });
+                            // Response headers are stored and sent along with the output
+                            if let Some(headers) = status.http_headers.as_mut() {
+                                for value in headers.values_mut() {
+                                    *value = redactor.redact(value);
+                                }
+                            }
+                            if let Some(reason) = status.aborted {
+                                let mut line = redactor.redact_line(OutputLine::system(reason.describe()));
+                                line.sequence = output_vec.len() as i64;
+                                let _ = output_resp.send(OutputModel::new(task_id, execution_id, line.clone()));
+                                output_vec.push(line);
+                            }
+                            let exec_report = ExecutionReport::new(execution_id, task_id, status, output_vec);
                             // If this fails, output isn't really a problem...
                             let _ = resp.send(exec_report);
                         }
                         Err(e) => {
-                            let report = ExecutionReport::new_string_output(uuid::Uuid::new_v4(), task_id, chrono::Utc::now().naive_utc(), false, e.to_string());
+                            // Errors of a run can quote its command, secrets and all
+                            let output = vec![redactor.redact_line(OutputLine::system(e.to_string()))];
+                            let report = ExecutionReport::new(execution_id, task_id, ExitStatus::unknown(), output);
                             // Same reason as above
                             let _ = resp.send(report);
                         }
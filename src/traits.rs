@@ -1,17 +1,19 @@
-use crate::models::TaskError;
+use crate::models::{ExecutionContext, TaskError};
 use std::fmt::Debug;
 
-pub use super::types::BoxedStream;
+pub use super::types::{BoxedStream, TaskExecution};
 use async_trait::async_trait;
 use serde_json::Value;
 use uuid::Uuid;
 
 #[async_trait]
 pub trait Executable: Debug + ToString {
-    async fn exec(&mut self) -> Result<BoxedStream, TaskError>;
+    async fn exec(&mut self) -> Result<TaskExecution, TaskError>;
     async fn abort(&mut self) -> bool;
     fn get_id(&self) -> uuid::Uuid;
     fn get_type(&self) -> String;
+    // Called right before exec, tasks that can pass it on to what they run should do so
+    fn set_context(&mut self, _context: &ExecutionContext) {}
 }
 
 pub trait FromJson {
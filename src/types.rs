@@ -1,10 +1,16 @@
 use crate::{
-    db::DBMessage, executor::ExecutorMessage, models::OutputModel, reactor::ReactorMessage,
-    server::ServerMessage, tasks::TaskWatcherMessage, traits::Executable,
+    db::DBMessage,
+    executor::ExecutorMessage,
+    models::{ExecutionProcess, ExitStatus, OutputLine, OutputModel},
+    reactor::ReactorMessage,
+    server::ServerMessage,
+    tasks::TaskWatcherMessage,
+    traits::Executable,
 };
 use futures::Stream;
 
-pub type BoxedStream = Box<dyn Stream<Item = String> + Unpin + Send>;
+pub type BoxedStream = Box<dyn Stream<Item = OutputLine> + Unpin + Send>;
+pub type CompletionReceiver = tokio::sync::oneshot::Receiver<ExitStatus>;
 pub type ExecutableTask = dyn Executable + Send + Sync;
 pub type BoxedTask = Box<ExecutableTask>;
 pub type OneShotMessageResponse<T> = tokio::sync::oneshot::Sender<T>;
@@ -19,6 +25,25 @@ pub type TaskWatcherSender = tokio::sync::mpsc::Sender<TaskWatcherMessage>;
 pub type ServerReceiver = tokio::sync::mpsc::Receiver<ServerMessage>;
 // pub type BoxedTaskQueue = Arc<Mutex<VecDeque<BoxedTask>>>;
 
+// What a task hands back when it starts, its output and a receiver that
+// resolves with the exit status once the task is finished.
+pub struct TaskExecution {
+    pub output: BoxedStream,
+    pub completion: CompletionReceiver,
+    // Set by the tasks that run outside of StewardX, so the run can be found after a restart
+    pub process: Option<ExecutionProcess>,
+}
+
+impl TaskExecution {
+    pub fn new(output: BoxedStream, completion: CompletionReceiver) -> Self {
+        Self { output, completion, process: None }
+    }
+    pub fn with_process(mut self, process: ExecutionProcess) -> Self {
+        self.process = Some(process);
+        self
+    }
+}
+
 #[macro_export]
 macro_rules! now {
     () => {